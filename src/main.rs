@@ -3,13 +3,14 @@ use std::fs::File;
 use log::{error, info, LevelFilter};
 use simplelog::{ColorChoice, CombinedLogger, Config, TermLogger, TerminalMode, WriteLogger};
 
-use crate::parser::{
+use slr1_parser::parser::{
+    export::augmented_grammar_dump,
     lexical_analysis::lexical_analysis,
-    syntax_analysis::{get_first, get_follow, get_slr1_table, slr1_analysis_with_log, Grammar},
+    syntax_analysis::{
+        get_first_sorted, get_follow_sorted, get_slr1_table, slr1_analysis_with_log, Grammar,
+    },
 };
 
-mod parser;
-
 fn main() {
     CombinedLogger::init(vec![
         TermLogger::new(
@@ -44,35 +45,22 @@ fn main() {
         Err(e) => panic!("grammar is not valid: {}", e),
     }
     info!("grammar:");
-    info!("s: {}", g.s);
-    info!("v: {:?}", g.v);
-    info!("t: {:?}", g.t);
-    info!("p:");
-    for p in g.p.iter() {
-        info!(
-            "  \"{}\" -> {}",
-            p.left,
-            p.right
-                .iter()
-                .map(|s| format!("\"{}\"", s))
-                .collect::<Vec<_>>()
-                .join(" ")
-        );
+    for line in g.to_string().lines() {
+        info!("{}", line);
     }
 
-    let mut first = get_first(&g);
-    first.iter_mut().for_each(|(_k, v)| {
-        v.sort();
-    });
+    info!("augmented grammar:");
+    for line in augmented_grammar_dump(&g).lines() {
+        info!("{}", line);
+    }
+
+    let first = get_first_sorted(&g);
     info!("first:");
     for (k, v) in first.iter() {
         info!("FIRST(\"{}\") = {:?}", k, v);
     }
 
-    let mut follow = get_follow(&g);
-    follow.iter_mut().for_each(|(_k, v)| {
-        v.sort();
-    });
+    let follow = get_follow_sorted(&g);
     info!("follow:");
     for (k, v) in follow.iter() {
         info!("FOLLOW(\"{}\") = {:?}", k, v);
@@ -88,26 +76,21 @@ fn main() {
     info!("action:");
     let mut buffer = String::new();
     buffer.push_str(&format!("{:<6}", ""));
-    for t in &g.t {
+    let terminals = g.table_terminals();
+    for t in &terminals {
         buffer.push_str(&format!("{:<6}", t));
     }
-    buffer.push_str(&format!("{:<6}", "#"));
     info!("{}", buffer);
     buffer.clear();
     for (i, map) in action.iter().enumerate() {
         buffer.push_str(&format!("{:<6}", i));
-        for t in &g.t {
-            if let Some(act) = map.get(t) {
+        for t in &terminals {
+            if let Some(act) = map.get(*t) {
                 buffer.push_str(&format!("{:<6}", act));
             } else {
                 buffer.push_str(&format!("{:<6}", ""));
             }
         }
-        if let Some(act) = map.get("#") {
-            buffer.push_str(&format!("{:<6}", act));
-        } else {
-            buffer.push_str(&format!("{:<6}", ""));
-        }
         info!("{}", buffer);
         buffer.clear();
     }
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use super::{
+    error::{LexicalError, SyntaxError},
+    lexical_analysis::{Lexer, LexerConfig, Position, Token, TokenType},
+    syntax_analysis::{slr1_analysis_with_options, Action, AnalysisOptions, Grammar},
+};
+
+/// 诊断的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// 诊断在源代码中的范围，`end` 为范围之后第一个字符的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// 单词所占的范围
+    pub fn of_token(token: &Token) -> Self {
+        let mut end = token.position.clone();
+        end.column += token.token_value.chars().count();
+        Self {
+            start: token.position.clone(),
+            end,
+        }
+    }
+
+    /// 位于 `position` 处的空范围
+    pub fn at(position: &Position) -> Self {
+        Self {
+            start: position.clone(),
+            end: position.clone(),
+        }
+    }
+}
+
+/// # 词法错误与语法错误的统一表示
+///
+/// 形式与LSP的诊断一致，可以直接转换后发布给编辑器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// 出错的范围
+    pub span: Span,
+    /// 出错时可以接受的终结符，词法错误为空
+    pub expected: Vec<String>,
+    /// 出错处的单词，在输入末尾出错时为 `None`
+    pub found: Option<String>,
+}
+
+impl Diagnostic {
+    /// 由识别失败的单词生成诊断，不是识别失败的单词时返回 `None`
+    pub fn from_error_token(token: &Token) -> Option<Self> {
+        let TokenType::Error(e) = &token.token_type else {
+            return None;
+        };
+        Some(Self {
+            severity: Severity::Error,
+            message: e.message.clone(),
+            span: Span::of_token(token),
+            expected: vec![],
+            found: Some(token.token_value.clone()),
+        })
+    }
+
+    /// 由预处理等阶段的词法错误生成诊断，范围为出错位置处的空范围，没有位置时为输入的开头
+    pub fn from_lexical_error(e: &LexicalError) -> Self {
+        let position = Position {
+            file: None,
+            line: e.line.unwrap_or(1),
+            column: e.column.unwrap_or(1),
+        };
+        Self {
+            severity: Severity::Error,
+            message: e.message.clone(),
+            span: Span::at(&position),
+            expected: vec![],
+            found: None,
+        }
+    }
+
+    /// # 由语法错误生成诊断
+    ///
+    /// `tokens` 为分析的输入，错误没有对应的token(在输入末尾出错)时范围为最后一个token之后的空范围
+    pub fn from_syntax_error(e: &SyntaxError, tokens: &[Token]) -> Self {
+        let token = e.token_index.and_then(|i| tokens.get(i));
+        let span = match (token, tokens.last()) {
+            (Some(token), _) => Span::of_token(token),
+            (None, Some(last)) => Span::at(&Span::of_token(last).end),
+            (None, None) => Span::at(&Position {
+                file: None,
+                line: 1,
+                column: 1,
+            }),
+        };
+        Self {
+            severity: Severity::Error,
+            message: e.message.clone(),
+            span,
+            expected: e.expected.clone(),
+            found: token.map(|token| token.token_value.clone()),
+        }
+    }
+}
+
+/// # 词法分析并收集诊断
+///
+/// 返回识别成功的token与词法错误的诊断，预处理失败时没有token
+pub fn lexical_diagnostics(input: &str, config: &LexerConfig) -> (Vec<Token>, Vec<Diagnostic>) {
+    let mut tokens = vec![];
+    let mut diagnostics = vec![];
+    for token in Lexer::with_config(input.to_string(), config) {
+        match token {
+            Ok(token) => match Diagnostic::from_error_token(&token) {
+                Some(diagnostic) => diagnostics.push(diagnostic),
+                None => tokens.push(token),
+            },
+            Err(e) => diagnostics.push(Diagnostic::from_lexical_error(&e)),
+        }
+    }
+    (tokens, diagnostics)
+}
+
+/// # 对源代码进行词法分析与SLR(1)分析，收集所有诊断
+///
+/// 词法错误的诊断在前，语法错误的诊断在后；语法分析只使用识别成功的token，
+/// 按照 `options` 进行错误恢复时可以得到多个语法错误
+#[allow(non_snake_case)]
+pub fn diagnose(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    source: &str,
+    options: &AnalysisOptions,
+) -> Vec<Diagnostic> {
+    let (tokens, mut diagnostics) = lexical_diagnostics(source, &LexerConfig::default());
+    let (_, errors) = slr1_analysis_with_options(g, ACTION, GOTO, tokens.clone(), options);
+    diagnostics.extend(
+        errors
+            .iter()
+            .map(|e| Diagnostic::from_syntax_error(e, &tokens)),
+    );
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::syntax_analysis::get_slr1_table;
+
+    const GRAMMAR: &str = "E -> E + T | T\nT -> T * F | F\nF -> ( E ) | id\n";
+
+    #[test]
+    fn test_syntax_diagnostic() {
+        let g = Grammar::from_bnf(GRAMMAR).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        let diagnostics = diagnose(&g, &action, &goto, "a +\n  * b", &Default::default());
+        assert_eq!(diagnostics.len(), 1);
+        let d = &diagnostics[0];
+        assert_eq!(d.severity, Severity::Error);
+        assert_eq!(d.found, Some("*".to_string()));
+        assert_eq!(d.expected, vec!["(", "id"]);
+        assert_eq!((d.span.start.line, d.span.start.column), (2, 3));
+        assert_eq!((d.span.end.line, d.span.end.column), (2, 4));
+
+        // 在输入末尾出错
+        let diagnostics = diagnose(&g, &action, &goto, "(abc + d", &Default::default());
+        let d = &diagnostics[0];
+        assert_eq!(d.found, None);
+        assert_eq!(d.expected, vec!["+", ")"]);
+        assert_eq!(
+            d.span,
+            Span::at(&Position {
+                file: None,
+                line: 1,
+                column: 9
+            })
+        );
+    }
+
+    #[test]
+    fn test_lexical_diagnostic() {
+        let g = Grammar::from_bnf(GRAMMAR).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        // 词法错误在前，识别失败的单词不参与语法分析
+        let diagnostics = diagnose(&g, &action, &goto, "a + 1b", &Default::default());
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "Invalid identifier");
+        assert_eq!(diagnostics[0].found, Some("1b".to_string()));
+        assert_eq!(diagnostics[0].span.start.column, 5);
+        assert_eq!(diagnostics[0].span.end.column, 7);
+        assert_eq!(diagnostics[1].found, None);
+
+        let (tokens, diagnostics) = lexical_diagnostics("a /* b", &LexerConfig::default());
+        assert!(tokens.is_empty());
+        assert_eq!(diagnostics[0].message, "multiline comment not closed");
+        assert_eq!(
+            diagnostics[0].span,
+            Span::at(&Position {
+                file: None,
+                line: 1,
+                column: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+
+use log::{debug, error};
+
+use super::{
+    error::SyntaxError,
+    lexical_analysis::Token,
+    syntax_analysis::{Grammar, EPSILON},
+};
+
+/// Earley项目
+///
+/// 项目的形式为 [A -> α·β, j]，`origin` 为识别A开始时所在的输入位置j
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct EarleyItem {
+    /// 产生式序号
+    production: usize,
+    /// ·的位置，在对应坐标符号的左边
+    dot: usize,
+    /// 项目开始识别的位置
+    origin: usize,
+}
+
+/// # 使用Earley算法分析token序列
+///
+/// 能够分析任意上下文无关文法，包括有冲突或二义性而无法构造SLR(1)、LALR(1)、LR(1)分析表的文法，
+/// 因此可以在分析表存在冲突时作为后备
+///
+/// 对输入的每个位置i维护项目集 S(i)，反复执行以下操作直到不再变化：
+/// - 预测：[A -> α·Bβ, j] 加入B的所有产生式 [B -> ·γ, i]，B能推导出ε时还加入 [A -> αB·β, j]
+/// - 扫描：[A -> α·aβ, j] 在输入的第i个符号为a时将 [A -> αa·β, j] 加入 S(i+1)
+/// - 完成：[B -> γ·, j] 将 S(j) 中的 [A -> α·Bβ, k] 推进为 [A -> αB·β, k]
+///
+/// S(n) 中存在开始符号的完整项目 [S -> γ·, 0] 时接受
+/// ## 输出
+/// - 输入是否为文法的句子，二义的句子同样被接受
+/// - 文法不合法时返回错误
+pub fn earley_parse(g: &Grammar, tokens: Vec<Token>) -> Result<bool, SyntaxError> {
+    g.validate().map_err(|e| SyntaxError::new(&e.to_string()))?;
+
+    let nullable = g.nullable();
+    // 去掉ε后的产生式右部
+    let rights =
+        g.p.iter()
+            .map(|p| {
+                p.right
+                    .iter()
+                    .map(|s| s.as_str())
+                    .filter(|s| *s != EPSILON)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+    let input = tokens
+        .iter()
+        .map(|token| g.terminal_of(token))
+        .collect::<Vec<_>>();
+    debug!("earley input: {:?}", input);
+
+    let mut sets: Vec<Vec<EarleyItem>> = vec![vec![]; input.len() + 1];
+    let mut seen: Vec<HashSet<EarleyItem>> = vec![HashSet::new(); input.len() + 1];
+    let mut add = |sets: &mut Vec<Vec<EarleyItem>>, i: usize, item: EarleyItem| {
+        if seen[i].insert(item) {
+            sets[i].push(item);
+        }
+    };
+
+    for (k, p) in g.p.iter().enumerate() {
+        if p.left == g.s {
+            add(
+                &mut sets,
+                0,
+                EarleyItem {
+                    production: k,
+                    dot: 0,
+                    origin: 0,
+                },
+            );
+        }
+    }
+
+    for i in 0..=input.len() {
+        let mut j = 0;
+        while j < sets[i].len() {
+            let item = sets[i][j];
+            j += 1;
+            let right = &rights[item.production];
+            let advanced = EarleyItem {
+                dot: item.dot + 1,
+                ..item
+            };
+
+            // 完成
+            let Some(symbol) = right.get(item.dot) else {
+                let left = g.p[item.production].left.as_str();
+                let waiting = sets[item.origin]
+                    .iter()
+                    .filter(|x| rights[x.production].get(x.dot) == Some(&left))
+                    .copied()
+                    .collect::<Vec<_>>();
+                for x in waiting {
+                    add(
+                        &mut sets,
+                        i,
+                        EarleyItem {
+                            dot: x.dot + 1,
+                            ..x
+                        },
+                    );
+                }
+                continue;
+            };
+
+            // 预测
+            if g.v.iter().any(|v| v == symbol) {
+                for (k, p) in g.p.iter().enumerate() {
+                    if p.left == *symbol {
+                        add(
+                            &mut sets,
+                            i,
+                            EarleyItem {
+                                production: k,
+                                dot: 0,
+                                origin: i,
+                            },
+                        );
+                    }
+                }
+                if nullable.contains(*symbol) {
+                    add(&mut sets, i, advanced);
+                }
+            }
+            // 扫描
+            else if input.get(i).is_some_and(|t| t == symbol) {
+                add(&mut sets, i + 1, advanced);
+            }
+        }
+
+        if i < input.len() && sets[i + 1].is_empty() {
+            error!("earley: 无法识别第{}个输入符号 {}", i + 1, input[i]);
+            return Ok(false);
+        }
+    }
+
+    let accepted = sets[input.len()].iter().any(|item| {
+        item.origin == 0
+            && g.p[item.production].left == g.s
+            && item.dot == rights[item.production].len()
+    });
+    Ok(accepted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        grammar_analysis::count_parse_trees,
+        lexical_analysis::lexical_analysis,
+        syntax_analysis::{get_slr1_table, slr1_analysis},
+    };
+
+    fn parse(g: &Grammar, input: &str) -> bool {
+        let tokens = lexical_analysis(input.to_string()).unwrap().0;
+        earley_parse(g, tokens).unwrap()
+    }
+
+    #[test]
+    fn test_earley_parse() {
+        // 二义文法，SLR(1)分析表中的冲突按覆盖处理，丢失了一部分句子
+        let g = Grammar::from_bnf("S -> id S id | S S | id\n").unwrap();
+        assert!(count_parse_trees(&g, &["id", "id", "id"].map(String::from)) > 1);
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let tokens = lexical_analysis("a b c".to_string()).unwrap().0;
+        assert!(!slr1_analysis(&g, &action, &goto, tokens));
+        assert!(parse(&g, "a b c"));
+        assert!(parse(&g, "a b"));
+        assert!(parse(&g, "a b c d e"));
+        assert!(parse(&g, "a"));
+        assert!(!parse(&g, ""));
+
+        let g = Grammar::from_bnf("E -> E + E | E * E | ( E ) | id\n").unwrap();
+        assert!(parse(&g, "(a + b) * c + d"));
+        assert!(!parse(&g, "a + * b"));
+        assert!(!parse(&g, "a +"));
+
+        // ε 产生式与可空的非终结符
+        let g = Grammar::from_bnf("S -> A A ;\nA -> id | ε\n").unwrap();
+        assert!(parse(&g, ";"));
+        assert!(parse(&g, "a ;"));
+        assert!(parse(&g, "a b ;"));
+        assert!(!parse(&g, "a b c ;"));
+
+        // 不合法的文法
+        let mut g = Grammar::from_bnf("S -> a\n").unwrap();
+        g.s = "T".to_string();
+        assert!(earley_parse(&g, vec![]).is_err());
+    }
+}
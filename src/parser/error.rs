@@ -1,21 +1,41 @@
 use std::{error::Error, fmt::Display};
 
-#[derive(Debug)]
+use super::syntax_analysis::Item;
+
+#[derive(Debug, Clone)]
 pub struct LexicalError {
     pub message: String,
+    /// 出错位置的行号，从1开始
+    pub line: Option<usize>,
+    /// 出错位置的列号，从1开始
+    pub column: Option<usize>,
 }
 
 impl LexicalError {
     pub fn new(message: &str) -> Self {
         Self {
             message: message.to_string(),
+            line: None,
+            column: None,
+        }
+    }
+
+    /// 带有出错位置的错误
+    pub fn at(message: &str, line: usize, column: usize) -> Self {
+        Self {
+            message: message.to_string(),
+            line: Some(line),
+            column: Some(column),
         }
     }
 }
 
 impl Display for LexicalError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{} at {}:{}", self.message, line, column),
+            _ => write!(f, "{}", self.message),
+        }
     }
 }
 
@@ -28,12 +48,31 @@ impl Error for LexicalError {
 #[derive(Debug)]
 pub struct SyntaxError {
     pub message: String,
+    /// 出错时可以接受的终结符
+    pub expected: Vec<String>,
+    /// 出错时正在分析的产生式，即出错状态中圆点不在最左端的项目
+    pub in_progress: Vec<Item>,
+    /// 出错时的输入token在输入中的下标，在输入末尾出错或错误与输入无关时为 `None`
+    pub token_index: Option<usize>,
 }
 
 impl SyntaxError {
     pub fn new(message: &str) -> Self {
         Self {
             message: message.to_string(),
+            expected: vec![],
+            in_progress: vec![],
+            token_index: None,
+        }
+    }
+
+    /// 带有可以接受的终结符的错误
+    pub fn with_expected(message: &str, expected: Vec<String>) -> Self {
+        Self {
+            message: message.to_string(),
+            expected,
+            in_progress: vec![],
+            token_index: None,
         }
     }
 }
@@ -74,3 +113,54 @@ impl Error for GrammarError {
         &self.message
     }
 }
+
+/// # 分析过程中可能出现的错误
+///
+/// 统一了词法分析、文法与语法分析的错误，各错误都可以通过 `?` 转换为该类型
+#[derive(Debug)]
+pub enum ParserError {
+    /// 文法无法读取或不合法
+    Grammar(GrammarError),
+    /// 源代码词法分析失败
+    Lexical(LexicalError),
+    /// 无法为文法构造分析器
+    Syntax(SyntaxError),
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Grammar(e) => write!(f, "grammar error: {}", e),
+            Self::Lexical(e) => write!(f, "lexical error: {}", e),
+            Self::Syntax(e) => write!(f, "syntax error: {}", e),
+        }
+    }
+}
+
+impl Error for ParserError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Grammar(e) => Some(e),
+            Self::Lexical(e) => Some(e),
+            Self::Syntax(e) => Some(e),
+        }
+    }
+}
+
+impl From<LexicalError> for ParserError {
+    fn from(e: LexicalError) -> Self {
+        Self::Lexical(e)
+    }
+}
+
+impl From<GrammarError> for ParserError {
+    fn from(e: GrammarError) -> Self {
+        Self::Grammar(e)
+    }
+}
+
+impl From<SyntaxError> for ParserError {
+    fn from(e: SyntaxError) -> Self {
+        Self::Syntax(e)
+    }
+}
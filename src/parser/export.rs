@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+
+use super::parse_tree::ParseNode;
+use super::syntax_analysis::{
+    augment, get_lr0_collection, goto, items_eq, Action, Grammar, EPSILON,
+};
+
+impl Grammar {
+    /// # 将文法导出为 yacc 的 `.y` 格式
+    ///
+    /// - 由字母、数字和下划线组成的终结符以 `%token` 声明，单个字符的终结符写作字符常量，如 `'+'`，
+    ///   其余终结符写作字符串常量，如 `"<="`
+    /// - 非终结符名中的 `'` 替换为 `_prime`，其余非法字符替换为 `_`
+    /// - ε 产生式为空的候选式
+    pub fn to_yacc(&self) -> String {
+        let tokens = self
+            .t
+            .iter()
+            .filter(|t| *t != EPSILON && is_identifier(t))
+            .map(|t| t.as_str())
+            .collect::<Vec<_>>();
+
+        let mut y = String::new();
+        if !tokens.is_empty() {
+            y.push_str(&format!("%token {}\n", tokens.join(" ")));
+        }
+        y.push_str(&format!("%start {}\n", yacc_nonterminal(&self.s)));
+        y.push_str("\n%%\n");
+
+        for v in self.v.iter() {
+            let alternatives = self
+                .p
+                .iter()
+                .filter(|p| p.left == *v)
+                .map(|p| {
+                    let right = p
+                        .right
+                        .iter()
+                        .filter(|s| *s != EPSILON)
+                        .map(|s| match self.v.contains(s) {
+                            true => yacc_nonterminal(s),
+                            false => yacc_terminal(s),
+                        })
+                        .collect::<Vec<_>>();
+                    match right.is_empty() {
+                        true => "/* empty */".to_string(),
+                        false => right.join(" "),
+                    }
+                })
+                .collect::<Vec<_>>();
+            if alternatives.is_empty() {
+                continue;
+            }
+
+            y.push_str(&format!("\n{}\n", yacc_nonterminal(v)));
+            for (i, alternative) in alternatives.iter().enumerate() {
+                let sep = if i == 0 { ':' } else { '|' };
+                y.push_str(&format!("    {} {}\n", sep, alternative));
+            }
+            y.push_str("    ;\n");
+        }
+
+        y.push_str("\n%%\n");
+        y
+    }
+}
+
+/// 是否为 yacc 中合法的标识符
+fn is_identifier(s: &str) -> bool {
+    s.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 非终结符在 yacc 中的名字
+fn yacc_nonterminal(v: &str) -> String {
+    v.replace('\'', "_prime")
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+/// 终结符在 yacc 中的写法
+fn yacc_terminal(t: &str) -> String {
+    if is_identifier(t) {
+        t.to_string()
+    } else if t.chars().count() == 1 {
+        format!("'{}'", t.replace('\\', "\\\\").replace('\'', "\\'"))
+    } else {
+        format!("\"{}\"", t.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// # 导出拓广文法G'的产生式
+///
+/// 每行为一个产生式及其序号，序号即分析表中规约动作 rj 的 j
+///
+/// 拓广时添加的 S' -> S 不是输入文法中的产生式，在行尾以注释标明
+pub fn augmented_grammar_dump(g: &Grammar) -> String {
+    let outreach_g = augment(g);
+
+    let mut dump = String::new();
+    dump.push_str(&format!("s: {}\n", outreach_g.s));
+    dump.push_str("p:\n");
+    for (i, p) in outreach_g.p.iter().enumerate() {
+        let right = match p.right.is_empty() {
+            true => EPSILON.to_string(),
+            false => p.right.join(" "),
+        };
+        dump.push_str(&format!("  {}: {} -> {}", i, p.left, right));
+        // 拓广时添加的产生式位于产生式集的末尾
+        if i == outreach_g.p.len() - 1 {
+            dump.push_str("  # synthetic: added by augmentation, not part of the input grammar");
+        }
+        dump.push('\n');
+    }
+    dump
+}
+
+/// # 将拓广文法G'的LR(0)自动机导出为 Graphviz DOT 格式
+///
+/// 每个项目集(状态)为一个节点，节点标签为状态编号与其中的所有项目
+///
+/// 每条边为一次状态转移，边的标签为转移时接受的终结符或非终结符
+pub fn automaton_to_dot(g: &Grammar) -> String {
+    let outreach_g = augment(g);
+    let lr0_items = get_lr0_collection(&outreach_g);
+
+    // 终结符集和非终结符集
+    let v_t = outreach_g
+        .v
+        .iter()
+        .chain(outreach_g.t.iter())
+        .collect::<Vec<_>>();
+
+    let mut dot = String::new();
+    dot.push_str("digraph LR0 {\n");
+    dot.push_str("    rankdir=LR;\n");
+    dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+    for (i, items) in lr0_items.iter().enumerate() {
+        let mut label = format!("I{}", i);
+        for item in items.iter() {
+            label.push_str("\\l");
+            label.push_str(&escape(&item.to_string()));
+        }
+        label.push_str("\\l");
+        dot.push_str(&format!("    I{} [label=\"{}\"];\n", i, label));
+    }
+
+    for (i, items) in lr0_items.iter().enumerate() {
+        for x in v_t.iter() {
+            let to_items = goto(items, x, &outreach_g);
+            if to_items.is_empty() {
+                continue;
+            }
+            if let Some(j) = lr0_items.iter().position(|c| items_eq(&to_items, c)) {
+                dot.push_str(&format!(
+                    "    I{} -> I{} [label=\"{}\"];\n",
+                    i,
+                    j,
+                    escape(x)
+                ));
+            }
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// # 将ACTION表和GOTO表导出为CSV
+///
+/// 表头为 `state`、终结符与 `#`(ACTION表的列，顺序见 [`Grammar::table_terminals`])和非终结符(GOTO表的列)，
+/// 每个状态一行，空的单元格留空
+///
+/// 含有 `,`、`"` 或换行的单元格用双引号括起
+#[allow(non_snake_case)]
+pub fn tables_to_csv(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+) -> String {
+    let terminals = g.table_terminals();
+
+    let mut csv = String::new();
+    let header = ["state"]
+        .into_iter()
+        .chain(terminals.iter().copied())
+        .chain(g.v.iter().map(|v| v.as_str()))
+        .map(csv_field)
+        .collect::<Vec<_>>();
+    csv.push_str(&header.join(","));
+    csv.push('\n');
+
+    for (i, (action, goto)) in ACTION.iter().zip(GOTO.iter()).enumerate() {
+        let mut row = vec![i.to_string()];
+        for t in terminals.iter() {
+            row.push(csv_field(
+                &action.get(*t).map_or(String::new(), |a| a.to_string()),
+            ));
+        }
+        for v in g.v.iter() {
+            row.push(csv_field(goto.get(v).map_or("", |j| j.as_str())));
+        }
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// CSV中的单元格，必要时以双引号括起
+fn csv_field(s: &str) -> String {
+    match s.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+/// # 将语法树导出为 Graphviz DOT 格式
+///
+/// 非终结符为内部节点(椭圆)，终结符为叶子节点(方框)，终结符节点的标签为终结符及其对应的token的值
+///
+/// 由 ε 产生式规约得到的非终结符节点没有子节点，为其添加一个 ε 叶子节点
+pub fn tree_to_dot(node: &ParseNode) -> String {
+    let mut dot = String::new();
+    dot.push_str("digraph ParseTree {\n");
+    dot.push_str("    node [fontname=\"monospace\"];\n");
+    let mut count = 0;
+    tree_node_to_dot(node, &mut dot, &mut count);
+    dot.push_str("}\n");
+    dot
+}
+
+/// 输出节点及其子树，`count` 为已输出的节点数，返回节点的编号
+fn tree_node_to_dot(node: &ParseNode, dot: &mut String, count: &mut usize) -> usize {
+    let id = *count;
+    *count += 1;
+
+    if node.is_leaf() {
+        let label = match &node.value {
+            Some(value) if *value != node.symbol => {
+                format!("{}\\n{}", escape(&node.symbol), escape(value))
+            }
+            _ => escape(&node.symbol),
+        };
+        dot.push_str(&format!("    n{} [label=\"{}\", shape=box];\n", id, label));
+        return id;
+    }
+
+    dot.push_str(&format!(
+        "    n{} [label=\"{}\"];\n",
+        id,
+        escape(&node.symbol)
+    ));
+    if node.children.is_empty() {
+        let epsilon = *count;
+        *count += 1;
+        dot.push_str(&format!(
+            "    n{} [label=\"{}\", shape=plaintext];\n",
+            epsilon, EPSILON
+        ));
+        dot.push_str(&format!("    n{} -> n{};\n", id, epsilon));
+    }
+    for child in node.children.iter() {
+        let child_id = tree_node_to_dot(child, dot, count);
+        dot.push_str(&format!("    n{} -> n{};\n", id, child_id));
+    }
+    id
+}
+
+/// 转义 DOT 字符串中的特殊字符
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        lexical_analysis::lexical_analysis,
+        syntax_analysis::{get_slr1_table, Parser, EXPR_GRAMMAR_YML},
+    };
+
+    #[test]
+    fn test_augmented_grammar_dump() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let dump = augmented_grammar_dump(&g);
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "s: E'");
+        assert_eq!(lines[2], "  0: E -> E + T");
+        assert_eq!(
+            lines[8],
+            "  6: E' -> E  # synthetic: added by augmentation, not part of the input grammar"
+        );
+        // 只有拓广时添加的产生式被标明
+        assert_eq!(dump.matches("synthetic").count(), 1);
+    }
+
+    #[test]
+    fn test_tables_to_csv() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let csv = tables_to_csv(&g, &action, &goto);
+
+        let rows = csv
+            .lines()
+            .map(|line| line.split(',').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows[0],
+            vec!["state", "+", "*", "(", ")", "id", "#", "E", "T", "F"]
+        );
+        assert_eq!(rows.len(), action.len() + 1);
+        assert!(rows.iter().all(|row| row.len() == rows[0].len()));
+
+        let cell = |state: usize, symbol: &str| {
+            let column = rows[0].iter().position(|s| *s == symbol).unwrap();
+            rows[state + 1][column]
+        };
+        assert_eq!(cell(1, "#"), "acc");
+        assert_eq!(cell(0, "id"), action[0]["id"].to_string());
+        assert_eq!(cell(0, "E"), "1");
+        assert_eq!(cell(0, "+"), "");
+
+        // 含有逗号的终结符被引号括起
+        let g = Grammar::from_bnf("L -> L , id | id\n").unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let csv = tables_to_csv(&g, &action, &goto);
+        assert!(csv.starts_with("state,\",\",id,#,L\n"));
+    }
+
+    #[test]
+    fn test_tables_to_csv_column_order() {
+        let mut g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        g.column_order = ["id", "#", "(", "unknown"].map(String::from).to_vec();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let csv = tables_to_csv(&g, &action, &goto);
+
+        let header = csv.lines().next().unwrap().split(',').collect::<Vec<_>>();
+        assert_eq!(
+            header,
+            vec!["state", "id", "#", "(", "+", "*", ")", "E", "T", "F"]
+        );
+        let row = csv.lines().nth(1).unwrap().split(',').collect::<Vec<_>>();
+        assert_eq!(row[1], action[0]["id"].to_string());
+
+        // 列的顺序随文法一起保存
+        let g2 = Grammar::from_yml(&g.to_yml().unwrap()).unwrap();
+        assert_eq!(g2.column_order, g.column_order);
+    }
+
+    #[test]
+    fn test_to_yacc() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let y = g.to_yacc();
+        let lines = y.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "%token id");
+        assert_eq!(lines[1], "%start E");
+        assert!(y.contains("\nE\n    : E '+' T\n    | T\n    ;\n"));
+        assert!(y.contains("\nT\n    : T '*' F\n    | F\n    ;\n"));
+        assert!(y.contains("\nF\n    : '(' E ')'\n    | id\n    ;\n"));
+        assert_eq!(y.matches("%%").count(), 2);
+
+        // ε 产生式为空的候选式，非终结符名中的 ' 被替换
+        let g = Grammar::from_bnf("E' -> + E' | ε\n").unwrap();
+        let y = g.to_yacc();
+        assert!(y.contains("\nE_prime\n    : '+' E_prime\n    | /* empty */\n    ;\n"));
+    }
+
+    #[test]
+    fn test_tree_to_dot() {
+        let parser = Parser::new(Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap()).unwrap();
+        let tokens = lexical_analysis("a + b".to_string()).unwrap().0;
+        let tree = parser.parse_tree(tokens).unwrap();
+        let dot = tree_to_dot(&tree);
+        assert!(dot.starts_with("digraph ParseTree {"));
+        // E, E, T, F, id, +, T, F, id
+        assert_eq!(dot.matches(" [label=").count(), 9);
+        assert_eq!(dot.matches("shape=box").count(), 3);
+        assert_eq!(dot.matches(" -> ").count(), 8);
+        assert!(dot.contains("n0 [label=\"E\"];"));
+        assert!(dot.contains("[label=\"id\\na\", shape=box];"));
+        assert!(dot.contains("[label=\"+\", shape=box];"));
+
+        // ε 产生式规约得到的节点
+        let parser = Parser::new(Grammar::from_bnf("S -> id S | ε\n").unwrap()).unwrap();
+        let tokens = lexical_analysis("a".to_string()).unwrap().0;
+        let dot = tree_to_dot(&parser.parse_tree(tokens).unwrap());
+        assert_eq!(dot.matches(" [label=").count(), 4);
+        assert!(dot.contains("[label=\"ε\", shape=plaintext];"));
+    }
+
+    #[test]
+    fn test_automaton_to_dot() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let dot = automaton_to_dot(&g);
+        assert!(dot.starts_with("digraph LR0 {"));
+        // 表达式文法的LR(0)自动机共有12个状态
+        assert_eq!(dot.matches(" [label=\"I").count(), 12);
+        assert!(dot.contains("[label=\"I0\\lE' -> ·E\\l"));
+        assert!(dot.contains("I0 -> I1 [label=\"E\"];"));
+        assert!(dot.contains("E' -> E ·"));
+    }
+
+    #[test]
+    fn test_automaton_to_dot_epsilon() {
+        let yml = r#"
+        s: S
+        v:
+          - S
+          - A
+        t:
+          - a
+        p:
+          - left: S
+            right:
+              - A
+          - left: A
+            right:
+              - a
+              - A
+          - left: A
+            right: []
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        let dot = automaton_to_dot(&g);
+        assert!(dot.contains("\\lA -> ·\\l"));
+        assert!(dot.contains("[label=\"a\"];"));
+    }
+}
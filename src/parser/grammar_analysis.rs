@@ -0,0 +1,910 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use log::warn;
+
+use super::syntax_analysis::{
+    augment, get_follow, get_lr0_collection, Grammar, Product, END_MARKER, EPSILON,
+};
+
+/// 搜索二义性证据时最多展开的句型数，防止单位循环等情况下无法终止
+const MAX_FORMS: usize = 100_000;
+
+/// # 求文法中的无穷非终结符
+///
+/// 若非终结符 A 能经过若干步推导到达一个递归的非终结符 B，且 B =>+ αBβ 中αβ能推导出非空的终结符串，
+/// 则 A 能推导出任意长的串，称 A 为无穷非终结符
+///
+/// 只考虑右部的非终结符都能推导出终结符串的产生式，因此不能推导出终结符串的非终结符不是无穷的；
+/// A -> B, B -> A 这类αβ只能推导出空串的循环不会使串变长，同样不被计入
+pub fn infinite_nonterminals(g: &Grammar) -> HashSet<String> {
+    let productive = productive_nonterminals(g);
+    let useful =
+        g.p.iter()
+            .filter(|p| {
+                productive.contains(&p.left)
+                    && p.right
+                        .iter()
+                        .all(|s| !g.v.contains(s) || productive.contains(s))
+            })
+            .collect::<Vec<_>>();
+    let non_empty = non_empty_nonterminals(g, &useful);
+    let yields_terminal = |s: &String| s != EPSILON && (!g.v.contains(s) || non_empty.contains(s));
+
+    // 只由有用的产生式构成的依赖图，边 A -> B 在产生式中B的其他兄弟能推导出非空串时使串变长
+    let mut graph =
+        g.v.iter()
+            .map(|v| (v.clone(), HashSet::new()))
+            .collect::<HashMap<_, _>>();
+    let mut growing_edges = vec![];
+    for p in useful.iter() {
+        for (k, symbol) in p.right.iter().enumerate() {
+            if !g.v.contains(symbol) {
+                continue;
+            }
+            graph.get_mut(&p.left).unwrap().insert(symbol.clone());
+            let grows = p
+                .right
+                .iter()
+                .enumerate()
+                .any(|(i, s)| i != k && yields_terminal(s));
+            if grows {
+                growing_edges.push((&p.left, symbol));
+            }
+        }
+    }
+
+    // 递归且每次循环都使串变长的非终结符，即经过使串变长的边能回到自身
+    let pumping = growing_edges
+        .iter()
+        .filter(|(from, to)| from == to || reachable_from(&graph, to).contains(*from))
+        .map(|(from, _)| *from)
+        .collect::<HashSet<_>>();
+
+    // 能到达这样的非终结符的非终结符也是无穷的
+    productive
+        .iter()
+        .filter(|v| {
+            pumping.contains(v)
+                || reachable_from(&graph, v)
+                    .iter()
+                    .any(|r| pumping.contains(r))
+        })
+        .cloned()
+        .collect()
+}
+
+/// 只使用产生式集 `useful` 时能推导出非空终结符串的非终结符
+fn non_empty_nonterminals(g: &Grammar, useful: &[&Product]) -> HashSet<String> {
+    let mut non_empty = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for p in useful.iter() {
+            if non_empty.contains(&p.left) {
+                continue;
+            }
+            if p.right
+                .iter()
+                .any(|s| s != EPSILON && (!g.v.contains(s) || non_empty.contains(s)))
+            {
+                non_empty.insert(p.left.clone());
+                changed = true;
+            }
+        }
+    }
+    non_empty
+}
+
+/// # 搜索文法二义性的证据
+///
+/// 按广度优先枚举从开始符号出发的最左推导，每条最左推导对应一棵不同的语法树，
+/// 若同一个终结符串被两条不同的最左推导得到，则它有两棵不同的语法树，即为二义性的证据
+///
+/// 只搜索长度不超过 `max_len` 的终结符串，且最多展开 `MAX_FORMS` 个句型，
+/// 因此返回 `None` 并不代表文法一定没有二义性
+pub fn ambiguity_witness(g: &Grammar, max_len: usize) -> Option<Vec<String>> {
+    // 不能推出ε的非终结符最终至少产生一个终结符，用于剪枝
+    let nullable = g.nullable();
+    let non_nullable =
+        g.v.iter()
+            .filter(|v| !nullable.contains(*v))
+            .collect::<HashSet<_>>();
+
+    let mut sentences = HashSet::new();
+    let mut queue = VecDeque::from([vec![g.s.clone()]]);
+    let mut expanded = 0;
+    while let Some(form) = queue.pop_front() {
+        // 找到最左的非终结符，没有非终结符说明得到了一个终结符串
+        let Some(i) = form.iter().position(|s| g.v.contains(s)) else {
+            if !sentences.insert(form.clone()) {
+                return Some(form);
+            }
+            continue;
+        };
+
+        expanded += 1;
+        if expanded > MAX_FORMS {
+            break;
+        }
+
+        for p in g.p.iter().filter(|p| p.left == form[i]) {
+            let new_form = form[..i]
+                .iter()
+                .chain(p.right.iter().filter(|s| *s != EPSILON))
+                .chain(form[i + 1..].iter())
+                .cloned()
+                .collect::<Vec<_>>();
+            // 句型至少会产生的终结符数超过 max_len 时剪枝
+            let min_len = new_form
+                .iter()
+                .filter(|s| !g.v.contains(s) || non_nullable.contains(s))
+                .count();
+            if min_len <= max_len {
+                queue.push_back(new_form);
+            }
+        }
+    }
+
+    None
+}
+
+/// # 求终结符串不同语法树的数目
+///
+/// 不依赖分析表，对每个非终结符和输入的每个区间 `input[i..j]` 记忆化地统计推导方式的数目：
+/// 产生式 A -> X_1...X_n 在区间上的数目为把区间切分给 X_1...X_n 的所有方式的数目乘积之和，
+/// A 的数目为其所有产生式的数目之和。结果大于1说明该串有多棵语法树，文法是二义的
+///
+/// 存在 A =>+ A 的循环时语法树有无穷多棵，此时循环推导不被计入；数目过大时饱和于 `usize::MAX`
+pub fn count_parse_trees(g: &Grammar, input: &[String]) -> usize {
+    let mut counter = TreeCounter {
+        g,
+        input,
+        memo: HashMap::new(),
+        visiting: HashMap::new(),
+    };
+    counter.count_symbol(&g.s, 0, input.len()).0
+}
+
+/// 统计语法树数目的记忆化状态
+struct TreeCounter<'a> {
+    g: &'a Grammar,
+    input: &'a [String],
+    /// (非终结符, 起点, 终点) 对应的语法树数目
+    memo: HashMap<(&'a str, usize, usize), usize>,
+    /// 正在统计的 (非终结符, 起点, 终点) 及其在统计栈中的深度，再次遇到时说明存在循环推导
+    visiting: HashMap<(&'a str, usize, usize), usize>,
+}
+
+/// 统计的结果，以及统计中被切断的循环所回到的最浅的栈深度
+///
+/// 深度为 `Some(d)` 说明结果依赖于深度d上尚未统计完成的项，只在该项统计完成后才是最终结果
+type Count = (usize, Option<usize>);
+
+impl<'a> TreeCounter<'a> {
+    /// 符号 x 推导出 `input[i..j]` 的方式数
+    fn count_symbol(&mut self, x: &'a str, i: usize, j: usize) -> Count {
+        if x == EPSILON {
+            return ((i == j) as usize, None);
+        }
+        if !self.g.v.iter().any(|v| v == x) {
+            return ((j == i + 1 && self.input[i] == x) as usize, None);
+        }
+        let key = (x, i, j);
+        if let Some(&n) = self.memo.get(&key) {
+            return (n, None);
+        }
+        if let Some(&depth) = self.visiting.get(&key) {
+            return (0, Some(depth));
+        }
+        let depth = self.visiting.len();
+        self.visiting.insert(key, depth);
+        let g = self.g;
+        let mut n = 0usize;
+        let mut cut = None;
+        for p in g.p.iter().filter(|p| p.left == x) {
+            let right = p
+                .right
+                .iter()
+                .map(|s| s.as_str())
+                .filter(|s| *s != EPSILON)
+                .collect::<Vec<_>>();
+            let (m, c) = self.count_sequence(&right, i, j);
+            n = n.saturating_add(m);
+            cut = min_depth(cut, c);
+        }
+        self.visiting.remove(&key);
+        // 只切断了回到自身的循环时结果是完整的，否则结果依赖于栈中更浅的项，不能记忆
+        match cut {
+            Some(c) if c < depth => (n, cut),
+            _ => {
+                self.memo.insert(key, n);
+                (n, None)
+            }
+        }
+    }
+
+    /// 符号串 xs 推导出 `input[i..j]` 的方式数
+    fn count_sequence(&mut self, xs: &[&'a str], i: usize, j: usize) -> Count {
+        let Some((&first, rest)) = xs.split_first() else {
+            return ((i == j) as usize, None);
+        };
+        let mut n = 0usize;
+        let mut cut = None;
+        for m in i..=j {
+            let (head, c) = self.count_symbol(first, i, m);
+            cut = min_depth(cut, c);
+            if head == 0 {
+                continue;
+            }
+            let (tail, c) = self.count_sequence(rest, m, j);
+            cut = min_depth(cut, c);
+            n = n.saturating_add(head.saturating_mul(tail));
+        }
+        (n, cut)
+    }
+}
+
+/// 两个被切断的循环中较浅的栈深度
+fn min_depth(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        _ => a.or(b),
+    }
+}
+
+/// # 求能推导出终结符串的非终结符
+///
+/// 若产生式 A -> X_1...X_n 右部的每个符号都是终结符、ε或能推导出终结符串的非终结符，
+/// 则A也能推导出终结符串，反复迭代直到不再变化
+pub fn productive_nonterminals(g: &Grammar) -> HashSet<String> {
+    let mut productive = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for p in g.p.iter() {
+            if productive.contains(&p.left) {
+                continue;
+            }
+            if p.right
+                .iter()
+                .all(|s| !g.v.contains(s) || productive.contains(s))
+            {
+                productive.insert(p.left.clone());
+                changed = true;
+            }
+        }
+    }
+    productive
+}
+
+/// # 求从开始符号出发能到达的非终结符
+///
+/// 开始符号本身总是可达的
+pub fn reachable_nonterminals(g: &Grammar) -> HashSet<String> {
+    let mut reachable = reachable_from(&dependency_graph(g), &g.s);
+    reachable.insert(g.s.clone());
+    reachable
+}
+
+impl Grammar {
+    /// # 求左递归的非终结符
+    ///
+    /// 若 A =>+ Aα，则 A 是左递归的，包括直接左递归(A -> Aβ)和经过其他非终结符的间接左递归
+    ///
+    /// 产生式右部开头能推导出ε(FIRST集含ε)的非终结符可以被跳过，因此 A -> B A β 在 B 可空时也是左递归
+    ///
+    /// 返回的非终结符按非终结符集的顺序排列
+    pub fn left_recursive_nonterminals(&self) -> Vec<String> {
+        let nullable = self.nullable();
+
+        // 产生式右部能出现在最左端的非终结符
+        let mut graph = self
+            .v
+            .iter()
+            .map(|v| (v.clone(), HashSet::new()))
+            .collect::<HashMap<_, _>>();
+        for p in self.p.iter() {
+            for symbol in p.right.iter().filter(|s| *s != EPSILON) {
+                if !self.v.contains(symbol) {
+                    break;
+                }
+                graph.get_mut(&p.left).unwrap().insert(symbol.clone());
+                if !nullable.contains(symbol) {
+                    break;
+                }
+            }
+        }
+
+        self.v
+            .iter()
+            .filter(|v| reachable_from(&graph, v).contains(*v))
+            .cloned()
+            .collect()
+    }
+
+    /// # 消除左递归
+    ///
+    /// 按非终结符集的顺序 A_1...A_n 依次处理每个 A_i:
+    /// 1. 将 A_i -> A_j γ (j < i) 中的 A_j 替换为 A_j 的所有候选式，把间接左递归化为直接左递归
+    /// 2. 将 A_i -> A_i α | β 改写为 A_i -> β A_i'，A_i' -> α A_i' | ε
+    ///
+    /// 新的非终结符 A_i' 紧跟在 A_i 之后加入非终结符集，名字与已有符号重复时继续添加 `'`，
+    /// 新的 ε 产生式与 `from_bnf` 一致以空的右部表示
+    ///
+    /// 注: 与标准算法相同，要求文法中没有 A =>+ A 的循环，含有 ε 产生式时可能无法消除经过可空非终结符的左递归
+    pub fn eliminate_left_recursion(&self) -> Grammar {
+        let mut g = self.clone();
+        let order = self.v.clone();
+        for (i, a_i) in order.iter().enumerate() {
+            // 将 A_i -> A_j γ 中的 A_j 替换为其候选式
+            for a_j in order[..i].iter() {
+                let alternatives =
+                    g.p.iter()
+                        .filter(|p| p.left == *a_j)
+                        .map(|p| p.right.clone())
+                        .collect::<Vec<_>>();
+                let mut p = vec![];
+                for product in g.p.drain(..) {
+                    if product.left != *a_i || product.right.first() != Some(a_j) {
+                        p.push(product);
+                        continue;
+                    }
+                    for alternative in alternatives.iter() {
+                        p.push(Product {
+                            left: a_i.clone(),
+                            right: alternative
+                                .iter()
+                                .chain(product.right[1..].iter())
+                                .filter(|s| *s != EPSILON)
+                                .cloned()
+                                .collect(),
+                        });
+                    }
+                }
+                g.p = p;
+            }
+            g.eliminate_immediate_left_recursion(a_i);
+        }
+        g
+    }
+
+    /// 消除非终结符 a 的直接左递归
+    fn eliminate_immediate_left_recursion(&mut self, a: &str) {
+        let Some(position) = self.p.iter().position(|p| p.left == a) else {
+            return;
+        };
+        let (recursive, others): (Vec<_>, Vec<_>) = self
+            .p
+            .iter()
+            .filter(|p| p.left == a)
+            .partition(|p| p.right.first().is_some_and(|s| s == a));
+        if recursive.is_empty() {
+            return;
+        }
+
+        let mut a_prime = format!("{}'", a);
+        while self.v.contains(&a_prime) || self.t.contains(&a_prime) {
+            a_prime.push('\'');
+        }
+
+        // A -> β A'
+        let mut p = others
+            .iter()
+            .map(|p| Product {
+                left: a.to_string(),
+                right: p
+                    .right
+                    .iter()
+                    .filter(|s| *s != EPSILON)
+                    .cloned()
+                    .chain([a_prime.clone()])
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        // A' -> α A' | ε，A -> A 这样的产生式没有意义，直接丢弃
+        p.extend(recursive.iter().filter(|p| p.right.len() > 1).map(|p| {
+            Product {
+                left: a_prime.clone(),
+                right: p.right[1..]
+                    .iter()
+                    .cloned()
+                    .chain([a_prime.clone()])
+                    .collect(),
+            }
+        }));
+        // 与文法中ε的写法保持一致
+        let epsilon = if self.t.iter().any(|t| t == EPSILON) {
+            vec![EPSILON.to_string()]
+        } else {
+            vec![]
+        };
+        p.push(Product {
+            left: a_prime.clone(),
+            right: epsilon,
+        });
+
+        self.p.retain(|p| p.left != a);
+        self.p.splice(position..position, p);
+        let i = self.v.iter().position(|v| v == a).unwrap();
+        self.v.insert(i + 1, a_prime);
+    }
+}
+
+/// # 检测由FOLLOW集重叠引起的归约-归约冲突
+///
+/// SLR(1)对项目 A -> α· 在 FOLLOW(A) 上归约，若同一状态中两个可归约项目左部的FOLLOW集有交集，
+/// 则在交集中的终结符上产生归约-归约冲突
+///
+/// 返回存在冲突的状态编号及冲突的终结符(已排序)
+///
+/// 注: 这类冲突往往是FOLLOW集过于粗糙造成的，使用LALR(1)或LR(1)的向前看符号通常可以消除
+pub fn follow_overlap_conflicts(g: &Grammar) -> Vec<(usize, Vec<String>)> {
+    let follow = get_follow(g);
+    let outreach_g = augment(g);
+    let lr0_items = get_lr0_collection(&outreach_g);
+
+    let mut conflicts = vec![];
+    for (i, items) in lr0_items.iter().enumerate() {
+        // 状态中可归约的项目，不含 S' -> S·
+        let finals = items
+            .iter()
+            .filter(|item| item.dot >= item.right.len() && item.left != outreach_g.s)
+            .collect::<Vec<_>>();
+
+        let mut overlap = HashSet::new();
+        for (j, a) in finals.iter().enumerate() {
+            for b in finals[j + 1..].iter() {
+                let follow_b = &follow[&b.left];
+                overlap.extend(follow[&a.left].iter().filter(|t| follow_b.contains(t)));
+            }
+        }
+
+        if !overlap.is_empty() {
+            let mut overlap = overlap.into_iter().cloned().collect::<Vec<_>>();
+            overlap.sort();
+            warn!(
+                "state {} has reduce/reduce conflicts on {:?}, LALR(1) or LR(1) may resolve them",
+                i, overlap
+            );
+            conflicts.push((i, overlap));
+        }
+    }
+    conflicts
+}
+
+/// # 生成SLR(1)分析表中所有冲突的报告
+///
+/// 按状态编号与终结符的顺序列出每个移进-归约冲突和归约-归约冲突，每个冲突列出:
+/// - 移进: 圆点在该终结符之前的项目
+/// - 归约: 可归约的项目及其产生式序号(即ACTION表中 rj 的 j)，该终结符属于产生式左部的FOLLOW集
+///
+/// 没有冲突时报告文法是SLR(1)文法
+pub fn conflict_report(g: &Grammar) -> String {
+    let follow = get_follow(g);
+    let outreach_g = augment(g);
+    let lr0_items = get_lr0_collection(&outreach_g);
+    let terminals =
+        g.t.iter()
+            .map(|t| t.as_str())
+            .filter(|t| *t != EPSILON)
+            .chain([END_MARKER])
+            .collect::<Vec<_>>();
+
+    let mut report = vec![];
+    for (i, items) in lr0_items.iter().enumerate() {
+        for t in terminals.iter() {
+            // 在 t 上移进的项目
+            let shifts = items
+                .iter()
+                .filter(|item| item.right.get(item.dot).is_some_and(|s| s == t))
+                .collect::<Vec<_>>();
+            // 在 t 上归约的项目及其产生式序号
+            let reduces = items
+                .iter()
+                .filter(|item| {
+                    item.dot >= item.right.len()
+                        && item.left != outreach_g.s
+                        && follow[&item.left].iter().any(|f| f == t)
+                })
+                .map(|item| {
+                    let j = outreach_g
+                        .p
+                        .iter()
+                        .position(|p| p.left == item.left && p.right == item.right)
+                        .unwrap();
+                    (j, item)
+                })
+                .collect::<Vec<_>>();
+
+            let kind = match (shifts.is_empty(), reduces.len()) {
+                (false, n) if n > 0 => "shift/reduce",
+                (true, n) if n > 1 => "reduce/reduce",
+                _ => continue,
+            };
+            report.push(format!("state {}: {} conflict on `{}`", i, kind, t));
+            for item in shifts {
+                report.push(format!("    shift:  {}", item));
+            }
+            for (j, item) in reduces {
+                report.push(format!("    reduce: {}    (production {})", item, j));
+            }
+        }
+    }
+
+    match report.len() {
+        0 => "no conflicts, the grammar is SLR(1)\n".to_string(),
+        _ => {
+            let count = report
+                .iter()
+                .filter(|line| line.starts_with("state"))
+                .count();
+            report.insert(0, format!("{} conflict(s) found", count));
+            report.join("\n") + "\n"
+        }
+    }
+}
+
+/// # 求文法的依赖图
+///
+/// 每个非终结符映射到其产生式右部直接引用的非终结符，没有引用其他非终结符时为空集，
+/// 是求无穷非终结符、可达的非终结符等的基础
+pub fn dependency_graph(g: &Grammar) -> HashMap<String, HashSet<String>> {
+    let mut graph =
+        g.v.iter()
+            .map(|v| (v.clone(), HashSet::new()))
+            .collect::<HashMap<_, _>>();
+    for p in g.p.iter() {
+        for symbol in p.right.iter().filter(|s| g.v.contains(s)) {
+            graph.get_mut(&p.left).unwrap().insert(symbol.clone());
+        }
+    }
+    graph
+}
+
+/// 求从非终结符 x 出发经过至少一步能到达的所有非终结符
+fn reachable_from(graph: &HashMap<String, HashSet<String>>, x: &str) -> HashSet<String> {
+    let mut reachable = HashSet::new();
+    let mut queue = VecDeque::from([x.to_string()]);
+    while let Some(v) = queue.pop_front() {
+        for next in graph.get(&v).into_iter().flatten() {
+            if reachable.insert(next.clone()) {
+                queue.push_back(next.clone());
+            }
+        }
+    }
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::parser::syntax_analysis::EXPR_GRAMMAR_YML;
+
+    #[test]
+    fn test_left_recursive_nonterminals() {
+        // 直接左递归
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        assert_eq!(g.left_recursive_nonterminals(), vec!["E", "T"]);
+
+        // 间接左递归
+        let g = Grammar::from_bnf("A -> B a | a\nB -> A b | b\n").unwrap();
+        assert_eq!(g.left_recursive_nonterminals(), vec!["A", "B"]);
+
+        // 经过可空的非终结符的左递归
+        let g = Grammar::from_bnf("S -> N S a | b\nN -> n | ε\n").unwrap();
+        assert_eq!(g.left_recursive_nonterminals(), vec!["S"]);
+
+        // 右递归不是左递归
+        let g = Grammar::from_bnf("S -> a S | b\n").unwrap();
+        assert!(g.left_recursive_nonterminals().is_empty());
+    }
+
+    #[test]
+    fn test_eliminate_left_recursion() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let eliminated = g.eliminate_left_recursion();
+        eliminated.validate().unwrap();
+        assert!(eliminated.left_recursive_nonterminals().is_empty());
+        assert_eq!(eliminated.v, vec!["E", "E'", "T", "T'", "F"]);
+        assert_eq!(
+            eliminated,
+            Grammar::from_bnf(
+                "E -> T E'\nE' -> + T E' | ε\nT -> F T'\nT' -> * F T' | ε\nF -> ( E ) | id\n"
+            )
+            .unwrap()
+        );
+
+        // 间接左递归先被替换为直接左递归
+        let g = Grammar::from_bnf("A -> B a | a\nB -> A b | b\n").unwrap();
+        let eliminated = g.eliminate_left_recursion();
+        assert!(eliminated.left_recursive_nonterminals().is_empty());
+        assert_eq!(
+            eliminated,
+            Grammar::from_bnf("A -> B a | a\nB -> a b B' | b B'\nB' -> a b B' | ε\n").unwrap()
+        );
+
+        // 新的非终结符不与已有的符号重复
+        let g = Grammar::from_bnf("A -> A x | A' y\nA' -> z\n").unwrap();
+        let eliminated = g.eliminate_left_recursion();
+        assert_eq!(eliminated.v, vec!["A", "A''", "A'"]);
+
+        // 文法以ε表示空产生式时，新的 A' -> ε 同样写作ε
+        let yml = r#"
+        s: S
+        v:
+          - S
+          - A
+        t:
+          - a
+          - b
+          - ε
+        p:
+          - left: S
+            right:
+              - S
+              - a
+          - left: S
+            right:
+              - A
+          - left: A
+            right:
+              - b
+          - left: A
+            right:
+              - ε
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        let eliminated = g.eliminate_left_recursion();
+        eliminated.validate().unwrap();
+        let epsilon_productions = eliminated
+            .p
+            .iter()
+            .filter(|p| p.arity() == 0)
+            .map(|p| (p.left.as_str(), p.right.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            epsilon_productions,
+            vec![
+                ("S'", vec![EPSILON.to_string()]),
+                ("A", vec![EPSILON.to_string()])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conflict_report() {
+        let g = Grammar::from_bnf("S -> if E then S else S | if E then S | other\nE -> cond\n")
+            .unwrap();
+        let report = conflict_report(&g);
+        let lines = report.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "1 conflict(s) found");
+        assert!(lines[1].ends_with("shift/reduce conflict on `else`"));
+        assert_eq!(lines[2], "    shift:  S -> if E then S ·else S");
+        assert_eq!(lines[3], "    reduce: S -> if E then S ·    (production 1)");
+
+        // 归约-归约冲突
+        let g = Grammar::from_bnf("S -> A x | B x\nA -> a\nB -> a\n").unwrap();
+        let report = conflict_report(&g);
+        assert!(report.contains("reduce/reduce conflict on `x`"));
+        assert!(report.contains("(production 2)"));
+        assert!(report.contains("(production 3)"));
+
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        assert_eq!(conflict_report(&g), "no conflicts, the grammar is SLR(1)\n");
+    }
+
+    #[test]
+    fn test_ambiguity_witness() {
+        let yml = r#"
+        s: E
+        v:
+          - E
+        t:
+          - +
+          - id
+        p:
+          - left: E
+            right:
+              - E
+              - +
+              - E
+          - left: E
+            right:
+              - id
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        assert_eq!(
+            ambiguity_witness(&g, 5),
+            Some(
+                ["id", "+", "id", "+", "id"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect()
+            )
+        );
+        assert_eq!(ambiguity_witness(&g, 3), None);
+
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        assert_eq!(ambiguity_witness(&g, 5), None);
+    }
+
+    #[test]
+    fn test_count_parse_trees() {
+        let input = |s: &str| s.split(' ').map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let g = Grammar::from_bnf("E -> E + E | E * E | ( E ) | id\n").unwrap();
+        assert_eq!(count_parse_trees(&g, &input("id")), 1);
+        assert_eq!(count_parse_trees(&g, &input("id + id")), 1);
+        assert_eq!(count_parse_trees(&g, &input("id + id + id")), 2);
+        // 卡特兰数 C_3
+        assert_eq!(count_parse_trees(&g, &input("id + id * id + id")), 5);
+        assert_eq!(count_parse_trees(&g, &input("( id + id ) + id")), 1);
+        assert_eq!(count_parse_trees(&g, &input("id +")), 0);
+
+        // 无二义性的文法每个句子只有一棵语法树
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        assert_eq!(count_parse_trees(&g, &input("id + id + id")), 1);
+
+        // ε 产生式与循环推导
+        let g = Grammar::from_bnf("S -> A S | ε\nA -> a | S\n").unwrap();
+        assert!(count_parse_trees(&g, &input("a a")) >= 1);
+        assert_eq!(count_parse_trees(&g, &[]), 1);
+
+        // 单位循环中切断循环得到的部分结果不能被记忆: T => A z => S z => b z
+        let g = Grammar::from_bnf("T -> S y | A z\nS -> A | b\nA -> S | c\n").unwrap();
+        assert_eq!(count_parse_trees(&g, &input("b z")), 1);
+        assert_eq!(count_parse_trees(&g, &input("c y")), 1);
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        let g = Grammar::from_bnf(
+            "E -> T E'\nE' -> + T E' | ε\nT -> F T'\nT' -> * F T' | ε\nF -> ( E ) | id\n",
+        )
+        .unwrap();
+        let graph = dependency_graph(&g);
+        let set = |symbols: &[&str]| {
+            symbols
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(graph.len(), g.v.len());
+        assert_eq!(graph["E"], set(&["T", "E'"]));
+        assert_eq!(graph["E'"], set(&["T", "E'"]));
+        assert_eq!(graph["T"], set(&["F", "T'"]));
+        assert_eq!(graph["F"], set(&["E"]));
+
+        let g = Grammar::from_bnf("S -> a\n").unwrap();
+        assert_eq!(dependency_graph(&g)["S"], HashSet::new());
+    }
+
+    #[test]
+    fn test_productive_and_reachable() {
+        let yml = r#"
+        s: S
+        v:
+          - S
+          - X
+          - Y
+        t:
+          - a
+        p:
+          - left: S
+            right:
+              - a
+          - left: S
+            right:
+              - X
+          - left: X
+            right:
+              - X
+          - left: Y
+            right:
+              - a
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        assert_eq!(
+            productive_nonterminals(&g),
+            HashSet::from(["S".to_string(), "Y".to_string()])
+        );
+        assert_eq!(
+            reachable_nonterminals(&g),
+            HashSet::from(["S".to_string(), "X".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_follow_overlap_conflicts() {
+        // LR(1)文法，但不是SLR(1)文法
+        let yml = r#"
+        s: S
+        v:
+          - S
+          - A
+          - B
+        t:
+          - a
+          - b
+          - c
+          - d
+        p:
+          - left: S
+            right:
+              - A
+              - a
+          - left: S
+            right:
+              - b
+              - A
+              - c
+          - left: S
+            right:
+              - B
+              - c
+          - left: S
+            right:
+              - b
+              - B
+              - a
+          - left: A
+            right:
+              - d
+          - left: B
+            right:
+              - d
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        let conflicts = follow_overlap_conflicts(&g);
+        // 读入 d 与读入 b d 后到达同一个LR(0)状态 {A -> d·, B -> d·}
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].1, vec!["a".to_string(), "c".to_string()]);
+
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        assert!(follow_overlap_conflicts(&g).is_empty());
+    }
+
+    #[test]
+    fn test_infinite_nonterminals() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        assert_eq!(
+            infinite_nonterminals(&g),
+            HashSet::from(["E".to_string(), "T".to_string(), "F".to_string()])
+        );
+
+        let yml = r#"
+        s: S
+        v:
+          - S
+          - A
+        t:
+          - a
+        p:
+          - left: S
+            right:
+              - A
+              - A
+          - left: A
+            right:
+              - a
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        assert!(infinite_nonterminals(&g).is_empty());
+
+        // 单位循环不会使串变长
+        let g = Grammar::from_bnf("A -> B | a\nB -> A\n").unwrap();
+        assert!(infinite_nonterminals(&g).is_empty());
+
+        // 没有终止的产生式，不能推导出终结符串
+        let g = Grammar::from_bnf("A -> A a\n").unwrap();
+        assert!(infinite_nonterminals(&g).is_empty());
+
+        // 循环中的兄弟只能推导出空串时不会使串变长，能推导出非空串时会
+        let g = Grammar::from_bnf("A -> A N | a\nN -> ε\n").unwrap();
+        assert!(infinite_nonterminals(&g).is_empty());
+        let g = Grammar::from_bnf("A -> A N | a\nN -> ε | n\n").unwrap();
+        assert_eq!(infinite_nonterminals(&g), HashSet::from(["A".to_string()]));
+    }
+}
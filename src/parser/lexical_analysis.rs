@@ -1,11 +1,455 @@
-use std::fmt::Display;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{Debug, Display},
+    iter::Peekable,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::Chars,
+};
 
 use super::error::LexicalError;
 
-/// 词法分析
+/// # 词法分析
+///
+/// 返回的token序列中包含识别失败的单词(`TokenType::Error`)，布尔值表示是否存在这样的单词，
+/// 需要分别得到合法的token与所有词法错误时使用 `lexical_analysis_with_errors`
 pub fn lexical_analysis(input: String) -> Result<(Vec<Token>, bool), LexicalError> {
-    let after_preprocessing = preprocess(input)?;
-    Ok(process(after_preprocessing))
+    lexical_analysis_with_config(input, &LexerConfig::default())
+}
+
+/// 词法分析配置
+#[derive(Debug, Clone)]
+pub struct LexerConfig {
+    /// 关键字，默认为C语言的关键字
+    pub keywords: HashSet<String>,
+    /// 运算符，识别时取最长匹配，默认为C语言的运算符
+    pub operators: HashSet<String>,
+    /// 分隔符，每个分隔符为单个字符，默认为C语言的分隔符
+    pub delimiters: HashSet<String>,
+    /// 是否在分析前将字符串与字符常量之外的连续空白字符合并为一个空格
+    ///
+    /// 合并后单词的位置仍然指向原始输入
+    pub normalize_whitespace: bool,
+    /// 是否允许多行注释嵌套，允许时 `/* a /* b */ c */` 是一个完整的注释
+    pub allow_nested_comments: bool,
+    /// 单行注释的开始符号，注释到行尾为止，默认为 `//`，可以使用 `#`、`--` 等其他语言的风格
+    ///
+    /// 使用 `#` 时 `#line` 与 `#define` 等预处理指示也被当作注释删除
+    pub line_comments: Vec<String>,
+    /// 多行注释的开始与结束符号，默认为 `/*` 与 `*/`，为 `None` 时不识别多行注释
+    pub block_comment: Option<(String, String)>,
+    /// 是否展开 `#define 名字 替换文本` 定义的宏，不展开时 `#define` 行被忽略，宏名作为标识符
+    pub expand_macros: bool,
+    /// 检查数字常量的函数，在内置的检查通过后对每个数字常量调用
+    pub number_validator: Option<NumberValidator>,
+}
+
+/// # 检查数字常量的函数
+///
+/// 用于实现特定语言的规则，如不允许前导零、限制小数的位数，
+/// 返回 `Err` 时该常量成为识别失败的单词，错误信息为返回的信息
+#[derive(Clone)]
+pub struct NumberValidator(Rc<ValidateFn>);
+
+type ValidateFn = dyn Fn(&str) -> Result<(), String>;
+
+impl NumberValidator {
+    pub fn new(validator: impl Fn(&str) -> Result<(), String> + 'static) -> Self {
+        Self(Rc::new(validator))
+    }
+
+    /// 检查数字常量 `number`
+    pub fn validate(&self, number: &str) -> Result<(), String> {
+        (self.0)(number)
+    }
+}
+
+impl Debug for NumberValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NumberValidator")
+    }
+}
+
+impl Default for LexerConfig {
+    fn default() -> Self {
+        let set = |words: &[&str]| words.iter().map(|w| w.to_string()).collect();
+        Self {
+            keywords: set(&KEYWORDS),
+            operators: set(&OPERATOR),
+            delimiters: set(&DELIMITERS),
+            normalize_whitespace: false,
+            allow_nested_comments: false,
+            line_comments: vec!["//".to_string()],
+            block_comment: Some(("/*".to_string(), "*/".to_string())),
+            expand_macros: false,
+            number_validator: None,
+        }
+    }
+}
+
+/// 按照配置进行词法分析
+pub fn lexical_analysis_with_config(
+    input: String,
+    config: &LexerConfig,
+) -> Result<(Vec<Token>, bool), LexicalError> {
+    let tokens = Lexer::with_config(input, config).collect::<Result<Vec<_>, _>>()?;
+    let error = tokens
+        .iter()
+        .any(|token| matches!(token.token_type, TokenType::Error(_)));
+    Ok((tokens, error))
+}
+
+/// 词法分析的结果，合法的token与词法错误分开存放
+#[derive(Debug, Clone)]
+pub struct LexicalOutput {
+    /// 识别成功的token
+    pub tokens: Vec<Token>,
+    /// 识别失败的单词的错误，按出现的顺序排列，每个错误都带有位置
+    pub errors: Vec<LexicalError>,
+}
+
+impl LexicalOutput {
+    /// 是否存在词法错误
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
+
+/// # 按照配置进行词法分析，并将词法错误与合法的token分开
+///
+/// 识别失败的单词是可恢复的错误，跳过后继续识别，全部收集在 `errors` 中，
+/// 预处理失败(如多行注释没有闭合)是致命的错误，以 `Err` 返回
+pub fn lexical_analysis_with_errors(
+    input: String,
+    config: &LexerConfig,
+) -> Result<LexicalOutput, LexicalError> {
+    let mut output = LexicalOutput {
+        tokens: vec![],
+        errors: vec![],
+    };
+    for token in Lexer::with_config(input, config) {
+        let token = token?;
+        match token.token_type {
+            TokenType::Error(e) => output.errors.push(e),
+            _ => output.tokens.push(token),
+        }
+    }
+    Ok(output)
+}
+
+/// # 词法分析器
+///
+/// 按顺序产生单词的迭代器，创建时完成预处理(去除注释等)，迭代时才逐行识别单词，
+/// 因此可以只取出前若干个单词而不必分析整个输入
+///
+/// 识别失败的单词以 `TokenType::Error` 的形式产生，只有预处理失败时才会产生一个 `Err`
+pub struct Lexer {
+    /// 预处理后尚未识别的行
+    lines: std::vec::IntoIter<SourceLine>,
+    /// 当前行中已识别但尚未取出的单词
+    tokens: VecDeque<Token>,
+    /// 预处理时发生的错误
+    error: Option<LexicalError>,
+    /// 识别单词时使用的配置
+    config: LexerConfig,
+    /// 已定义的宏，只在展开宏时记录
+    macros: HashMap<String, String>,
+}
+
+impl Lexer {
+    pub fn new(input: String) -> Self {
+        Self::with_config(input, &LexerConfig::default())
+    }
+
+    pub fn with_config(input: String, config: &LexerConfig) -> Self {
+        let (lines, error) = match preprocess(input, config) {
+            Ok(lines) => (lines, None),
+            Err(e) => (vec![], Some(e)),
+        };
+        Self {
+            lines: lines.into_iter(),
+            tokens: VecDeque::new(),
+            error,
+            config: config.clone(),
+            macros: HashMap::new(),
+        }
+    }
+}
+
+impl Iterator for Lexer {
+    type Item = Result<Token, LexicalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        while self.tokens.is_empty() {
+            let line = self.lines.next()?;
+            if let Some((name, value)) = line.define {
+                if self.config.expand_macros {
+                    self.macros.insert(name, value);
+                }
+                continue;
+            }
+            let tokens = process_line(&line, &self.config);
+            let tokens = self.expand_macros(tokens, &mut vec![]);
+            self.tokens.extend(tokens);
+        }
+        self.tokens.pop_front().map(Ok)
+    }
+}
+
+impl Lexer {
+    /// # 展开token序列中的宏
+    ///
+    /// 与宏名相同的标识符被替换为替换文本中的单词，替换得到的单词的位置为宏名的位置
+    ///
+    /// 替换文本中的宏同样被展开，`expanding` 为正在展开的宏，宏在自身的替换文本中不再展开
+    fn expand_macros(&self, tokens: Vec<Token>, expanding: &mut Vec<String>) -> Vec<Token> {
+        let mut result = vec![];
+        for token in tokens {
+            let value = match token.token_type {
+                TokenType::Identifier if !expanding.contains(&token.token_value) => {
+                    self.macros.get(&token.token_value)
+                }
+                _ => None,
+            };
+            let Some(value) = value else {
+                result.push(token);
+                continue;
+            };
+
+            let line = SourceLine {
+                file: token.position.file.clone(),
+                number: token.position.line,
+                columns: vec![token.position.column; value.chars().count()],
+                text: value.clone(),
+                define: None,
+            };
+            expanding.push(token.token_value);
+            let replaced = process_line(&line, &self.config);
+            result.extend(self.expand_macros(replaced, expanding));
+            expanding.pop();
+        }
+        result
+    }
+}
+
+/// # 无损词法分析
+///
+/// 除单词外，还以 `TokenType::Whitespace` 与 `TokenType::Comment` 保留单词之间的空白与注释，
+/// 每个单词的值都是其在源代码中的原文，因此 `tokens_to_source` 可以还原出与输入完全相同的文本
+///
+/// 分析时不展开宏，也不合并空白字符，`#define` 等预处理指示行整行作为注释保留；
+/// 输入中的 `#line` 指示改变了行号，使单词无法对应回原文时返回错误
+pub fn lexical_analysis_lossless(
+    input: String,
+    config: &LexerConfig,
+) -> Result<Vec<Token>, LexicalError> {
+    let config = LexerConfig {
+        normalize_whitespace: false,
+        expand_macros: false,
+        ..config.clone()
+    };
+    let tokens = Lexer::with_config(input.clone(), &config).collect::<Result<Vec<_>, _>>()?;
+
+    let chars = input.chars().collect::<Vec<_>>();
+    // 每一行第一个字符在输入中的下标
+    let mut line_starts = vec![0];
+    line_starts.extend(
+        chars
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c == '\n')
+            .map(|(i, _)| i + 1),
+    );
+    let position_of = |i: usize| {
+        let line = line_starts.partition_point(|start| *start <= i);
+        Position {
+            file: None,
+            line,
+            column: i - line_starts[line - 1] + 1,
+        }
+    };
+
+    let mut result = vec![];
+    // 已经对应到原文的字符数
+    let mut offset = 0;
+    for token in tokens {
+        let length = token.token_value.chars().count();
+        let start = line_starts
+            .get(token.position.line.wrapping_sub(1))
+            .map(|start| start + token.position.column - 1)
+            .filter(|start| {
+                *start >= offset
+                    && chars
+                        .get(*start..*start + length)
+                        .is_some_and(|text| text.iter().copied().eq(token.token_value.chars()))
+            })
+            .ok_or_else(|| {
+                LexicalError::at(
+                    &format!(
+                        "token `{}` does not match the source text",
+                        token.token_value
+                    ),
+                    token.position.line,
+                    token.position.column,
+                )
+            })?;
+        for (i, token_type, text) in split_trivia(&chars[offset..start], &config) {
+            result.push(Token {
+                token_type,
+                token_value: text,
+                position: position_of(offset + i),
+                literal: None,
+            });
+        }
+        result.push(token);
+        offset = start + length;
+    }
+    for (i, token_type, text) in split_trivia(&chars[offset..], &config) {
+        result.push(Token {
+            token_type,
+            token_value: text,
+            position: position_of(offset + i),
+            literal: None,
+        });
+    }
+    Ok(result)
+}
+
+/// # 将token序列还原为源代码
+///
+/// 依次拼接每个单词的值，对无损词法分析的结果可以得到与原始输入完全相同的文本
+pub fn tokens_to_source(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| token.token_value.as_str())
+        .collect()
+}
+
+/// # 将单词之间的文本划分为空白与注释
+///
+/// 返回每一段在 `text` 中的起始下标、类型与内容，连续的空白字符为一段，
+/// 多行注释到与之匹配的结束符号为止，单行注释与预处理指示行到行尾为止
+fn split_trivia(text: &[char], config: &LexerConfig) -> Vec<(usize, TokenType, String)> {
+    let block_comment = block_comment_chars(config);
+    let mut result = vec![];
+    let mut i = 0;
+    while i < text.len() {
+        let start = i;
+        let token_type = if text[i].is_whitespace() {
+            while i < text.len() && text[i].is_whitespace() {
+                i += 1;
+            }
+            TokenType::Whitespace
+        } else if let Some((open, close)) = block_comment
+            .as_ref()
+            .filter(|(open, _)| text[i..].starts_with(open))
+        {
+            i += open.len();
+            // 注释的嵌套深度
+            let mut depth = 1;
+            while i < text.len() && depth > 0 {
+                if text[i..].starts_with(close) {
+                    depth -= 1;
+                    i += close.len();
+                } else if config.allow_nested_comments && text[i..].starts_with(open) {
+                    depth += 1;
+                    i += open.len();
+                } else {
+                    i += 1;
+                }
+            }
+            TokenType::Comment
+        } else {
+            // 行尾的换行(包括 \r\n)不属于注释
+            while i < text.len() && text[i] != '\n' && !text[i..].starts_with(&['\r', '\n']) {
+                i += 1;
+            }
+            TokenType::Comment
+        };
+        result.push((start, token_type, text[start..i].iter().collect()));
+    }
+    result
+}
+
+/// # 从编辑位置开始重新进行词法分析
+///
+/// `previous` 为编辑前的源代码的分析结果，`source` 为编辑后的源代码，
+/// `edit_offset` 为编辑位置的字节偏移，其之前的内容没有改变
+///
+/// 安全边界之前的单词直接复用，只对之后的部分重新识别。安全边界为编辑位置所在行的行首：
+/// 字符串与字符常量不跨行，行首不会在常量中；行首在多行注释中时，退回到该注释开始的行首
+///
+/// 使用默认的配置，预处理的错误也以 `TokenType::Error` 的形式出现在结果中；
+/// 边界之前有 `#line` 指示时行号与原文不对应，退回到分析整个输入
+pub fn relex_from(previous: &[Token], source: &str, edit_offset: usize) -> Vec<Token> {
+    let config = LexerConfig::default();
+    let mut edit_offset = edit_offset.min(source.len());
+    while !source.is_char_boundary(edit_offset) {
+        edit_offset -= 1;
+    }
+
+    let line_start = |line: usize| {
+        source
+            .match_indices('\n')
+            .nth(line.wrapping_sub(2))
+            .map_or(0, |(i, _)| i + 1)
+    };
+    let mut start = source[..edit_offset].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &source[..start];
+    if prefix
+        .lines()
+        .any(|line| strip_directive(line.trim_start(), "#line").is_some())
+    {
+        start = 0;
+    } else if let Err(LexicalError {
+        line: Some(line), ..
+    }) = remove_comment(prefix.to_string(), &config)
+    {
+        start = line_start(line);
+    }
+    // 重新识别的第一行的行号
+    let first_line = source[..start].matches('\n').count() + 1;
+
+    let mut result = previous
+        .iter()
+        .take_while(|token| token.position.line < first_line)
+        .cloned()
+        .collect::<Vec<_>>();
+    for token in Lexer::with_config(source[start..].to_string(), &config) {
+        let mut token = match token {
+            Ok(token) => token,
+            Err(mut e) => {
+                let position = Position {
+                    file: None,
+                    line: e.line.unwrap_or(1),
+                    column: e.column.unwrap_or(1),
+                };
+                e.line = e.line.map(|line| line + first_line - 1);
+                Token {
+                    token_type: TokenType::Error(e),
+                    token_value: String::new(),
+                    position,
+                    literal: None,
+                }
+            }
+        };
+        token.position.line += first_line - 1;
+        result.push(token);
+    }
+    result
+}
+
+/// # 对源文件进行词法分析
+///
+/// 会展开源文件中的 `#include "file"`，被包含的文件路径相对于当前文件所在目录
+///
+/// 展开时在被包含文件的前后插入 `#line` 指示，使得每个单词的位置都指向其原始文件与行号
+pub fn lexical_analysis_file(path: &Path) -> Result<(Vec<Token>, bool), LexicalError> {
+    let input = expand_includes(path, &mut vec![])?;
+    lexical_analysis(input)
 }
 
 #[rustfmt::skip]
@@ -21,21 +465,132 @@ const KEYWORDS: [&str; 32] = [
 ];
 
 #[rustfmt::skip]
-const OPERATOR: [&str; 24] = [
+const OPERATOR: [&str; 36] = [
     "+", "-", "*", "/", "%", "++", "--", // 算术运算符
     "==", "!=", ">", "<", ">=", "<=", // 关系运算符
-    "&", "|", // 按位与，按位或（也是逻辑运算符的先导符）
+    "&", "|", "^", "~", "<<", ">>", // 位运算符
     "&&", "||", "!", // 逻辑运算符
-    "=", "+=", "-=", "+=", "/=", "%=", // 赋值运算符
+    "=", "+=", "-=", "*=", "/=", "%=", // 赋值运算符
+    "&=", "|=", "^=", "<<=", ">>=", // 位运算赋值运算符
+    "?", ":", "->", // 条件运算符，成员访问运算符
 ];
 
 #[rustfmt::skip]
 const DELIMITERS: [&str; 9] = ["{", "}", "[", "]", "(", ")", ",", ".", ";"];
 
-#[derive(Debug)]
+/// 单词在源代码中的位置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Position {
+    /// 所在文件，直接分析字符串时为 `None`
+    pub file: Option<String>,
+    /// 行号，从1开始
+    pub line: usize,
+    /// 列号，从1开始
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file, self.line, self.column),
+            None => write!(f, "{}:{}", self.line, self.column),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub token_value: String,
+    /// 单词第一个字符的位置，不参与单词的比较
+    pub position: Position,
+    /// 常量的值，只有常量才有，由 `token_value` 解析得到，不参与单词的比较
+    pub literal: Option<LiteralValue>,
+}
+
+impl Token {
+    pub fn new(token_type: TokenType, token_value: &str) -> Self {
+        let literal = match token_type {
+            TokenType::Constant => LiteralValue::parse(token_value),
+            _ => None,
+        };
+        Self {
+            token_type,
+            token_value: token_value.to_string(),
+            position: Position::default(),
+            literal,
+        }
+    }
+}
+
+/// # 常量的值
+///
+/// 由常量单词的原始文本解析得到，使用者不需要再自行解析数字或去掉引号
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiteralValue {
+    /// 整数，包括十六进制数(`0x` 开头)和八进制数(`0` 开头)
+    Int(i64),
+    /// 浮点数，包括科学计数法
+    Float(f64),
+    /// 字符常数，不含单引号
+    Char(char),
+    /// 字符串常数，不含双引号，转义字符已被替换
+    Str(String),
+}
+
+impl LiteralValue {
+    /// # 解析常量的原始文本
+    ///
+    /// 文本不是合法的常量或数值超出范围时返回 `None`
+    pub fn parse(text: &str) -> Option<LiteralValue> {
+        if let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            return Some(LiteralValue::Str(unescape(inner)));
+        }
+        if let Some(inner) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+            let mut chars = inner.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(LiteralValue::Char(c)),
+                _ => None,
+            };
+        }
+        if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            return i64::from_str_radix(digits, 16).ok().map(LiteralValue::Int);
+        }
+        if text.contains(['.', 'e', 'E']) {
+            return text.parse().ok().map(LiteralValue::Float);
+        }
+        if text.len() > 1 && text.starts_with('0') {
+            return i64::from_str_radix(&text[1..], 8)
+                .ok()
+                .map(LiteralValue::Int);
+        }
+        text.parse().ok().map(LiteralValue::Int)
+    }
+}
+
+/// 替换字符串常数中的转义字符，不认识的转义字符保持原样
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some(c @ ('\\' | '"' | '\'')) => result.push(c),
+            Some(c) => {
+                result.push('\\');
+                result.push(c);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
 }
 
 impl PartialEq for Token {
@@ -44,7 +599,7 @@ impl PartialEq for Token {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TokenType {
     Keyword,
     Identifier,
@@ -52,17 +607,67 @@ pub enum TokenType {
     Operator,
     Delimiter,
     Error(LexicalError),
+    /// 空白字符，只在无损词法分析中产生
+    Whitespace,
+    /// 注释或预处理指示行，只在无损词法分析中产生
+    Comment,
 }
 
 impl PartialEq for TokenType {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Self::Error(l0), Self::Error(r0)) => l0.to_string() == r0.to_string(),
+            (Self::Error(l0), Self::Error(r0)) => l0.message == r0.message,
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
     }
 }
 
+impl TokenType {
+    /// 单词的类别，不含识别失败时的错误信息
+    pub fn kind(&self) -> TokenTypeKind {
+        match self {
+            Self::Keyword => TokenTypeKind::Keyword,
+            Self::Identifier => TokenTypeKind::Identifier,
+            Self::Constant => TokenTypeKind::Constant,
+            Self::Operator => TokenTypeKind::Operator,
+            Self::Delimiter => TokenTypeKind::Delimiter,
+            Self::Error(_) => TokenTypeKind::Error,
+            Self::Whitespace => TokenTypeKind::Whitespace,
+            Self::Comment => TokenTypeKind::Comment,
+        }
+    }
+}
+
+/// 单词的类别，与 `TokenType` 一一对应，但不携带数据，可以直接比较、复制和作为集合的元素
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenTypeKind {
+    Keyword,
+    Identifier,
+    Constant,
+    Operator,
+    Delimiter,
+    Error,
+    Whitespace,
+    Comment,
+}
+
+impl Display for TokenTypeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// # 按类别过滤token序列
+///
+/// 只保留类别在 `keep` 中的单词，单词的顺序不变，
+/// 需要去掉某些类别时在 `keep` 中列出其余的类别
+pub fn filter_tokens(tokens: Vec<Token>, keep: &[TokenTypeKind]) -> Vec<Token> {
+    tokens
+        .into_iter()
+        .filter(|token| keep.contains(&token.token_type.kind()))
+        .collect()
+}
+
 impl Display for TokenType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -72,302 +677,747 @@ impl Display for TokenType {
             Self::Operator => write!(f, "Operator"),
             Self::Delimiter => write!(f, "Delimiter"),
             Self::Error(e) => write!(f, "Error: {}", e),
+            Self::Whitespace => write!(f, "Whitespace"),
+            Self::Comment => write!(f, "Comment"),
         }
     }
 }
 
-/// 处理
-fn process(input: Vec<String>) -> (Vec<Token>, bool) {
+/// 处理一行，识别其中的所有单词
+fn process_line(line: &SourceLine, config: &LexerConfig) -> Vec<Token> {
     let mut result = Vec::new();
-    let mut error = false;
+    let mut chars = Cursor::new(&line.text);
 
-    for line in input {
-        let mut chars = line.chars().peekable();
+    while let Some(char) = chars.next() {
+        // 空格 跳过
+        if char == ' ' {
+            continue;
+        }
 
-        while let Some(char) = chars.next() {
-            // 空格 跳过
-            if char == ' ' {
-                continue;
-            }
+        // 单词第一个字符的位置
+        let position = Position {
+            file: line.file.clone(),
+            line: line.number,
+            column: line.columns[chars.column - 1],
+        };
 
-            // 分隔符
-            if DELIMITERS.contains(&char.to_string().as_str()) {
-                result.push(Token {
-                    token_type: TokenType::Delimiter,
-                    token_value: char.to_string(),
-                });
-                continue;
-            }
+        // 分隔符
+        if config.delimiters.contains(&char.to_string()) {
+            result.push(Token {
+                token_type: TokenType::Delimiter,
+                token_value: char.to_string(),
+                position: position.clone(),
+                literal: None,
+            });
+            continue;
+        }
 
-            // 运算符
-            if OPERATOR.contains(&char.to_string().as_str()) {
-                let mut operator = char.to_string();
+        // 运算符
+        if config.operators.contains(&char.to_string()) {
+            let mut operator = char.to_string();
 
-                if let Some(next_char) = chars.peek() {
-                    let double_operator = format!("{}{}", operator, next_char);
-                    if OPERATOR.contains(&double_operator.as_str()) {
-                        chars.next();
-                        operator = double_operator;
-                    }
+            // 最长匹配: 向后读取字符，直到不再是任何运算符的前缀，取其中最长的运算符
+            let mut candidate = operator.clone();
+            let mut length = 0;
+            for next_char in chars.clone() {
+                candidate.push(next_char);
+                if !config.operators.iter().any(|op| op.starts_with(&candidate)) {
+                    break;
+                }
+                if config.operators.contains(&candidate) {
+                    operator = candidate.clone();
+                    length = candidate.chars().count() - 1;
                 }
-
-                result.push(Token {
-                    token_type: TokenType::Operator,
-                    token_value: operator,
-                });
-                continue;
             }
+            for _ in 0..length {
+                chars.next();
+            }
+
+            result.push(Token {
+                token_type: TokenType::Operator,
+                token_value: operator,
+                position: position.clone(),
+                literal: None,
+            });
+            continue;
+        }
 
-            // 数字
-            if char.is_ascii_digit() {
-                let mut number = char.to_string();
+        // 数字
+        // 数字常量不含符号，`-1` 识别为运算符 `-` 与常量 `1`，`-` 是一元负号还是减号由语法分析决定
+        if char.is_ascii_digit() {
+            let mut number = char.to_string();
 
+            // 十六进制数，以 0x 或 0X 开头
+            if char == '0' && matches!(chars.peek(), Some('x' | 'X')) {
+                number.push(chars.next().unwrap());
                 while let Some(next_char) = chars.peek() {
-                    if next_char.is_ascii_digit() || *next_char == '.' {
-                        number.push(*next_char);
-                        chars.next();
-                    } else {
+                    if is_separator(next_char, config) {
                         break;
                     }
+                    number.push(*next_char);
+                    chars.next();
                 }
 
-                // 防止出现数字开头的非法标识符
-                if number.len() == line.len()
-                    || OPERATOR.contains(&chars.peek().unwrap().to_string().as_str())
-                    || DELIMITERS.contains(&chars.peek().unwrap().to_string().as_str())
-                    || chars.peek().unwrap() == &' '
-                {
-                    if number.contains('.') {
-                        let idx = number.find('.').unwrap();
-                        if number[idx + 1..].contains('.') {
-                            result.push(Token {
-                                token_type: TokenType::Error(LexicalError::new(
-                                    "Invalid float number",
-                                )),
-                                token_value: number,
-                            });
-                            error = true;
-                        } else {
-                            result.push(Token {
-                                token_type: TokenType::Constant,
-                                token_value: number,
-                            });
-                        }
-                    } else {
-                        result.push(Token {
-                            token_type: TokenType::Constant,
-                            token_value: number,
-                        });
-                    }
+                let digits = &number[2..];
+                if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    result.push(Token {
+                        token_type: TokenType::Constant,
+                        token_value: number,
+                        position: position.clone(),
+                        literal: None,
+                    });
                 } else {
-                    while let Some(next_char) = chars.peek() {
-                        if next_char == &' '
-                            || OPERATOR.contains(&next_char.to_string().as_str())
-                            || DELIMITERS.contains(&next_char.to_string().as_str())
-                        {
-                            break;
-                        } else {
-                            number.push(*next_char);
-                            chars.next();
-                        }
-                    }
                     result.push(Token {
-                        token_type: TokenType::Error(LexicalError::new("Invalid identifier")),
+                        token_type: TokenType::Error(LexicalError::new(
+                            "Invalid hexadecimal number",
+                        )),
                         token_value: number,
+                        position: position.clone(),
+                        literal: None,
                     });
-                    error = true;
                 }
                 continue;
             }
 
-            // 字符常数
-            if char == '\'' {
-                let mut constant = char.to_string();
+            while let Some(next_char) = chars.peek() {
+                if next_char.is_ascii_digit() || *next_char == '.' {
+                    number.push(*next_char);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
 
+            // 科学计数法的指数部分，形如 e10、E-3，至少要有一位数字
+            // 只有紧跟在 e、E 之后的符号属于数字常量
+            let mut invalid_exponent = false;
+            if matches!(chars.peek(), Some('e' | 'E')) {
+                number.push(chars.next().unwrap());
+                if matches!(chars.peek(), Some('+' | '-')) {
+                    number.push(chars.next().unwrap());
+                }
+                let mut digits = 0;
                 while let Some(next_char) = chars.peek() {
-                    if *next_char == '\'' {
-                        constant.push(*next_char);
+                    if next_char.is_ascii_digit() {
+                        number.push(*next_char);
                         chars.next();
-                        break;
+                        digits += 1;
                     } else {
-                        constant.push(*next_char);
-                        chars.next();
+                        break;
                     }
                 }
+                invalid_exponent = digits == 0;
+            }
 
-                if constant.len() == 3 {
+            // 防止出现数字开头的非法标识符，数字后为行尾或分隔单词的字符时才是合法的数字
+            let at_boundary = match chars.peek() {
+                Some(c) => is_separator(c, config),
+                None => true,
+            };
+            if at_boundary {
+                if invalid_exponent {
                     result.push(Token {
-                        token_type: TokenType::Constant,
-                        token_value: constant,
+                        token_type: TokenType::Error(LexicalError::new("Invalid exponent")),
+                        token_value: number,
+                        position: position.clone(),
+                        literal: None,
+                    });
+                } else if number.contains('.') {
+                    let idx = number.find('.').unwrap();
+                    if number[idx + 1..].contains('.') {
+                        result.push(Token {
+                            token_type: TokenType::Error(LexicalError::new("Invalid float number")),
+                            token_value: number,
+                            position: position.clone(),
+                            literal: None,
+                        });
+                    } else {
+                        result.push(Token {
+                            token_type: TokenType::Constant,
+                            token_value: number,
+                            position: position.clone(),
+                            literal: None,
+                        });
+                    }
+                }
+                // 八进制数，以 0 开头且只包含 0-7
+                else if number.len() > 1
+                    && number.starts_with('0')
+                    && !number.contains(['e', 'E'])
+                    && !number.chars().all(|c| ('0'..='7').contains(&c))
+                {
+                    result.push(Token {
+                        token_type: TokenType::Error(LexicalError::new("Invalid octal number")),
+                        token_value: number,
+                        position: position.clone(),
+                        literal: None,
                     });
                 } else {
                     result.push(Token {
-                        token_type: TokenType::Error(LexicalError::new(
-                            "Invalid character constant",
-                        )),
-                        token_value: constant,
+                        token_type: TokenType::Constant,
+                        token_value: number,
+                        position: position.clone(),
+                        literal: None,
                     });
-                    error = true;
                 }
-                continue;
-            }
-
-            // 字符串常数
-            if char == '"' {
-                let mut constant = char.to_string();
-
+            } else {
                 while let Some(next_char) = chars.peek() {
-                    if *next_char == '"' {
-                        constant.push(*next_char);
-                        chars.next();
+                    if is_separator(next_char, config) {
                         break;
                     } else {
-                        constant.push(*next_char);
+                        number.push(*next_char);
                         chars.next();
                     }
                 }
-
-                // 字符串首尾必须有双引号
-                if constant.len() >= 2 && constant.starts_with('"') && constant.ends_with('"') {
-                    result.push(Token {
-                        token_type: TokenType::Constant,
-                        token_value: constant,
-                    });
-                } else {
-                    result.push(Token {
-                        token_type: TokenType::Error(LexicalError::new("Invalid string constant")),
-                        token_value: constant,
-                    });
-                    error = true;
-                }
-                continue;
+                result.push(Token {
+                    token_type: TokenType::Error(LexicalError::new("Invalid identifier")),
+                    token_value: number,
+                    position: position.clone(),
+                    literal: None,
+                });
             }
+            continue;
+        }
+
+        // 字符常数
+        if char == '\'' {
+            let mut constant = char.to_string();
 
-            // 标识符 & 关键字
-            let mut identifier = char.to_string();
             while let Some(next_char) = chars.peek() {
-                if next_char == &' '
-                    || OPERATOR.contains(&next_char.to_string().as_str())
-                    || DELIMITERS.contains(&next_char.to_string().as_str())
-                {
+                if *next_char == '\'' {
+                    constant.push(*next_char);
+                    chars.next();
                     break;
                 } else {
-                    identifier.push(*next_char);
+                    constant.push(*next_char);
                     chars.next();
                 }
             }
 
-            if KEYWORDS.contains(&identifier.as_str()) {
+            if constant.len() == 3 {
                 result.push(Token {
-                    token_type: TokenType::Keyword,
-                    token_value: identifier,
+                    token_type: TokenType::Constant,
+                    token_value: constant,
+                    position: position.clone(),
+                    literal: None,
                 });
             } else {
-                // 首字母应为字母或下划线
-                let first_char = identifier.chars().next().unwrap();
-                if first_char == '_' || first_char.is_alphabetic() {
-                    result.push(Token {
-                        token_type: TokenType::Identifier,
-                        token_value: identifier,
-                    })
-                } else {
-                    result.push(Token {
-                        token_type: TokenType::Error(LexicalError::new("Invalid identifier")),
-                        token_value: identifier,
-                    });
+                result.push(Token {
+                    token_type: TokenType::Error(LexicalError::new("Invalid character constant")),
+                    token_value: constant,
+                    position: position.clone(),
+                    literal: None,
+                });
+            }
+            continue;
+        }
+
+        // 字符串常数
+        if char == '"' {
+            let mut constant = char.to_string();
+            // 是否读到了结束的双引号，`\"` 是转义的双引号，不结束字符串
+            let mut terminated = false;
+            let mut escaped = false;
+
+            for next_char in chars.by_ref() {
+                constant.push(next_char);
+                if escaped {
+                    escaped = false;
+                } else if next_char == '\\' {
+                    escaped = true;
+                } else if next_char == '"' {
+                    terminated = true;
+                    break;
+                }
+            }
+
+            // 按行识别单词，行尾之前没有结束的双引号时字符串没有闭合，错误的位置为字符串的开始
+            if terminated {
+                result.push(Token {
+                    token_type: TokenType::Constant,
+                    token_value: constant,
+                    position: position.clone(),
+                    literal: None,
+                });
+            } else {
+                result.push(Token {
+                    token_type: TokenType::Error(LexicalError::new("unterminated string constant")),
+                    token_value: constant,
+                    position: position.clone(),
+                    literal: None,
+                });
+            }
+            continue;
+        }
+
+        // 标识符 & 关键字
+        let mut identifier = char.to_string();
+        while let Some(next_char) = chars.peek() {
+            if is_separator(next_char, config) {
+                break;
+            } else {
+                identifier.push(*next_char);
+                chars.next();
+            }
+        }
+
+        if config.keywords.contains(&identifier) {
+            result.push(Token {
+                token_type: TokenType::Keyword,
+                token_value: identifier,
+                position: position.clone(),
+                literal: None,
+            });
+        } else {
+            // 首字母应为字母或下划线，其余字符应为字母、数字或下划线，字母与数字包括非ASCII的Unicode字符
+            let mut identifier_chars = identifier.chars();
+            let first_char = identifier_chars.next().unwrap();
+            if (first_char == '_' || first_char.is_alphabetic())
+                && identifier_chars.all(|c| c == '_' || c.is_alphanumeric())
+            {
+                result.push(Token {
+                    token_type: TokenType::Identifier,
+                    token_value: identifier,
+                    position: position.clone(),
+                    literal: None,
+                })
+            } else {
+                result.push(Token {
+                    token_type: TokenType::Error(LexicalError::new("Invalid identifier")),
+                    token_value: identifier,
+                    position: position.clone(),
+                    literal: None,
+                });
+            }
+        }
+    }
+
+    // 按照配置检查数字常量
+    if let Some(validator) = &config.number_validator {
+        for token in result.iter_mut() {
+            let is_number = token.token_type == TokenType::Constant
+                && token.token_value.starts_with(|c: char| c.is_ascii_digit());
+            if !is_number {
+                continue;
+            }
+            if let Err(message) = validator.validate(&token.token_value) {
+                token.token_type = TokenType::Error(LexicalError::new(&message));
+            }
+        }
+    }
+
+    // 解析常量的值，需在检查数字常量之后，被判为错误的常量没有值
+    for token in result.iter_mut() {
+        if token.token_type == TokenType::Constant {
+            token.literal = LiteralValue::parse(&token.token_value);
+        }
+    }
+
+    // 记录识别失败的单词的位置
+    for token in result.iter_mut() {
+        if let TokenType::Error(e) = &mut token.token_type {
+            e.line = Some(token.position.line);
+            e.column = Some(token.position.column);
+        }
+    }
+
+    result
+}
+
+/// 是否为分隔单词的字符，即空格、运算符或分隔符
+fn is_separator(char: &char, config: &LexerConfig) -> bool {
+    char == &' '
+        || config.operators.contains(&char.to_string())
+        || config.delimiters.contains(&char.to_string())
+}
+
+/// 记录当前列号的字符迭代器
+#[derive(Clone)]
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// 最近一次读出的字符的列号，从1开始
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            column: 0,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.chars.next();
+        if char.is_some() {
+            self.column += 1;
+        }
+        char
+    }
+}
+
+/// 预处理后的一行源代码
+#[derive(Debug)]
+struct SourceLine {
+    /// 所在文件
+    file: Option<String>,
+    /// 原始行号，从1开始
+    number: usize,
+    /// 内容中每个字符在原始行中的列号
+    columns: Vec<usize>,
+    /// 删除首尾空格后的内容
+    text: String,
+    /// `#define` 指示定义的宏名与替换文本，不是 `#define` 指示时为 `None`
+    define: Option<(String, String)>,
+}
+
+/// 预处理输入
+///
+/// 1. 去除注释
+/// 2. 删除首尾空格，删除空行，按空格分割转为 Vec
+/// 3. 处理 `#line 行号 "文件名"` 指示，修改其后各行的行号与所在文件
+/// 4. 解析 `#define 名字 替换文本` 指示，是否展开由词法分析时的配置决定
+/// 5. 按照配置合并连续的空白字符
+fn preprocess(input: String, config: &LexerConfig) -> Result<Vec<SourceLine>, LexicalError> {
+    let input_remove_comment = remove_comment(input, config)?;
+
+    let mut lines = vec![];
+    let mut file = None;
+    let mut number = 0;
+    for raw in input_remove_comment.lines() {
+        number += 1;
+        let text = raw.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        if let Some(directive) = strip_directive(text, "#line") {
+            let (line, name) = parse_line_directive(directive)?;
+            // 指示的下一行的行号为指示给出的行号
+            number = line - 1;
+            if name.is_some() {
+                file = name;
+            }
+            continue;
+        }
+
+        if let Some(directive) = text.strip_prefix("#define") {
+            lines.push(SourceLine {
+                file: file.clone(),
+                number,
+                columns: vec![],
+                text: String::new(),
+                define: Some(parse_define_directive(directive)?),
+            });
+            continue;
+        }
+
+        let offset = raw.chars().take_while(|c| c.is_whitespace()).count();
+        let (text, columns) = if config.normalize_whitespace {
+            normalize_whitespace(text, offset)
+        } else {
+            let columns = (offset + 1..=offset + text.chars().count()).collect();
+            (text.to_string(), columns)
+        };
+        lines.push(SourceLine {
+            file: file.clone(),
+            number,
+            columns,
+            text,
+            define: None,
+        });
+    }
+
+    Ok(lines)
+}
+
+/// # 将字符串与字符常量之外的连续空白字符合并为一个空格
+///
+/// `offset` 为 `text` 之前被删除的字符数，返回合并后的内容与其中每个字符在原始行中的列号
+fn normalize_whitespace(text: &str, offset: usize) -> (String, Vec<usize>) {
+    let mut result = String::new();
+    let mut columns = vec![];
+    // 当前所在常量的引号
+    let mut quote = None;
+    let mut escaped = false;
+    let mut last_space = false;
+
+    for (i, char) in text.chars().enumerate() {
+        let column = offset + i + 1;
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if char == '\\' {
+                    escaped = true;
+                } else if char == q {
+                    quote = None;
+                }
+            }
+            None if char.is_whitespace() => {
+                if !last_space {
+                    result.push(' ');
+                    columns.push(column);
+                    last_space = true;
+                }
+                continue;
+            }
+            None => {
+                if char == '"' || char == '\'' {
+                    quote = Some(char);
                 }
             }
         }
+        result.push(char);
+        columns.push(column);
+        last_space = false;
     }
 
-    (result, error)
+    (result, columns)
+}
+
+/// 解析 `#line` 指示，格式为 `#line 行号` 或 `#line 行号 "文件名"`
+fn parse_line_directive(directive: &str) -> Result<(usize, Option<String>), LexicalError> {
+    let directive = directive.trim();
+    let (line, name) = match directive.split_once(' ') {
+        Some((line, name)) => (line, Some(name.trim())),
+        None => (directive, None),
+    };
+
+    let line = line
+        .parse::<usize>()
+        .ok()
+        .filter(|line| *line > 0)
+        .ok_or_else(|| LexicalError::new(&format!("invalid #line directive: {}", directive)))?;
+    let name = match name {
+        Some(name) if name.len() >= 2 && name.starts_with('"') && name.ends_with('"') => {
+            Some(name[1..name.len() - 1].to_string())
+        }
+        Some(_) => {
+            return Err(LexicalError::new(&format!(
+                "invalid #line directive: {}",
+                directive
+            )))
+        }
+        None => None,
+    };
+
+    Ok((line, name))
+}
+
+/// 解析 `#define` 指示，格式为 `#define 名字 替换文本`，替换文本可以为空
+fn parse_define_directive(directive: &str) -> Result<(String, String), LexicalError> {
+    let invalid = || LexicalError::new(&format!("invalid #define directive: {}", directive.trim()));
+    // #define 与名字之间必须有空白
+    if !directive.starts_with(char::is_whitespace) {
+        return Err(invalid());
+    }
+    let directive = directive.trim();
+    let (name, value) = match directive.split_once(char::is_whitespace) {
+        Some((name, value)) => (name, value.trim()),
+        None => (directive, ""),
+    };
+
+    let valid_name = name
+        .chars()
+        .next()
+        .is_some_and(|c| c == '_' || c.is_alphabetic())
+        && name.chars().all(|c| c == '_' || c.is_alphanumeric());
+    if !valid_name {
+        return Err(invalid());
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// # 展开源文件中的 `#include "file"`
+///
+/// 在文件开头以及被包含文件的前后插入 `#line` 指示，记录之后各行的原始位置
+///
+/// 展开前先删除注释，被注释掉的 `#include` 不会被展开，删除注释不改变各行的行号
+///
+/// `including` 为正在展开的文件，用于检测循环包含，以规范化的路径比较，
+/// 因此 `./a.h` 与 `sub/../a.h` 是同一个文件
+fn expand_includes(path: &Path, including: &mut Vec<PathBuf>) -> Result<String, LexicalError> {
+    let read_error =
+        |e: std::io::Error| LexicalError::new(&format!("failed to read {}: {}", path.display(), e));
+    let canonical = std::fs::canonicalize(path).map_err(read_error)?;
+    if including.contains(&canonical) {
+        return Err(LexicalError::new(&format!(
+            "recursive #include of {}",
+            path.display()
+        )));
+    }
+    let input = std::fs::read_to_string(path).map_err(read_error)?;
+    let name = path.display().to_string();
+    let input = remove_comment(input, &LexerConfig::default()).map_err(|mut e| {
+        e.message = format!("{}: {}", name, e.message);
+        e
+    })?;
+    including.push(canonical);
+
+    let mut result = format!("#line 1 \"{}\"\n", name);
+    for (i, line) in input.lines().enumerate() {
+        match strip_directive(line.trim(), "#include") {
+            Some(include) => {
+                let include = include.trim();
+                if include.len() < 2 || !include.starts_with('"') || !include.ends_with('"') {
+                    return Err(LexicalError::new(&format!(
+                        "invalid #include at {}:{}",
+                        name,
+                        i + 1
+                    )));
+                }
+                let include_path = path
+                    .parent()
+                    .unwrap_or(Path::new(""))
+                    .join(&include[1..include.len() - 1]);
+                result.push_str(&expand_includes(&include_path, including)?);
+                // 回到当前文件的下一行
+                result.push_str(&format!("#line {} \"{}\"\n", i + 2, name));
+            }
+            None => {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+    }
+
+    including.pop();
+    Ok(result)
 }
 
-/// 预处理输入
-///
-/// 1. 去除注释
-/// 2. 删除首尾空格，删除空行，按空格分割转为 Vec
-fn preprocess(input: String) -> Result<Vec<String>, LexicalError> {
-    let input_remove_comment = remove_comment(input)?;
-
-    let lines = input_remove_comment
-        .lines()
-        .map(|line| line.trim().to_string())
-        .filter(|line| !line.is_empty())
-        .collect();
+/// 去掉行首的预处理指示名，指示名之后应为空白或行尾，因此 `#lineX` 不是 `#line` 指示
+fn strip_directive<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    line.strip_prefix(name)
+        .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
 
-    Ok(lines)
+/// 配置的多行注释的开始与结束符号，任一符号为空时不识别多行注释
+fn block_comment_chars(config: &LexerConfig) -> Option<(Vec<char>, Vec<char>)> {
+    config
+        .block_comment
+        .as_ref()
+        .filter(|(open, close)| !open.is_empty() && !close.is_empty())
+        .map(|(open, close)| (open.chars().collect(), close.chars().collect()))
 }
 
 /// 删除注释
 ///
-/// 删除单行注释，删除多行注释
+/// 删除单行注释，将多行注释替换为等长的空格
+///
+/// 多行注释中的换行被保留，其余字符(包括开始与结束符号)替换为空格，
+/// 因此注释之后的单词的行号和列号与原始输入一致
+///
+/// 配置允许嵌套时记录多行注释的嵌套深度，只有与最外层开始符号匹配的结束符号才结束注释
+///
+/// 字符串常量与字符常量原样保留，其中的注释符号不视为注释，
+/// 常量在匹配的未转义引号或行尾处结束
+///
+/// 注释的符号由配置给出，默认为C语言的注释:
 ///
 /// 单行注释 format: // xxx
 /// 多行注释 format: /* xxx */
-fn remove_comment(input: String) -> Result<String, LexicalError> {
+fn remove_comment(input: String, config: &LexerConfig) -> Result<String, LexicalError> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let line_comments = config
+        .line_comments
+        .iter()
+        .filter(|c| !c.is_empty())
+        .map(|c| c.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let block_comment = block_comment_chars(config);
+
     let mut result = String::new();
-    let mut chars = input.chars().peekable();
     let mut row = 1;
     let mut column = 0;
-    while let Some(char) = chars.next() {
+    let mut i = 0;
+    while i < chars.len() {
+        let char = chars[i];
         if char == '\n' {
             row += 1;
             column = 0;
             result.push(char);
-        } else {
-            column += 1;
-            match char {
-                '/' => match chars.next() {
-                    Some(next) => match next {
-                        '/' => {
-                            while let Some(char) = chars.next() {
-                                if char == '\n' {
-                                    row += 1;
-                                    column = 0;
-                                    result.push(char);
-                                    break;
-                                }
-                            }
-                        }
-                        '*' => {
-                            let mut error = true;
-                            let start_pos = (row, column);
-                            while let Some(char) = chars.next() {
-                                match char {
-                                    '\n' => {
-                                        row += 1;
-                                        column = 0;
-                                    }
-                                    '*' => {
-                                        if let Some(next) = chars.next() {
-                                            if next == '/' {
-                                                error = false;
-                                                break;
-                                            }
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                            if error {
-                                return Err(LexicalError::new(&format!(
-                                    "multiline comment not closed at {}:{}",
-                                    start_pos.0, start_pos.1
-                                )));
-                            }
-                        }
-                        _ => {
-                            result.push(char);
-                            result.push(next);
-                        }
-                    },
-                    None => {
-                        result.push(char);
-                    }
-                },
-                _ => {
-                    result.push(char);
+            i += 1;
+            continue;
+        }
+
+        if let Some((open, close)) = block_comment
+            .as_ref()
+            .filter(|(open, _)| chars[i..].starts_with(open))
+        {
+            let start_pos = (row, column + 1);
+            // 注释的嵌套深度
+            let mut depth = 1;
+            i += open.len();
+            column += open.len();
+            result.push_str(&" ".repeat(open.len()));
+            while depth > 0 && i < chars.len() {
+                if chars[i] == '\n' {
+                    // 保留注释中的换行，使之后的行号保持不变
+                    row += 1;
+                    column = 0;
+                    result.push('\n');
+                    i += 1;
+                } else if chars[i..].starts_with(close) {
+                    depth -= 1;
+                    i += close.len();
+                    column += close.len();
+                    result.push_str(&" ".repeat(close.len()));
+                } else if config.allow_nested_comments && chars[i..].starts_with(open) {
+                    depth += 1;
+                    i += open.len();
+                    column += open.len();
+                    result.push_str(&" ".repeat(open.len()));
+                } else {
+                    // 其余字符替换为空格，使之后的列号保持不变
+                    i += 1;
+                    column += 1;
+                    result.push(' ');
+                }
+            }
+            if depth > 0 {
+                return Err(LexicalError::at(
+                    "multiline comment not closed",
+                    start_pos.0,
+                    start_pos.1,
+                ));
+            }
+            continue;
+        }
+
+        if line_comments.iter().any(|c| chars[i..].starts_with(c)) {
+            // 删除到行尾，换行由下一次循环处理
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        i += 1;
+        column += 1;
+        result.push(char);
+        if char == '"' || char == '\'' {
+            let mut escaped = false;
+            // 未闭合的常量在行尾结束，由词法分析报告错误
+            while i < chars.len() && chars[i] != '\n' {
+                let next = chars[i];
+                i += 1;
+                column += 1;
+                result.push(next);
+                if escaped {
+                    escaped = false;
+                } else if next == '\\' {
+                    escaped = true;
+                } else if next == char {
+                    break;
                 }
             }
         }
@@ -396,19 +1446,34 @@ int main() {
 }
 "#,
         );
-        let output = remove_comment(input).unwrap();
+        let output = remove_comment(input, &LexerConfig::default()).unwrap();
         assert_eq!(
             output,
-            r#"
-
-int main() {
+            format!(
+                "\n\nint main() {{\n   \n{}\n{}\n  \n    int a = {} 1;\n{}",
+                " ".repeat(31),
+                " ".repeat(14),
+                " ".repeat(40),
+                "    printf(\"Hello, world!\");\n    return 0;\n}\n"
+            )
+        );
+    }
 
-    int a =  1;
-    printf("Hello, world!");
-    return 0;
-}
-"#
+    #[test]
+    fn test_remove_comment_keeps_literals() {
+        let config = LexerConfig::default();
+        let input = String::from("char* url = \"a//b\";");
+        assert_eq!(remove_comment(input.clone(), &config).unwrap(), input);
+        let input = String::from("s = \"/* not \\\" a comment */\"; c = '/'; // tail");
+        assert_eq!(
+            remove_comment(input, &config).unwrap(),
+            "s = \"/* not \\\" a comment */\"; c = '/'; "
         );
+
+        let (tokens, _) = lexical_analysis(String::from("char* url = \"a//b\";")).unwrap();
+        assert!(tokens
+            .iter()
+            .any(|t| t.token_type == TokenType::Constant && t.token_value == "\"a//b\""));
     }
 
     #[test]
@@ -427,14 +1492,254 @@ int main() {
 }
 "#,
         );
-        let result = remove_comment(input);
+        let result = remove_comment(input, &LexerConfig::default());
         assert!(result.is_err());
+        let error = result.unwrap_err();
+        assert_eq!(error.to_string(), "multiline comment not closed at 5:5");
+        assert_eq!((error.line, error.column), (Some(5), Some(5)));
+    }
+
+    #[test]
+    // 3.14 是输入中的常量，不是π的近似值
+    #[allow(clippy::approx_constant)]
+    fn test_literal_value() {
+        let input = r#"x = 3.14 + 42 + 0x1F + 017 + 1e3 + 'c' + "ab" + "a\"b\n";"#;
+        let (tokens, _) = lexical_analysis(input.to_string()).unwrap();
+        let literals = tokens
+            .iter()
+            .filter(|t| t.token_type == TokenType::Constant)
+            .map(|t| t.literal.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            literals,
+            vec![
+                LiteralValue::Float(3.14),
+                LiteralValue::Int(42),
+                LiteralValue::Int(31),
+                LiteralValue::Int(15),
+                LiteralValue::Float(1000.0),
+                LiteralValue::Char('c'),
+                LiteralValue::Str("ab".to_string()),
+                LiteralValue::Str("a\"b\n".to_string()),
+            ]
+        );
+        // 非常量没有值
+        assert_eq!(tokens[0].literal, None);
+        assert_eq!(
+            Token::new(TokenType::Constant, "7").literal,
+            Some(LiteralValue::Int(7))
+        );
+    }
+
+    #[test]
+    fn test_unicode_identifier() {
+        let (tokens, error) = lexical_analysis("fürimmer = _名字1 + ñ;".to_string()).unwrap();
+        assert!(!error);
+        assert_eq!(tokens[0], Token::new(TokenType::Identifier, "fürimmer"));
+        assert_eq!(tokens[2], Token::new(TokenType::Identifier, "_名字1"));
+        assert_eq!(tokens[4], Token::new(TokenType::Identifier, "ñ"));
+
+        // 标识符中间出现非字母数字的字符
+        let (tokens, error) = lexical_analysis("a$b = 1;".to_string()).unwrap();
+        assert!(error);
+        assert_eq!(tokens[0].token_value, "a$b");
+        assert!(matches!(tokens[0].token_type, TokenType::Error(_)));
+        assert_eq!(
+            tokens[0].token_type.to_string(),
+            "Error: Invalid identifier at 1:1"
+        );
+    }
+
+    #[test]
+    fn test_minus_sign() {
+        let split = |input: &str| {
+            let (tokens, _) = lexical_analysis(input.to_string()).unwrap();
+            tokens
+                .into_iter()
+                .map(|t| (t.token_type.kind(), t.token_value))
+                .collect::<Vec<_>>()
+        };
+        let operator = |v: &str| (TokenTypeKind::Operator, v.to_string());
+        let constant = |v: &str| (TokenTypeKind::Constant, v.to_string());
+        let id = |v: &str| (TokenTypeKind::Identifier, v.to_string());
+
+        // 符号总是单独的运算符，不会并入数字常量
+        assert_eq!(split("a-1"), vec![id("a"), operator("-"), constant("1")]);
+        assert_eq!(split("a - 1"), vec![id("a"), operator("-"), constant("1")]);
+        assert_eq!(split("-1"), vec![operator("-"), constant("1")]);
+        assert_eq!(
+            split("1-2"),
+            vec![constant("1"), operator("-"), constant("2")]
+        );
+        assert_eq!(
+            split("x=-1"),
+            vec![id("x"), operator("="), operator("-"), constant("1")]
+        );
+        assert_eq!(split("a--1"), vec![id("a"), operator("--"), constant("1")]);
+
+        // 指数部分的符号属于常量
         assert_eq!(
-            result.unwrap_err().to_string(),
-            "multiline comment not closed at 5:5"
+            split("1e-3-1"),
+            vec![constant("1e-3"), operator("-"), constant("1")]
         );
     }
 
+    #[test]
+    fn test_filter_tokens() {
+        let input = "int main() {\n    int a = 1 + 2 * 3;\n    a += a == 1;\n    return a;\n}";
+        let (tokens, _) = lexical_analysis(input.to_string()).unwrap();
+        let operators = filter_tokens(tokens.clone(), &[TokenTypeKind::Operator]);
+        let values = operators
+            .iter()
+            .map(|t| t.token_value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec!["=", "+", "*", "+=", "=="]);
+        assert!(operators
+            .iter()
+            .all(|t| t.token_type.kind() == TokenTypeKind::Operator));
+
+        // 去掉分隔符
+        let keep = [
+            TokenTypeKind::Keyword,
+            TokenTypeKind::Identifier,
+            TokenTypeKind::Constant,
+            TokenTypeKind::Operator,
+        ];
+        let filtered = filter_tokens(tokens.clone(), &keep);
+        assert_eq!(filtered.len(), tokens.len() - 7);
+        assert!(filter_tokens(tokens, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_comment_styles() {
+        let config = LexerConfig {
+            line_comments: vec!["#".to_string()],
+            block_comment: None,
+            ..Default::default()
+        };
+        let (tokens, error) =
+            lexical_analysis_with_config("x = 1 # comment".to_string(), &config).unwrap();
+        assert!(!error);
+        let values = tokens
+            .iter()
+            .map(|t| t.token_value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec!["x", "=", "1"]);
+
+        // 字符串中的注释符号不是注释，未配置的 // 也不再是注释
+        let output = remove_comment("s = \"#\" -- a\n// b # c".to_string(), &config).unwrap();
+        assert_eq!(output, "s = \"#\" -- a\n// b ");
+
+        let config = LexerConfig {
+            line_comments: vec!["--".to_string()],
+            block_comment: Some(("{-".to_string(), "-}".to_string())),
+            ..Default::default()
+        };
+        let output = remove_comment("a -- b\nc {- d\n -} e".to_string(), &config).unwrap();
+        assert_eq!(output, "a \nc     \n    e");
+        let error = remove_comment("a\n  {- b".to_string(), &config).unwrap_err();
+        assert_eq!(error.to_string(), "multiline comment not closed at 2:3");
+        // 未配置的 /* 不是注释
+        assert!(remove_comment("a /* b".to_string(), &config).is_ok());
+    }
+
+    #[test]
+    fn test_inline_comment_position() {
+        let code = String::from("int a = /* inline */ 1;\n/* a\n b */ c = /**/2;");
+        let (result, _) = lexical_analysis(code).unwrap();
+        let positions = result
+            .iter()
+            .map(|token| {
+                (
+                    token.token_value.as_str(),
+                    token.position.line,
+                    token.position.column,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            vec![
+                ("int", 1, 1),
+                ("a", 1, 5),
+                ("=", 1, 7),
+                ("1", 1, 22),
+                (";", 1, 23),
+                ("c", 3, 7),
+                ("=", 3, 9),
+                ("2", 3, 15),
+                (";", 3, 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_comment() {
+        let input = "a /* a /* b */ c */ b".to_string();
+
+        // 不允许嵌套时第一个 */ 结束注释
+        let output = remove_comment(input.clone(), &LexerConfig::default()).unwrap();
+        assert_eq!(output, format!("a {} c */ b", " ".repeat(12)));
+
+        let config = LexerConfig {
+            allow_nested_comments: true,
+            ..Default::default()
+        };
+        let output = remove_comment(input, &config).unwrap();
+        assert_eq!(output, format!("a {} b", " ".repeat(17)));
+        let (tokens, _) =
+            lexical_analysis_with_config("x /* a /* b */ c */ y".to_string(), &config).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[1].position.column, 21);
+
+        // 嵌套深度没有回到0时注释没有结束
+        let error = remove_comment("a /* a /* b */ c".to_string(), &config).unwrap_err();
+        assert_eq!(error.to_string(), "multiline comment not closed at 1:3");
+        assert!(remove_comment("a /* a /* b */ c".to_string(), &LexerConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_lexical_errors() {
+        let input = "int 1a = 2;\nfloat b = 1.2.3;".to_string();
+        let output = lexical_analysis_with_errors(input, &LexerConfig::default()).unwrap();
+        assert!(output.has_errors());
+        assert_eq!(output.errors.len(), 2);
+        assert_eq!(output.errors[0].to_string(), "Invalid identifier at 1:5");
+        assert_eq!(output.errors[1].to_string(), "Invalid float number at 2:11");
+        // 合法的token不受影响
+        assert_eq!(output.tokens.len(), 8);
+        assert_eq!(output.tokens[1], Token::new(TokenType::Operator, "="));
+
+        // 没有闭合的多行注释是致命的错误
+        let input = "int a; /* b".to_string();
+        assert!(lexical_analysis_with_errors(input, &LexerConfig::default()).is_err());
+
+        let input = "int a;".to_string();
+        let output = lexical_analysis_with_errors(input, &LexerConfig::default()).unwrap();
+        assert!(!output.has_errors());
+    }
+
+    #[test]
+    fn test_error_position() {
+        let input = "int a = 1;\nint b = 2;\n  int 1c = 3.1.4;".to_string();
+        let (tokens, error) = lexical_analysis(input).unwrap();
+        assert!(error);
+
+        let errors = tokens
+            .iter()
+            .filter_map(|token| match &token.token_type {
+                TokenType::Error(e) => Some(e),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].message, "Invalid identifier");
+        assert_eq!((errors[0].line, errors[0].column), (Some(3), Some(7)));
+        assert_eq!(errors[1].message, "Invalid float number");
+        assert_eq!(errors[1].line, Some(3));
+        assert_eq!(errors[1].to_string(), "Invalid float number at 3:12");
+    }
+
     #[test]
     fn test_preprocess() {
         let input = String::from(
@@ -451,12 +1756,15 @@ int main() {
     }
 "#,
         );
-        let output = preprocess(input).unwrap();
+        let output = preprocess(input, &LexerConfig::default()).unwrap();
         assert_eq!(
-            output,
+            output
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>(),
             vec![
                 "int main() {",
-                "int a =  1;",
+                &format!("int a = {} 1;", " ".repeat(40)),
                 "printf(\"Hello, world!\");",
                 "return 0;",
                 "}"
@@ -490,34 +1798,563 @@ int main() {
         assert_eq!(
             result,
             vec![
-                Token {token_type: TokenType::Keyword, token_value: "int".to_string()},
-                Token {token_type: TokenType::Identifier, token_value: "main".to_string()},
-                Token {token_type: TokenType::Delimiter, token_value: "(".to_string()},
-                Token {token_type: TokenType::Keyword, token_value: "int".to_string()},
-                Token {token_type: TokenType::Identifier, token_value: "a".to_string()},
-                Token {token_type: TokenType::Delimiter, token_value: ",".to_string()},
-                Token {token_type: TokenType::Keyword, token_value: "int".to_string()},
-                Token {token_type: TokenType::Identifier, token_value: "b".to_string()},
-                Token {token_type: TokenType::Delimiter, token_value: ")".to_string()},
-                Token {token_type: TokenType::Delimiter, token_value: "{".to_string()},
-                Token {token_type: TokenType::Keyword, token_value: "int".to_string()},
-                Token {token_type: TokenType::Identifier, token_value: "res".to_string()},
-                Token {token_type: TokenType::Delimiter, token_value: ";".to_string()},
-                Token {token_type: TokenType::Identifier, token_value: "res".to_string()},
-                Token {token_type: TokenType::Operator, token_value: "=".to_string()},
-                Token {token_type: TokenType::Identifier, token_value: "a".to_string()},
-                Token {token_type: TokenType::Operator, token_value: "+".to_string()},
-                Token {token_type: TokenType::Identifier, token_value: "b".to_string()},
-                Token {token_type: TokenType::Delimiter, token_value: ";".to_string()},
-                Token {token_type: TokenType::Keyword, token_value: "int".to_string()},
-                Token {token_type: TokenType::Identifier, token_value: "d".to_string()},
-                Token {token_type: TokenType::Operator, token_value: "=".to_string()},
-                Token {token_type: TokenType::Identifier, token_value: "a".to_string()},
-                Token {token_type: TokenType::Delimiter, token_value: ";".to_string()},
-                Token {token_type: TokenType::Delimiter, token_value: "}".to_string()}
+                Token::new(TokenType::Keyword, "int"),
+                Token::new(TokenType::Identifier, "main"),
+                Token::new(TokenType::Delimiter, "("),
+                Token::new(TokenType::Keyword, "int"),
+                Token::new(TokenType::Identifier, "a"),
+                Token::new(TokenType::Delimiter, ","),
+                Token::new(TokenType::Keyword, "int"),
+                Token::new(TokenType::Identifier, "b"),
+                Token::new(TokenType::Delimiter, ")"),
+                Token::new(TokenType::Delimiter, "{"),
+                Token::new(TokenType::Keyword, "int"),
+                Token::new(TokenType::Identifier, "res"),
+                Token::new(TokenType::Delimiter, ";"),
+                Token::new(TokenType::Identifier, "res"),
+                Token::new(TokenType::Operator, "="),
+                Token::new(TokenType::Identifier, "a"),
+                Token::new(TokenType::Operator, "+"),
+                Token::new(TokenType::Identifier, "b"),
+                Token::new(TokenType::Delimiter, ";"),
+                Token::new(TokenType::Keyword, "int"),
+                Token::new(TokenType::Identifier, "d"),
+                Token::new(TokenType::Operator, "="),
+                Token::new(TokenType::Identifier, "a"),
+                Token::new(TokenType::Delimiter, ";"),
+                Token::new(TokenType::Delimiter, "}")
+            ]
+        );
+        assert!(!error);
+    }
+
+    #[test]
+    fn test_relex_from() {
+        let source = "int main() {\n    int a = 1;\n    /* a\n       b */ int b = 2;\n    return a + b;\n}\n";
+        let (previous, _) = lexical_analysis(source.to_string()).unwrap();
+        let check = |edited: &str, edit_offset: usize| {
+            let tokens = relex_from(&previous, edited, edit_offset);
+            let (expected, _) = lexical_analysis(edited.to_string()).unwrap();
+            assert_eq!(tokens.len(), expected.len());
+            for (token, expected) in tokens.iter().zip(&expected) {
+                assert_eq!(token, expected);
+                assert_eq!(token.position, expected.position);
+            }
+            tokens
+        };
+
+        // 修改第2行的 `1`，第1行的单词原样复用
+        let offset = source.find('1').unwrap();
+        let edited = source.replacen('1', "7", 1);
+        let tokens = check(&edited, offset);
+        let reused = previous.iter().filter(|t| t.position.line < 2).count();
+        assert_eq!(reused, 5);
+        for (token, old) in tokens.iter().zip(&previous).take(reused) {
+            assert_eq!(token, old);
+            assert_eq!(token.position, old.position);
+        }
+        assert_eq!(tokens[reused + 3].token_value, "7");
+
+        // 修改多行注释中的字符，从注释开始的行重新识别
+        let offset = source.find('b').unwrap();
+        let mut edited = source.to_string();
+        edited.replace_range(offset..offset + 1, "c");
+        check(&edited, offset);
+
+        // 删除注释的结尾，之后的内容都成为注释
+        let offset = source.find("*/").unwrap();
+        let mut edited = source.to_string();
+        edited.replace_range(offset..offset + 2, "");
+        let tokens = relex_from(&previous, &edited, offset);
+        let error = tokens.last().unwrap();
+        assert!(matches!(
+            &error.token_type,
+            TokenType::Error(e) if e.line == Some(3) && e.column == Some(5)
+        ));
+    }
+
+    #[test]
+    fn test_lexical_analysis_lossless() {
+        let program = include_str!("../../program.txt");
+        let tokens =
+            lexical_analysis_lossless(program.to_string(), &LexerConfig::default()).unwrap();
+        assert_eq!(tokens_to_source(&tokens), program);
+        // 去掉空白与注释后与一般的词法分析结果相同
+        let significant = tokens
+            .iter()
+            .filter(|t| !matches!(t.token_type, TokenType::Whitespace | TokenType::Comment))
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            significant,
+            lexical_analysis(program.to_string()).unwrap().0
+        );
+        assert_eq!(
+            tokens[0],
+            Token::new(TokenType::Comment, "// This is a note.")
+        );
+        assert_eq!(tokens[1], Token::new(TokenType::Whitespace, "\n"));
+
+        let code = "#define N 10\nint  x = /* a\n b */ \"a  b\";\t// end\r\n";
+        let tokens = lexical_analysis_lossless(code.to_string(), &LexerConfig::default()).unwrap();
+        assert_eq!(tokens_to_source(&tokens), code);
+        assert_eq!(tokens[0], Token::new(TokenType::Comment, "#define N 10"));
+        let comment = tokens
+            .iter()
+            .find(|t| t.token_value.starts_with("/*"))
+            .unwrap();
+        assert_eq!(comment.token_value, "/* a\n b */");
+        assert_eq!((comment.position.line, comment.position.column), (2, 10));
+        let string = tokens
+            .iter()
+            .find(|t| t.token_value.starts_with('"'))
+            .unwrap();
+        assert_eq!(string.token_value, "\"a  b\"");
+        assert_eq!(
+            tokens.last().unwrap(),
+            &Token::new(TokenType::Whitespace, "\r\n")
+        );
+    }
+
+    #[test]
+    fn test_number_validator() {
+        // 不允许有前导零的整数
+        let config = LexerConfig {
+            number_validator: Some(NumberValidator::new(|number| {
+                if number.len() > 1
+                    && number.starts_with('0')
+                    && number.chars().all(|c| c.is_ascii_digit())
+                {
+                    Err(format!("leading zero in `{}`", number))
+                } else {
+                    Ok(())
+                }
+            })),
+            ..Default::default()
+        };
+        let output =
+            lexical_analysis_with_errors("x = 007 + 0 + 0.5 + 70;".to_string(), &config).unwrap();
+        assert_eq!(output.errors.len(), 1);
+        assert_eq!(output.errors[0].message, "leading zero in `007`");
+        assert_eq!(output.errors[0].column, Some(5));
+        let constants = output
+            .tokens
+            .iter()
+            .filter(|t| t.token_type == TokenType::Constant)
+            .map(|t| t.token_value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(constants, vec!["0", "0.5", "70"]);
+
+        // 默认不检查，007 是合法的八进制数
+        let (_, error) = lexical_analysis("x = 007;".to_string()).unwrap();
+        assert!(!error);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let output = lexical_analysis_with_errors(
+            "char* s = \"abc\nint x;".to_string(),
+            &LexerConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(output.errors.len(), 1);
+        let error = &output.errors[0];
+        assert_eq!(error.message, "unterminated string constant");
+        assert_eq!((error.line, error.column), (Some(1), Some(11)));
+        // 下一行的单词不受影响
+        assert_eq!(
+            output.tokens.last().unwrap(),
+            &Token::new(TokenType::Delimiter, ";")
+        );
+
+        // 转义的双引号不结束字符串
+        let (tokens, error) = lexical_analysis(r#"s = "a\"b";"#.to_string()).unwrap();
+        assert!(!error);
+        assert_eq!(tokens[2], Token::new(TokenType::Constant, r#""a\"b""#));
+        let (tokens, error) = lexical_analysis(r#"s = "ab\""#.to_string()).unwrap();
+        assert!(error);
+        assert_eq!(tokens[2].token_value, r#""ab\""#);
+    }
+
+    #[test]
+    fn test_hex_and_octal_number() {
+        let (result, error) = lexical_analysis("int mask = 0xFF;".to_string()).unwrap();
+        assert_eq!(result[3], Token::new(TokenType::Constant, "0xFF"));
+        assert!(!error);
+
+        let (result, error) = lexical_analysis("a = 0755 + 0;".to_string()).unwrap();
+        assert_eq!(result[2].token_type, TokenType::Constant);
+        assert_eq!(result[2].token_value, "0755");
+        assert_eq!(result[4].token_type, TokenType::Constant);
+        assert!(!error);
+
+        let (result, error) = lexical_analysis("0x + 0x1G + 0789;".to_string()).unwrap();
+        assert_eq!(
+            result[0].token_type,
+            TokenType::Error(LexicalError::new("Invalid hexadecimal number"))
+        );
+        assert_eq!(
+            result[2].token_type,
+            TokenType::Error(LexicalError::new("Invalid hexadecimal number"))
+        );
+        assert_eq!(result[2].token_value, "0x1G");
+        assert_eq!(
+            result[4].token_type,
+            TokenType::Error(LexicalError::new("Invalid octal number"))
+        );
+        assert!(error);
+    }
+
+    #[test]
+    fn test_number_at_end_of_line() {
+        let (result, error) = lexical_analysis("return 0\n42\nx = 1.5".to_string()).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(
+            result,
+            vec![
+                Token::new(TokenType::Keyword, "return"),
+                Token::new(TokenType::Constant, "0"),
+                Token::new(TokenType::Constant, "42"),
+                Token::new(TokenType::Identifier, "x"),
+                Token::new(TokenType::Operator, "="),
+                Token::new(TokenType::Constant, "1.5"),
+            ]
+        );
+        assert!(!error);
+    }
+
+    #[test]
+    fn test_exponent_number() {
+        let (result, error) = lexical_analysis("x = 6.022e23 * 2E-3 + 1e+5;".to_string()).unwrap();
+        let constants = result
+            .iter()
+            .filter(|t| t.token_type == TokenType::Constant)
+            .map(|t| t.token_value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(constants, vec!["6.022e23", "2E-3", "1e+5"]);
+        assert!(!error);
+
+        let (result, error) = lexical_analysis("1e\n1e+;".to_string()).unwrap();
+        assert_eq!(
+            result[0],
+            Token::new(
+                TokenType::Error(LexicalError::new("Invalid exponent")),
+                "1e"
+            )
+        );
+        assert_eq!(
+            result[1],
+            Token::new(
+                TokenType::Error(LexicalError::new("Invalid exponent")),
+                "1e+"
+            )
+        );
+        assert_eq!(result[2], Token::new(TokenType::Delimiter, ";"));
+        assert!(error);
+    }
+
+    #[test]
+    fn test_normalize_whitespace() {
+        let config = LexerConfig {
+            normalize_whitespace: true,
+            ..Default::default()
+        };
+        let (irregular, error) = lexical_analysis_with_config(
+            "\tint\ta  =\t\t1;\n  s = \"a\tb  c\";".to_string(),
+            &config,
+        )
+        .unwrap();
+        assert!(!error);
+        let (regular, _) = lexical_analysis("int a = 1;\ns = \"a\tb  c\";".to_string()).unwrap();
+        assert_eq!(irregular, regular);
+        // 字符串常量中的空白字符保持不变
+        assert_eq!(irregular[7].token_value, "\"a\tb  c\"");
+
+        // 位置仍然指向原始输入
+        assert_eq!(irregular[0].position.column, 2);
+        assert_eq!(irregular[1].position.column, 6);
+        assert_eq!(irregular[2].position.column, 9);
+        assert_eq!(irregular[3].position.column, 12);
+        assert_eq!(irregular[7].position.column, 7);
+
+        // 不合并时制表符不会被视为空格
+        let (result, _) = lexical_analysis("int\ta;".to_string()).unwrap();
+        assert_eq!(result[0].token_value, "int\ta");
+    }
+
+    #[test]
+    fn test_lexer_iterator() {
+        let code = "int a;\n".repeat(1000);
+        let tokens = Lexer::new(code)
+            .take(100)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(tokens.len(), 100);
+        assert_eq!(tokens[99], Token::new(TokenType::Keyword, "int"));
+        assert_eq!(tokens[99].position.line, 34);
+
+        let mut lexer = Lexer::new("int a; /* not closed".to_string());
+        assert!(lexer.next().unwrap().is_err());
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn test_token_position() {
+        let code = String::from("int a;\n/*\n  comment\n*/\n    b = 1;");
+        let (result, _) = lexical_analysis(code).unwrap();
+        assert_eq!(
+            result[0].position,
+            Position {
+                file: None,
+                line: 1,
+                column: 1
+            }
+        );
+        assert_eq!(
+            result[1].position,
+            Position {
+                file: None,
+                line: 1,
+                column: 5
+            }
+        );
+        assert_eq!(result[3].token_value, "b");
+        assert_eq!(
+            result[3].position,
+            Position {
+                file: None,
+                line: 5,
+                column: 5
+            }
+        );
+        assert_eq!(
+            result[5].position,
+            Position {
+                file: None,
+                line: 5,
+                column: 9
+            }
+        );
+    }
+
+    #[test]
+    fn test_define_directive() {
+        let code = String::from("#define MAX 10\nint a = MAX;");
+
+        // 默认不展开宏，#define 行被忽略
+        let (tokens, error) = lexical_analysis(code.clone()).unwrap();
+        assert!(!error);
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[3], Token::new(TokenType::Identifier, "MAX"));
+
+        let config = LexerConfig {
+            expand_macros: true,
+            ..Default::default()
+        };
+        let (tokens, error) = lexical_analysis_with_config(code, &config).unwrap();
+        assert!(!error);
+        assert_eq!(tokens[3], Token::new(TokenType::Constant, "10"));
+        assert_eq!((tokens[3].position.line, tokens[3].position.column), (2, 9));
+
+        // 替换文本中的宏同样展开，宏在自身的替换文本中不再展开
+        let code = String::from("#define A B + 1\n#define B 2\n#define X X\nx = A; X;");
+        let (tokens, _) = lexical_analysis_with_config(code, &config).unwrap();
+        let values = tokens
+            .iter()
+            .map(|t| t.token_value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec!["x", "=", "2", "+", "1", ";", "X", ";"]);
+
+        // 宏在定义之后才展开
+        let code = String::from("a = N;\n#define N\nb = N;");
+        let (tokens, _) = lexical_analysis_with_config(code, &config).unwrap();
+        assert_eq!(tokens.len(), 7);
+
+        assert!(lexical_analysis("#define 1A 2".to_string()).is_err());
+        assert!(lexical_analysis("#defineA 2".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_line_directive() {
+        let code = String::from("a;\n#line 10 \"other.c\"\nb;\n#line 3\nc;");
+        let (result, _) = lexical_analysis(code).unwrap();
+        assert_eq!(result[0].position.line, 1);
+        assert_eq!(result[0].position.file, None);
+        assert_eq!(result[2].position.line, 10);
+        assert_eq!(result[2].position.file, Some("other.c".to_string()));
+        assert_eq!(result[4].position.line, 3);
+        assert_eq!(result[4].position.file, Some("other.c".to_string()));
+    }
+
+    #[test]
+    fn test_include_position() {
+        let dir = std::env::temp_dir().join(format!("slr1_include_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("inc.h"), "// header\nint y;\n").unwrap();
+        std::fs::write(dir.join("main.c"), "int x;\n#include \"inc.h\"\nint z;\n").unwrap();
+
+        let (result, error) = lexical_analysis_file(&dir.join("main.c")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(!error);
+
+        let names = result
+            .iter()
+            .map(|t| t.token_value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["int", "x", ";", "int", "y", ";", "int", "z", ";"]
+        );
+
+        let y = &result[4].position;
+        assert!(y.file.as_ref().unwrap().ends_with("inc.h"));
+        assert_eq!(y.line, 2);
+        assert_eq!(y.column, 5);
+
+        let z = &result[7].position;
+        assert!(z.file.as_ref().unwrap().ends_with("main.c"));
+        assert_eq!(z.line, 3);
+    }
+
+    #[test]
+    fn test_include_directive_checks() {
+        let dir = std::env::temp_dir().join(format!("slr1_include_checks_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        // 被注释掉的 #include 不会被展开，missing.h 不存在
+        std::fs::write(
+            dir.join("commented.c"),
+            "// #include \"missing.h\"\n/*\n#include \"missing.h\"\n*/\nint x;\n",
+        )
+        .unwrap();
+        // 路径不同的同一个文件构成循环包含
+        std::fs::write(dir.join("a.h"), "#include \"sub/../a.h\"\n").unwrap();
+        std::fs::write(dir.join("loop.c"), "#include \"./a.h\"\n").unwrap();
+
+        let commented = lexical_analysis_file(&dir.join("commented.c"));
+        let looped = lexical_analysis_file(&dir.join("loop.c"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let (tokens, error) = commented.unwrap();
+        assert!(!error);
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[1].position.line, 5);
+        assert!(looped
+            .unwrap_err()
+            .message
+            .starts_with("recursive #include"));
+
+        // 指示名之后应为空白或行尾
+        let (tokens, _) = lexical_analysis("#lineX 10\nint x;\n".to_string()).unwrap();
+        assert_eq!(tokens[0].token_value, "#lineX");
+    }
+
+    #[test]
+    fn test_longest_operator() {
+        let (result, error) = lexical_analysis("x >>= 2".to_string()).unwrap();
+        assert!(!error);
+        assert_eq!(
+            result,
+            vec![
+                Token::new(TokenType::Identifier, "x"),
+                Token::new(TokenType::Operator, ">>="),
+                Token::new(TokenType::Constant, "2"),
+            ]
+        );
+
+        let values = |code: &str| {
+            lexical_analysis(code.to_string())
+                .unwrap()
+                .0
+                .into_iter()
+                .map(|t| t.token_value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            values("a>>=b>>c>d"),
+            vec!["a", ">>=", "b", ">>", "c", ">", "d"]
+        );
+        assert_eq!(
+            values("a<<=1<<2<=3"),
+            vec!["a", "<<=", "1", "<<", "2", "<=", "3"]
+        );
+        // 不构成更长运算符时回退到已匹配的最长运算符
+        assert_eq!(values("a>>-b"), vec!["a", ">>", "-", "b"]);
+    }
+
+    #[test]
+    fn test_bitwise_operator() {
+        let (result, error) = lexical_analysis("a ^= b & ~c;".to_string()).unwrap();
+        assert!(!error);
+        assert_eq!(
+            result,
+            vec![
+                Token::new(TokenType::Identifier, "a"),
+                Token::new(TokenType::Operator, "^="),
+                Token::new(TokenType::Identifier, "b"),
+                Token::new(TokenType::Operator, "&"),
+                Token::new(TokenType::Operator, "~"),
+                Token::new(TokenType::Identifier, "c"),
+                Token::new(TokenType::Delimiter, ";"),
             ]
         );
+
+        let (result, error) = lexical_analysis("x=a|b^c?p->q:r*=2;".to_string()).unwrap();
+        assert!(!error);
+        let operators = result
+            .iter()
+            .filter(|t| t.token_type == TokenType::Operator)
+            .map(|t| t.token_value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(operators, vec!["=", "|", "^", "?", "->", ":", "*="]);
+    }
+
+    #[test]
+    fn test_custom_keywords() {
+        let mut config = LexerConfig::default();
+        config
+            .keywords
+            .extend(["fn".to_string(), "let".to_string()]);
+        let (tokens, error) =
+            lexical_analysis_with_config("fn main() { let x = 1; }".to_string(), &config).unwrap();
+        assert!(!error);
+        assert_eq!(tokens[0], Token::new(TokenType::Keyword, "fn"));
+        assert_eq!(tokens[1], Token::new(TokenType::Identifier, "main"));
+        assert_eq!(tokens[5], Token::new(TokenType::Keyword, "let"));
+
+        // 默认配置下不是关键字
+        let (tokens, _) = lexical_analysis("fn main".to_string()).unwrap();
+        assert_eq!(tokens[0], Token::new(TokenType::Identifier, "fn"));
+
+        // 自定义运算符与分隔符
+        let mut config = LexerConfig::default();
+        config.operators.insert("=>".to_string());
+        config.operators.insert("@".to_string());
+        config.delimiters.insert(":".to_string());
+        config.operators.remove(":");
+        let (tokens, error) =
+            lexical_analysis_with_config("a => b@c: d".to_string(), &config).unwrap();
         assert!(!error);
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(TokenType::Identifier, "a"),
+                Token::new(TokenType::Operator, "=>"),
+                Token::new(TokenType::Identifier, "b"),
+                Token::new(TokenType::Operator, "@"),
+                Token::new(TokenType::Identifier, "c"),
+                Token::new(TokenType::Delimiter, ":"),
+                Token::new(TokenType::Identifier, "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_operator_table() {
+        // 运算符表中没有重复的运算符
+        let mut operators = OPERATOR.to_vec();
+        operators.sort();
+        operators.dedup();
+        assert_eq!(operators.len(), OPERATOR.len());
     }
 
     #[test]
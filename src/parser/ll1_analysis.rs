@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use log::{debug, error};
+
+use super::{
+    error::{GrammarError, SyntaxError},
+    lexical_analysis::Token,
+    parse_tree::ParseNode,
+    syntax_analysis::{
+        get_first_sets, get_follow_with_first, Grammar, SequenceFirst, END_MARKER, EPSILON,
+    },
+};
+
+/// LL(1)预测分析表，键为(非终结符, 终结符)，值为应当使用的产生式序号
+pub type Ll1Table = HashMap<(String, String), usize>;
+
+/// # 对输入文法G构造LL(1)预测分析表
+///
+/// 对每个产生式 A -> α (第k个产生式)：
+/// 1. 对FIRST(α)中的每个终结符a，置M[A, a]为k
+/// 2. 若ε∈FIRST(α)，对FOLLOW(A)中的每个终结符b(包括 #)，置M[A, b]为k
+///
+/// 表中没有的单元格代表出现错误
+///
+/// 同一单元格对应多个产生式时文法不是LL(1)文法，返回描述第一个冲突的错误，
+/// 如含有左递归或左公因子的文法
+pub fn get_ll1_table(g: &Grammar) -> Result<Ll1Table, GrammarError> {
+    let first = get_first_sets(g);
+    let follow = get_follow_with_first(g, &first);
+    let mut sequence_first = SequenceFirst::new(&first);
+
+    let mut table = Ll1Table::new();
+    for (k, p) in g.p.iter().enumerate() {
+        let body_first = sequence_first.get(&p.right);
+        let mut lookaheads = body_first
+            .iter()
+            .filter(|a| *a != EPSILON)
+            .cloned()
+            .collect::<Vec<_>>();
+        if body_first.contains(EPSILON) {
+            lookaheads.extend(follow.get(&p.left).into_iter().flatten().cloned());
+        }
+        // 排序使报告的冲突是确定的
+        lookaheads.sort();
+        lookaheads.dedup();
+
+        for a in lookaheads {
+            match table.insert((p.left.clone(), a.clone()), k) {
+                Some(j) if j != k => {
+                    return Err(GrammarError::new(&format!(
+                        "LL(1) conflict: M[{},\"{}\"] = {} ({}) or {} ({})",
+                        p.left,
+                        a,
+                        j,
+                        g.p[j].to_string().trim_end(),
+                        k,
+                        p.to_string().trim_end()
+                    )));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(table)
+}
+
+/// 预测分析栈中的元素
+enum StackEntry {
+    /// 尚未匹配或展开的文法符号
+    Symbol(String),
+    /// 第k个产生式右部的符号都已分析完成，构造产生式左部的节点
+    Build(usize),
+}
+
+/// # LL(1) 预测分析
+///
+/// 分析栈中初始为开始符号，每一步取出栈顶：
+/// - 终结符：与当前输入符号相同时匹配并读入下一个符号，否则出错
+/// - 非终结符A：按照M[A, a]给出的产生式展开，将右部符号逆序压栈，a为当前输入符号，单元格为空时出错
+///
+/// 展开时在右部符号之下压入构造节点的标记，右部分析完成后将其子节点组合为A的节点，
+/// 因此得到的语法树与SLR(1)分析(`Parser::parse_tree`)得到的语法树结构相同
+/// ## 输入
+/// - `g`: 文法
+/// - `table`: `get_ll1_table` 构造的预测分析表
+/// - `tokens`: 词法分析得到的token序列
+/// ## 输出
+/// - 分析成功时返回语法树的根节点
+/// - 分析失败时返回第一个语法错误
+pub fn ll1_analysis(
+    g: &Grammar,
+    table: &Ll1Table,
+    tokens: Vec<Token>,
+) -> Result<ParseNode, SyntaxError> {
+    let buffer = tokens
+        .iter()
+        .map(|token| g.terminal_of(token))
+        .collect::<Vec<_>>();
+    // 当前输入符号的下标
+    let mut i = 0;
+    let error_at = |i: usize, message: String, expected: Vec<String>| {
+        error!("错误: {}", message);
+        let mut syntax_error = SyntaxError::with_expected(&message, expected);
+        syntax_error.token_index = Some(i).filter(|i| *i < buffer.len());
+        syntax_error
+    };
+
+    let mut stack = vec![StackEntry::Symbol(g.s.clone())];
+    let mut nodes: Vec<ParseNode> = vec![];
+    while let Some(entry) = stack.pop() {
+        let a = buffer.get(i).map_or(END_MARKER, |a| a.as_str());
+        match entry {
+            StackEntry::Build(k) => {
+                let p = &g.p[k];
+                let children = nodes.split_off(nodes.len() - p.arity());
+                nodes.push(ParseNode {
+                    symbol: p.left.clone(),
+                    value: None,
+                    production: Some(k),
+                    children,
+                });
+            }
+            StackEntry::Symbol(x) if g.v.contains(&x) => {
+                let Some(&k) = table.get(&(x.clone(), a.to_string())) else {
+                    let mut expected = table
+                        .keys()
+                        .filter(|(v, _)| *v == x)
+                        .map(|(_, t)| t.clone())
+                        .collect::<Vec<_>>();
+                    expected.sort();
+                    let message = format!(
+                        "unexpected `{}` while expanding {}, expected one of {}",
+                        a,
+                        x,
+                        expected
+                            .iter()
+                            .map(|t| format!("`{}`", t))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    return Err(error_at(i, message, expected));
+                };
+                debug!("展开: 按照第{}个产生式 {} 展开 {}", k, g.p[k], x);
+                stack.push(StackEntry::Build(k));
+                stack.extend(
+                    g.p[k]
+                        .right
+                        .iter()
+                        .rev()
+                        .filter(|s| *s != EPSILON)
+                        .map(|s| StackEntry::Symbol(s.clone())),
+                );
+            }
+            StackEntry::Symbol(x) => {
+                if i >= buffer.len() || x != a {
+                    let message = format!("unexpected `{}`, expected `{}`", a, x);
+                    return Err(error_at(i, message, vec![x]));
+                }
+                debug!("匹配: {}", x);
+                nodes.push(ParseNode::leaf(&x, &tokens[i].token_value));
+                i += 1;
+            }
+        }
+    }
+
+    if i < buffer.len() {
+        let message = format!("trailing input `{}` after a complete sentence", buffer[i]);
+        return Err(error_at(i, message, vec![]));
+    }
+    Ok(nodes.pop().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_ll1_table, ll1_analysis};
+    use crate::parser::{
+        lexical_analysis::lexical_analysis,
+        syntax_analysis::{Grammar, Parser},
+    };
+
+    const LL1_GRAMMAR: &str = "
+        E -> T E'
+        E' -> + T E' | ε
+        T -> F T'
+        T' -> * F T' | ε
+        F -> ( E ) | id
+    ";
+
+    #[test]
+    fn test_get_ll1_table() {
+        let g = Grammar::from_bnf(LL1_GRAMMAR).unwrap();
+        let table = get_ll1_table(&g).unwrap();
+        let m = |v: &str, t: &str| table.get(&(v.to_string(), t.to_string())).copied();
+
+        assert_eq!(m("E", "id"), Some(0));
+        assert_eq!(m("E", "("), Some(0));
+        assert_eq!(m("E'", "+"), Some(1));
+        // E' -> ε 在FOLLOW(E')上使用
+        assert_eq!(m("E'", ")"), Some(2));
+        assert_eq!(m("E'", "#"), Some(2));
+        assert_eq!(m("T'", "*"), Some(4));
+        assert_eq!(m("T'", "+"), Some(5));
+        assert_eq!(m("F", "id"), Some(7));
+        assert_eq!(m("E", "+"), None);
+        assert_eq!(table.len(), 13);
+
+        // 左递归的文法不是LL(1)文法
+        let g = Grammar::from_bnf("E -> E + T | T\nT -> id\n").unwrap();
+        let e = get_ll1_table(&g).unwrap_err();
+        assert_eq!(
+            e.message,
+            "LL(1) conflict: M[E,\"id\"] = 0 (E -> E + T) or 1 (E -> T)"
+        );
+    }
+
+    #[test]
+    fn test_ll1_analysis() {
+        let g = Grammar::from_bnf(LL1_GRAMMAR).unwrap();
+        let table = get_ll1_table(&g).unwrap();
+        let parser = Parser::new(g.clone()).unwrap();
+
+        for input in ["a + b", "a * ( b + c )", "a"] {
+            let (tokens, _) = lexical_analysis(input.to_string()).unwrap();
+            let tree = ll1_analysis(&g, &table, tokens.clone()).unwrap();
+            assert_eq!(Some(tree), parser.parse_tree(tokens));
+        }
+
+        let (tokens, _) = lexical_analysis("a + b".to_string()).unwrap();
+        let tree = ll1_analysis(&g, &table, tokens).unwrap();
+        assert_eq!(tree.symbol, "E");
+        assert_eq!(tree.production, Some(0));
+        // E' -> ε 得到的节点没有子节点
+        let e1 = &tree.children[1].children[2];
+        assert_eq!(e1.symbol, "E'");
+        assert!(e1.children.is_empty());
+
+        let (tokens, _) = lexical_analysis("a + + b".to_string()).unwrap();
+        let e = ll1_analysis(&g, &table, tokens).unwrap_err();
+        assert_eq!(e.token_index, Some(2));
+        assert_eq!(e.expected, vec!["(", "id"]);
+
+        let (tokens, _) = lexical_analysis("( a".to_string()).unwrap();
+        let e = ll1_analysis(&g, &table, tokens).unwrap_err();
+        assert_eq!(e.message, "unexpected `#`, expected `)`");
+        assert_eq!(e.token_index, None);
+
+        let (tokens, _) = lexical_analysis("a )".to_string()).unwrap();
+        let e = ll1_analysis(&g, &table, tokens).unwrap_err();
+        assert_eq!(e.token_index, Some(1));
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use log::warn;
+
+use super::{
+    error::SyntaxError,
+    syntax_analysis::{
+        augment, empty_tables, get_first_sets, Action, ActionTable, Grammar, Item, SequenceFirst,
+        Table, END_MARKER,
+    },
+};
+
+/// LR(1)项目
+///
+/// 一个LR(1)项目是带圆点和向前看符号的产生式
+/// 项目的形式为 [A -> α·Bβ, a]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Lr1Item {
+    /// 产生式左部
+    pub left: String,
+    /// 产生式右部
+    pub right: Vec<String>,
+    /// ·的位置，在对应坐标字符的左边
+    pub dot: usize,
+    /// 向前看符号
+    pub lookahead: String,
+}
+
+impl Lr1Item {
+    /// 项目的核心，即去掉向前看符号后的LR(0)项目
+    fn core(&self) -> (&str, &[String], usize) {
+        (&self.left, &self.right, self.dot)
+    }
+
+    /// 转换为去掉向前看符号后的LR(0)项目
+    fn to_item(&self) -> Item {
+        Item {
+            left: self.left.clone(),
+            right: self.right.clone(),
+            dot: self.dot,
+        }
+    }
+}
+
+/// LR(1)项目集族
+///
+/// 每个项目集都是一个状态，状态 0 为开始状态
+#[derive(Debug, Clone)]
+pub struct Lr1Collection {
+    /// 所有状态，每个项目集中的项目已排序
+    pub states: Vec<Vec<Lr1Item>>,
+    /// 状态转移，键为(状态 i, 符号 x)，值为状态 i 接受 x 后转移到的状态
+    pub transitions: HashMap<(usize, String), usize>,
+}
+
+/// # 求文法G的规范LR(1)项目集族
+///
+/// 项目集族在拓广文法G'上求解，开始项目为 [S' -> ·S, #]
+pub fn get_lr1_collection(g: &Grammar) -> Lr1Collection {
+    lr1_collection(&augment(g))
+}
+
+/// # 求拓广文法G'的LR(1)项目集族
+///
+/// 与LR(0)项目集族的求法相同，只是项目带有向前看符号
+fn lr1_collection(g: &Grammar) -> Lr1Collection {
+    let mut first = get_first_sets(g);
+    // 句子结束符 # 的FIRST集为自身，求 FIRST(βa) 时使用
+    first.insert(
+        END_MARKER.to_string(),
+        HashSet::from([END_MARKER.to_string()]),
+    );
+    let mut first = SequenceFirst::new(&first);
+
+    // 开始项目集(状态)
+    let first_prodution = g.p.iter().find(|p| p.left == g.s).unwrap();
+    let i = vec![Lr1Item {
+        left: first_prodution.left.clone(),
+        right: first_prodution.right.clone(),
+        dot: 0,
+        lookahead: END_MARKER.to_string(),
+    }];
+
+    let mut states = vec![closure(&i, g, &mut first)];
+    let mut index = HashMap::from([(states[0].clone(), 0)]);
+    let mut transitions = HashMap::new();
+
+    // 用于记录还未处理的状态
+    let mut e = VecDeque::from([0]);
+    while let Some(from) = e.pop_front() {
+        // 按圆点后符号第一次出现的顺序求转移，保证状态编号稳定
+        let mut symbols = vec![];
+        for item in states[from].iter() {
+            if let Some(x) = item.right.get(item.dot) {
+                if !symbols.contains(x) && (g.v.contains(x) || g.t.contains(x)) {
+                    symbols.push(x.clone());
+                }
+            }
+        }
+
+        for x in symbols {
+            let to_items = goto(&states[from], &x, g, &mut first);
+            let to = match index.get(&to_items) {
+                Some(to) => *to,
+                None => {
+                    states.push(to_items.clone());
+                    index.insert(to_items, states.len() - 1);
+                    e.push_back(states.len() - 1);
+                    states.len() - 1
+                }
+            };
+            transitions.insert((from, x), to);
+        }
+    }
+
+    Lr1Collection {
+        states,
+        transitions,
+    }
+}
+
+/// # LR(1)项目集的状态转移函数
+///
+/// 找到项目集中形如 [A -> α·xβ, a] 的项目，将 [A -> αx·β, a] 加入到 J 中，然后求J的闭包
+fn goto(items: &[Lr1Item], x: &str, g: &Grammar, first: &mut SequenceFirst) -> Vec<Lr1Item> {
+    let j = items
+        .iter()
+        .filter(|item| item.right.get(item.dot).is_some_and(|a| a == x))
+        .map(|item| Lr1Item {
+            dot: item.dot + 1,
+            ..item.clone()
+        })
+        .collect::<Vec<_>>();
+
+    closure(&j, g, first)
+}
+
+/// # 在拓广文法G'中求解LR(1)项目集I的闭包J
+///
+/// 闭包的定义为：J = I U {[B -> ·γ, b] | [A -> α·Bβ, a] ∈ J, B -> γ ∈ G', b ∈ FIRST(βa)}
+///
+/// 返回的项目集已排序，便于比较两个项目集是否相同
+fn closure(i: &[Lr1Item], g: &Grammar, first: &mut SequenceFirst) -> Vec<Lr1Item> {
+    let mut j = i.iter().cloned().collect::<HashSet<_>>();
+    let mut e = i.iter().cloned().collect::<VecDeque<_>>();
+
+    while let Some(item) = e.pop_front() {
+        // 圆点后为非终结符 B 时展开
+        let Some(b) = item.right.get(item.dot).filter(|b| g.v.contains(b)) else {
+            continue;
+        };
+
+        // 求 FIRST(βa)，β为空或可推出ε时包含a
+        let mut beta_a = item.right[item.dot + 1..].to_vec();
+        beta_a.push(item.lookahead.clone());
+        let lookaheads = first.get(&beta_a).clone();
+
+        g.p.iter().filter(|p| p.left == *b).for_each(|p| {
+            for lookahead in lookaheads.iter() {
+                let new_item = Lr1Item {
+                    left: p.left.clone(),
+                    right: p.right.clone(),
+                    dot: 0,
+                    lookahead: lookahead.clone(),
+                };
+                if j.insert(new_item.clone()) {
+                    e.push_back(new_item);
+                }
+            }
+        });
+    }
+
+    let mut j = j.into_iter().collect::<Vec<_>>();
+    j.sort();
+    j
+}
+
+/// # 对输入文法G获取规范LR(1)分析表
+///
+/// 获取ACTION表与GOTO表，返回值与 `get_slr1_table` 相同
+/// 1. 将非拓广文法G转换为拓广文法G'
+/// 2. 求解拓广文法G'的LR(1)项目集族
+/// 3. 遍历项目集族构造ACTION表与GOTO表，规约时使用项目的向前看符号
+///
+/// 状态数通常多于SLR(1)与LALR(1)，但能处理的文法也更多
+pub fn get_lr1_table(g: &Grammar) -> Result<(ActionTable, Table), SyntaxError> {
+    let outreach_g = augment(g);
+    let lr1 = lr1_collection(&outreach_g);
+    Ok(fill_tables(&outreach_g, &lr1, "LR(1)"))
+}
+
+/// # 对输入文法G获取LALR(1)分析表
+///
+/// 获取ACTION表与GOTO表，返回值与 `get_slr1_table` 相同
+/// 1. 将非拓广文法G转换为拓广文法G'
+/// 2. 求解拓广文法G'的LR(1)项目集族
+/// 3. 合并核心(LR(0)项目)相同的状态，合并后状态的项目为原状态项目的并集
+/// 4. 遍历合并后的状态构造ACTION表与GOTO表，规约时使用项目的向前看符号而不是FOLLOW集
+pub fn get_lalr1_table(g: &Grammar) -> Result<(ActionTable, Table), SyntaxError> {
+    let outreach_g = augment(g);
+    let lalr1 = get_lalr1_states(&outreach_g);
+    Ok(fill_tables(&outreach_g, &lalr1, "LALR"))
+}
+
+/// # 由带向前看符号的项目集族构造ACTION表与GOTO表
+///
+/// 1. 若状态 k 接受符号 x 后转移到状态 j，x为终结符时置ACTION[k,x]为sj，否则置GOTO[k,x]为j
+/// 2. 若项目 [A -> α·, a] 属于I_k，则置ACTION[k,a]为rj，假定A->α为G'的第j个产生式
+/// 3. 若项目 [S' -> S·, #] 属于I_k，则置ACTION[k,#]为“acc”
+#[allow(non_snake_case)]
+fn fill_tables(
+    outreach_g: &Grammar,
+    collection: &Lr1Collection,
+    mode: &str,
+) -> (ActionTable, Table) {
+    let (mut ACTION, mut GOTO) = empty_tables(outreach_g, collection.states.len());
+
+    for ((from, x), to) in collection.transitions.iter() {
+        if outreach_g.t.contains(x) {
+            set_action(&mut ACTION, *from, x, Action::Shift(*to), mode);
+        } else {
+            GOTO[*from].insert(x.clone(), to.to_string());
+        }
+    }
+
+    for (i, items) in collection.states.iter().enumerate() {
+        for item in items.iter().filter(|item| item.dot >= item.right.len()) {
+            if item.left == outreach_g.s {
+                ACTION[i].insert(END_MARKER.to_string(), Action::Accept);
+                continue;
+            }
+            let j = outreach_g
+                .p
+                .iter()
+                .position(|p| p.left == item.left && p.right == item.right)
+                .unwrap();
+            set_action(&mut ACTION, i, &item.lookahead, Action::Reduce(j), mode);
+        }
+    }
+
+    (ACTION, GOTO)
+}
+
+/// # 求LALR(1)分析表每个状态中可规约项目的向前看符号集
+///
+/// 状态编号与 `get_lalr1_table` 相同，每个状态中的项目按在项目集中出现的顺序排列
+///
+/// SLR(1)对项目 A -> α· 在整个 FOLLOW(A) 上规约，而LALR(1)只在其向前看符号集上规约，
+/// 向前看符号集是 FOLLOW(A) 的子集
+pub fn lalr_lookaheads(g: &Grammar) -> Vec<Vec<(Item, HashSet<String>)>> {
+    let outreach_g = augment(g);
+    let lalr1 = get_lalr1_states(&outreach_g);
+
+    lalr1
+        .states
+        .iter()
+        .map(|items| {
+            let mut lookaheads: Vec<(Item, HashSet<String>)> = vec![];
+            for item in items.iter().filter(|item| item.dot >= item.right.len()) {
+                let core = item.to_item();
+                match lookaheads.iter_mut().find(|(i, _)| *i == core) {
+                    Some((_, set)) => {
+                        set.insert(item.lookahead.clone());
+                    }
+                    None => lookaheads.push((core, HashSet::from([item.lookahead.clone()]))),
+                }
+            }
+            lookaheads
+        })
+        .collect()
+}
+
+/// # 求拓广文法G'的LALR(1)状态
+///
+/// 求解LR(1)项目集族，合并核心(LR(0)项目)相同的状态，合并后状态的项目为原状态项目的并集
+///
+/// 返回合并后的状态与状态转移
+fn get_lalr1_states(outreach_g: &Grammar) -> Lr1Collection {
+    let lr1 = lr1_collection(outreach_g);
+
+    // 合并核心相同的状态，merged[i] 为LR(1)状态 i 合并后的状态编号
+    let mut cores: Vec<HashSet<(&str, &[String], usize)>> = vec![];
+    let mut states: Vec<Vec<Lr1Item>> = vec![];
+    let mut merged = vec![];
+    for items in lr1.states.iter() {
+        let core = items.iter().map(|item| item.core()).collect::<HashSet<_>>();
+        match cores.iter().position(|c| *c == core) {
+            Some(k) => {
+                states[k].extend(items.iter().cloned());
+                merged.push(k);
+            }
+            None => {
+                cores.push(core);
+                states.push(items.clone());
+                merged.push(states.len() - 1);
+            }
+        }
+    }
+
+    // 合并后的项目集去重并排序
+    for items in states.iter_mut() {
+        items.sort();
+        items.dedup();
+    }
+
+    // 核心相同的状态转移到的状态核心也相同，因此合并后的转移是确定的
+    let transitions = lr1
+        .transitions
+        .into_iter()
+        .map(|((from, x), to)| ((merged[from], x), merged[to]))
+        .collect();
+
+    Lr1Collection {
+        states,
+        transitions,
+    }
+}
+
+/// 填写ACTION表，单元格中已有不同的动作时报告冲突并使用新的动作
+#[allow(non_snake_case)]
+fn set_action(ACTION: &mut ActionTable, state: usize, symbol: &str, action: Action, mode: &str) {
+    match ACTION[state].insert(symbol.to_string(), action) {
+        Some(a) if a != Action::Error && a != action => {
+            warn!(
+                "{} action conflict: ACTION[{},\"{}\"] = {} or {}, use {}",
+                mode, state, symbol, a, action, action
+            );
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{get_lalr1_table, get_lr1_collection, get_lr1_table, lalr_lookaheads};
+    use crate::parser::{
+        lexical_analysis::lexical_analysis,
+        syntax_analysis::{get_follow, get_slr1_table, slr1_analysis, Grammar},
+    };
+
+    /// LALR(1)文法，但不是SLR(1)文法
+    ///
+    /// SLR(1)中状态 {S -> L·=R, R -> L·} 在 = 上存在移进-规约冲突，因为 = ∈ FOLLOW(R)
+    const GRAMMAR: &str = "S -> L = R | R\nL -> * R | id\nR -> L\n";
+
+    #[test]
+    fn test_lalr1_table() {
+        let g = Grammar::from_bnf(GRAMMAR).unwrap();
+        let (action, goto) = get_lalr1_table(&g).unwrap();
+        // 与LR(0)项目集族的状态数相同
+        assert_eq!(action.len(), 10);
+        assert_eq!(goto.len(), 10);
+
+        for source in ["a = b", "* a = * * b", "a", "* * a"] {
+            let tokens = lexical_analysis(source.to_string()).unwrap().0;
+            assert!(slr1_analysis(&g, &action, &goto, tokens), "{}", source);
+        }
+        for source in ["a = = b", "a = ", "* = a"] {
+            let tokens = lexical_analysis(source.to_string()).unwrap().0;
+            assert!(!slr1_analysis(&g, &action, &goto, tokens), "{}", source);
+        }
+
+        // SLR(1)分析表在冲突时选择了规约，无法分析合法的句子
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let tokens = lexical_analysis("a = b".to_string()).unwrap().0;
+        assert!(!slr1_analysis(&g, &action, &goto, tokens));
+    }
+
+    #[test]
+    fn test_lalr_lookaheads() {
+        let g = Grammar::from_bnf(GRAMMAR).unwrap();
+        let lookaheads = lalr_lookaheads(&g);
+        assert_eq!(lookaheads.len(), 10);
+
+        let follow_r = get_follow(&g)["R"].iter().cloned().collect::<HashSet<_>>();
+        assert_eq!(follow_r, HashSet::from(["#".to_string(), "=".to_string()]));
+
+        let r_lookaheads = lookaheads
+            .iter()
+            .flatten()
+            .filter(|(item, _)| item.left == "R")
+            .map(|(_, set)| set)
+            .collect::<Vec<_>>();
+        // 读入 L 后的状态 {S -> L·=R, R -> L·} 中 R -> L· 的向前看符号只有 #，严格小于 FOLLOW(R)
+        assert!(r_lookaheads.contains(&&HashSet::from(["#".to_string()])));
+        assert!(r_lookaheads.iter().all(|set| set.is_subset(&follow_r)));
+
+        // 开始状态中没有可规约的项目
+        assert!(lookaheads[0].is_empty());
+    }
+
+    #[test]
+    fn test_lr1_table() {
+        let g = Grammar::from_bnf(GRAMMAR).unwrap();
+        let lr1 = get_lr1_collection(&g);
+        // LR(1)项目集族有14个状态，多于LR(0)项目集族的10个状态
+        assert_eq!(lr1.states.len(), 14);
+        assert_eq!(lr1.states[0][0].lookahead, "#");
+        // 开始状态接受 S 后到达接受状态 {[S' -> S·, #]}
+        let accept = &lr1.states[lr1.transitions[&(0, "S".to_string())]];
+        assert_eq!(accept.len(), 1);
+        assert_eq!((accept[0].left.as_str(), accept[0].dot), ("S'", 1));
+
+        let (action, goto) = get_lr1_table(&g).unwrap();
+        assert_eq!(action.len(), 14);
+        let (slr_action, _) = get_slr1_table(&g).unwrap();
+        assert!(action.len() > slr_action.len());
+
+        // 没有冲突，所有合法的句子都能被接受
+        for source in ["a = b", "* a = * * b", "a", "* * a"] {
+            let tokens = lexical_analysis(source.to_string()).unwrap().0;
+            assert!(slr1_analysis(&g, &action, &goto, tokens), "{}", source);
+        }
+        for source in ["a = = b", "a = ", "* = a"] {
+            let tokens = lexical_analysis(source.to_string()).unwrap().0;
+            assert!(!slr1_analysis(&g, &action, &goto, tokens), "{}", source);
+        }
+    }
+}
@@ -1,3 +1,155 @@
+pub mod diagnostic;
+pub mod earley_analysis;
 pub mod error;
+pub mod export;
+pub mod grammar_analysis;
 pub mod lexical_analysis;
+pub mod ll1_analysis;
+pub mod lr1_analysis;
+pub mod parse_tree;
 pub mod syntax_analysis;
+
+use error::{GrammarError, ParserError, SyntaxError};
+use lexical_analysis::{lexical_analysis, Token, TokenType};
+use syntax_analysis::{Grammar, Parser};
+
+/// 对源代码进行分析的结果
+#[derive(Debug)]
+pub struct ParseOutcome {
+    /// 词法分析得到的token序列
+    pub tokens: Vec<Token>,
+    /// 源代码是否为文法的句子
+    pub accepted: bool,
+    /// 分析过程中的语法错误
+    pub errors: Vec<SyntaxError>,
+}
+
+/// # 在内存中由yml文法直接分析源代码
+///
+/// 依次进行文法读取与验证、分析表构造、词法分析和SLR(1)分析，不读写任何文件
+///
+/// 源代码不是文法的句子时返回 `accepted` 为 `false` 的结果，
+/// 文法不合法、无法构造分析表或存在无法识别的单词时返回错误
+pub fn parse(grammar_yml: &str, source: &str) -> Result<ParseOutcome, ParserError> {
+    let grammar = Grammar::from_yml(grammar_yml)
+        .map_err(|e| ParserError::Grammar(GrammarError::new(&e.to_string())))?;
+    grammar.validate()?;
+    let parser = Parser::new(grammar)?;
+
+    let (tokens, _) = lexical_analysis(source.to_string())?;
+    // 存在无法识别的单词时返回第一个词法错误
+    if let Some(e) = tokens.iter().find_map(|token| match &token.token_type {
+        TokenType::Error(e) => Some(e.clone()),
+        _ => None,
+    }) {
+        return Err(e.into());
+    }
+
+    let (accepted, errors) = parser.parse_with_options(tokens.clone(), &Default::default());
+    Ok(ParseOutcome {
+        tokens,
+        accepted,
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        error::{GrammarError, ParserError, SyntaxError},
+        lexical_analysis::lexical_analysis,
+        parse,
+        syntax_analysis::EXPR_GRAMMAR_YML,
+    };
+
+    #[test]
+    fn test_parse() {
+        let outcome = parse(EXPR_GRAMMAR_YML, "a * (b + c)").unwrap();
+        assert!(outcome.accepted);
+        assert!(outcome.errors.is_empty());
+        assert_eq!(outcome.tokens.len(), 7);
+
+        let outcome = parse(EXPR_GRAMMAR_YML, "a + ").unwrap();
+        assert!(!outcome.accepted);
+        assert_eq!(outcome.errors.len(), 1);
+
+        assert!(matches!(
+            parse(EXPR_GRAMMAR_YML, "a + 1b"),
+            Err(ParserError::Lexical(_))
+        ));
+        assert!(matches!(parse("s: [", "a"), Err(ParserError::Grammar(_))));
+    }
+
+    #[test]
+    fn test_parser_error_from() {
+        // 各阶段的错误通过 ? 转换为 ParserError
+        fn lex(source: &str) -> Result<usize, ParserError> {
+            Ok(lexical_analysis(source.to_string())?.0.len())
+        }
+        assert_eq!(lex("a + b").unwrap(), 3);
+        let e = lex("a /* b").unwrap_err();
+        assert!(matches!(e, ParserError::Lexical(_)));
+        assert!(e
+            .to_string()
+            .starts_with("lexical error: multiline comment not closed"));
+        assert!(std::error::Error::source(&e).is_some());
+
+        let e: ParserError = GrammarError::new("bad").into();
+        assert!(matches!(e, ParserError::Grammar(_)));
+        let e: ParserError = SyntaxError::new("bad").into();
+        assert!(matches!(e, ParserError::Syntax(_)));
+    }
+
+    #[test]
+    fn test_parse_empty_program() {
+        // 开始符号只能推出ε
+        let yml = r#"
+        s: S
+        v:
+          - S
+        t:
+          - ε
+        p:
+          - left: S
+            right:
+              - ε
+        "#;
+        let outcome = parse(yml, "").unwrap();
+        assert!(outcome.tokens.is_empty());
+        assert!(outcome.accepted);
+        // 只有空白和注释的源代码也是空程序
+        assert!(
+            parse(yml, "  \n// comment\n/* block */\n")
+                .unwrap()
+                .accepted
+        );
+        assert!(!parse(yml, "a").unwrap().accepted);
+
+        // 开始符号经过其他非终结符推出ε
+        let yml = r#"
+        s: Program
+        v:
+          - Program
+          - StmtList
+        t:
+          - id
+          - ;
+          - ε
+        p:
+          - left: Program
+            right:
+              - StmtList
+          - left: StmtList
+            right:
+              - StmtList
+              - id
+              - ;
+          - left: StmtList
+            right:
+              - ε
+        "#;
+        assert!(parse(yml, "").unwrap().accepted);
+        assert!(parse(yml, "a; b;").unwrap().accepted);
+        assert!(!parse(yml, "a").unwrap().accepted);
+    }
+}
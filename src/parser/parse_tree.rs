@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+
+use super::{
+    lexical_analysis::Token,
+    syntax_analysis::{ParseEvent, Parser},
+};
+
+/// 语法树的节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNode {
+    /// 节点的文法符号
+    pub symbol: String,
+    /// 终结符节点对应的token的值，非终结符节点为 `None`
+    pub value: Option<String>,
+    /// 非终结符节点规约时使用的产生式序号，终结符节点为 `None`
+    pub production: Option<usize>,
+    /// 子节点，ε 产生式规约得到的节点没有子节点
+    pub children: Vec<ParseNode>,
+}
+
+impl ParseNode {
+    /// 终结符节点
+    pub fn leaf(symbol: &str, value: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            value: Some(value.to_string()),
+            production: None,
+            children: vec![],
+        }
+    }
+
+    /// 是否为终结符节点
+    pub fn is_leaf(&self) -> bool {
+        self.production.is_none()
+    }
+}
+
+impl Parser {
+    /// # 对token序列进行SLR(1)分析并构造语法树
+    ///
+    /// 按照分析过程中的移进和规约事件自底向上构造语法树，分析失败时返回 `None`
+    pub fn parse_tree(&self, tokens: Vec<Token>) -> Option<ParseNode> {
+        let (success, events) = self.parse_events(tokens.clone());
+        if !success {
+            return None;
+        }
+
+        let mut tokens = tokens.into_iter();
+        let mut stack: Vec<ParseNode> = vec![];
+        for event in events {
+            match event {
+                ParseEvent::Shift { token, .. } => {
+                    let value = tokens.next()?.token_value;
+                    stack.push(ParseNode::leaf(&token, &value));
+                }
+                ParseEvent::Reduce { production } => {
+                    let p = &self.grammar.p[production];
+                    let children = stack.split_off(stack.len().checked_sub(p.arity())?);
+                    stack.push(ParseNode {
+                        symbol: p.left.clone(),
+                        value: None,
+                        production: Some(production),
+                        children,
+                    });
+                }
+                ParseEvent::Skip { .. } => {
+                    tokens.next()?;
+                }
+                ParseEvent::Accept => return stack.pop(),
+                ParseEvent::Goto { .. } | ParseEvent::Error { .. } => {}
+            }
+        }
+        None
+    }
+}
+
+/// 语法树化简时的透明规则
+///
+/// 只有一个子节点的非终结符节点，若其符号在 `nonterminals` 中或其产生式在 `productions` 中，
+/// 则该节点是透明的，化简时被其唯一的子节点替代
+#[derive(Debug, Clone, Default)]
+pub struct TransparencyRules {
+    /// 透明的非终结符
+    pub nonterminals: HashSet<String>,
+    /// 透明的产生式序号
+    pub productions: HashSet<usize>,
+}
+
+impl TransparencyRules {
+    /// 由透明的非终结符构造规则
+    pub fn nonterminals(nonterminals: &[&str]) -> Self {
+        Self {
+            nonterminals: nonterminals.iter().map(|v| v.to_string()).collect(),
+            productions: HashSet::new(),
+        }
+    }
+
+    /// 节点是否是透明的
+    fn is_transparent(&self, node: &ParseNode) -> bool {
+        node.children.len() == 1
+            && (self.nonterminals.contains(&node.symbol)
+                || node
+                    .production
+                    .is_some_and(|p| self.productions.contains(&p)))
+    }
+}
+
+/// # 按照透明规则化简语法树
+///
+/// 自底向上地将透明节点替换为其唯一的子节点，如 `E -> T -> F -> id` 这样的单子节点链被折叠为 `id`
+pub fn simplify_tree(node: &ParseNode, rules: &TransparencyRules) -> ParseNode {
+    let mut simplified = ParseNode {
+        children: node
+            .children
+            .iter()
+            .map(|child| simplify_tree(child, rules))
+            .collect(),
+        ..node.clone()
+    };
+    if rules.is_transparent(&simplified) {
+        simplified = simplified.children.pop().unwrap();
+    }
+    simplified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{lexical_analysis::lexical_analysis, syntax_analysis::Grammar};
+
+    const GRAMMAR: &str = "E -> E + T | T\nT -> T * F | F\nF -> ( E ) | id\n";
+
+    #[test]
+    fn test_parse_tree() {
+        let parser = Parser::new(Grammar::from_bnf(GRAMMAR).unwrap()).unwrap();
+
+        let tokens = lexical_analysis("a + b".to_string()).unwrap().0;
+        let tree = parser.parse_tree(tokens).unwrap();
+        assert_eq!(tree.symbol, "E");
+        assert_eq!(tree.production, Some(0));
+        let symbols = tree
+            .children
+            .iter()
+            .map(|c| c.symbol.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(symbols, vec!["E", "+", "T"]);
+        assert_eq!(tree.children[1], ParseNode::leaf("+", "+"));
+
+        let tokens = lexical_analysis("a + ) b".to_string()).unwrap().0;
+        assert_eq!(parser.parse_tree(tokens), None);
+    }
+
+    #[test]
+    fn test_simplify_tree() {
+        let parser = Parser::new(Grammar::from_bnf(GRAMMAR).unwrap()).unwrap();
+
+        // E -> T -> F -> id 折叠为 id
+        let tokens = lexical_analysis("a".to_string()).unwrap().0;
+        let tree = parser.parse_tree(tokens).unwrap();
+        let rules = TransparencyRules::nonterminals(&["E", "T", "F"]);
+        assert_eq!(simplify_tree(&tree, &rules), ParseNode::leaf("id", "a"));
+
+        // 有多个子节点的节点不是透明的
+        let tokens = lexical_analysis("a * (b)".to_string()).unwrap().0;
+        let tree = parser.parse_tree(tokens).unwrap();
+        let simplified = simplify_tree(&tree, &rules);
+        assert_eq!(simplified.symbol, "T");
+        assert_eq!(simplified.children[0], ParseNode::leaf("id", "a"));
+        assert_eq!(simplified.children[2].symbol, "F");
+        assert_eq!(
+            simplified.children[2].children[1],
+            ParseNode::leaf("id", "b")
+        );
+
+        // 只有 F -> id 是透明的
+        let rules = TransparencyRules {
+            productions: HashSet::from([5]),
+            ..Default::default()
+        };
+        let simplified = simplify_tree(&tree, &rules);
+        assert_eq!(simplified.symbol, "E");
+        let t = &simplified.children[0];
+        assert_eq!(t.children[0].symbol, "T");
+        assert_eq!(t.children[0].children[0], ParseNode::leaf("id", "a"));
+    }
+}
@@ -1,21 +1,32 @@
 use super::{
     error::{GrammarError, SyntaxError},
+    grammar_analysis::{productive_nonterminals, reachable_nonterminals},
     lexical_analysis::{Token, TokenType},
 };
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    fmt::Display,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::{Debug, Display},
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
     vec,
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Product {
     pub left: String,       // 产生式左部，为一个非终结符
     pub right: Vec<String>, // 产生式右部，含多个终结符或非终结符
 }
 
+impl Product {
+    /// 产生式右部中ε以外的符号数，即按该产生式规约时弹出的状态数
+    pub fn arity(&self) -> usize {
+        self.right.iter().filter(|s| *s != EPSILON).count()
+    }
+}
+
 impl Display for Product {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} -> ", self.left)?;
@@ -27,7 +38,7 @@ impl Display for Product {
 }
 
 /// 语法定义
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Grammar {
     /// 开始符号
     pub s: String,
@@ -37,12 +48,372 @@ pub struct Grammar {
     pub t: Vec<String>,
     /// 产生式集
     pub p: Vec<Product>,
+    /// 词法分析得到的token类型到终结符的映射，键为token类型名，如 `Identifier`、`Constant`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub token_terminals: HashMap<String, String>,
+    /// 终结符在错误信息中显示的名字，如 `;` 显示为 `semicolon`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub terminal_labels: HashMap<String, String>,
+    /// 导出分析表时终结符列的顺序，未列出的终结符按声明顺序排在其后
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub column_order: Vec<String>,
+}
+
+impl Display for Grammar {
+    /// 依次为开始符号、非终结符集、终结符集与带序号的产生式，产生式每行一个，右部为空时显示为ε
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "s: {}", self.s)?;
+        writeln!(f, "v: {}", self.v.join(" "))?;
+        writeln!(f, "t: {}", self.t.join(" "))?;
+        writeln!(f, "p:")?;
+        for (i, p) in self.p.iter().enumerate() {
+            let right = match p.right.is_empty() {
+                true => EPSILON.to_string(),
+                false => p.right.join(" "),
+            };
+            writeln!(f, "  {}: {} -> {}", i, p.left, right)?;
+        }
+        Ok(())
+    }
+}
+
+/// 可以包含其他文件的yml文法文件，见 `Grammar::from_yml_with_includes`
+#[derive(Deserialize)]
+struct GrammarFile {
+    /// 被包含的文件
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    s: Option<String>,
+    #[serde(default)]
+    v: Vec<String>,
+    #[serde(default)]
+    t: Vec<String>,
+    #[serde(default)]
+    p: Vec<Product>,
+    #[serde(default)]
+    token_terminals: HashMap<String, String>,
+    #[serde(default)]
+    terminal_labels: HashMap<String, String>,
+    #[serde(default)]
+    column_order: Vec<String>,
 }
 
 impl Grammar {
-    /// 从yml中读取语法定义
+    /// # 从yml中读取语法定义
+    ///
+    /// 产生式右部中带有EBNF后缀的符号会被展开，见 [`Grammar::desugar_ebnf`]
     pub fn from_yml(input: &str) -> Result<Grammar, serde_yaml::Error> {
-        serde_yaml::from_str::<Grammar>(input)
+        let mut g = serde_yaml::from_str::<Grammar>(input)?;
+        g.desugar_ebnf();
+        Ok(g)
+    }
+
+    /// # 从json中读取语法定义
+    ///
+    /// 字段名与yml相同，同一个文法可以用两种格式中的任意一种表示，读取后同样应调用 `validate` 验证
+    ///
+    /// 产生式右部中带有EBNF后缀的符号会被展开，见 [`Grammar::desugar_ebnf`]
+    pub fn from_json(input: &str) -> Result<Grammar, serde_json::Error> {
+        let mut g = serde_json::from_str::<Grammar>(input)?;
+        g.desugar_ebnf();
+        Ok(g)
+    }
+
+    /// # 从yml文件中读取语法定义，并合并其包含的子文法
+    ///
+    /// 文件顶层的 `include: [file1.yml, ...]` 列出被包含的文件，路径相对于当前文件所在目录，
+    /// 被包含的文件同样可以包含其他文件，各文件中除 `include` 外的字段都可以省略
+    /// - 非终结符集、终结符集与产生式集按当前文件、被包含文件的顺序合并，重复的符号只保留一个
+    /// - 开始符号只需在一个文件中给出，多个文件给出不同的开始符号时返回错误
+    /// - 终结符映射与显示名字中同一个键以先读取到的为准
+    /// - 同一个文件被多次包含时只读取一次，如两个文件都包含了同一个公共文件
+    ///
+    /// 合并后展开EBNF后缀，见 [`Grammar::desugar_ebnf`]
+    pub fn from_yml_with_includes(path: &Path) -> Result<Grammar, GrammarError> {
+        let mut g = Grammar {
+            s: String::new(),
+            v: vec![],
+            t: vec![],
+            p: vec![],
+            token_terminals: HashMap::new(),
+            terminal_labels: HashMap::new(),
+            column_order: vec![],
+        };
+        g.merge_yml_file(path, &mut vec![], &mut HashSet::new())?;
+        if g.s.is_empty() {
+            return Err(GrammarError::new(&format!(
+                "{} 及其包含的文件中没有开始符号",
+                path.display()
+            )));
+        }
+        g.desugar_ebnf();
+        Ok(g)
+    }
+
+    /// 将yml文件及其包含的文件合并到文法中
+    ///
+    /// `including` 为正在读取的文件，用于发现循环包含，`loaded` 为已经合并的文件，
+    /// 两者都以规范化的路径比较，因此 `sub/../x.yml` 与 `x.yml` 是同一个文件
+    fn merge_yml_file(
+        &mut self,
+        path: &Path,
+        including: &mut Vec<PathBuf>,
+        loaded: &mut HashSet<PathBuf>,
+    ) -> Result<(), GrammarError> {
+        let path = &std::fs::canonicalize(path)
+            .map_err(|e| GrammarError::new(&format!("读取 {} 失败: {}", path.display(), e)))?;
+        if including.contains(path) {
+            return Err(GrammarError::new(&format!(
+                "循环包含文件 {}",
+                path.display()
+            )));
+        }
+        if !loaded.insert(path.clone()) {
+            return Ok(());
+        }
+        let input = std::fs::read_to_string(path)
+            .map_err(|e| GrammarError::new(&format!("读取 {} 失败: {}", path.display(), e)))?;
+        let file = serde_yaml::from_str::<GrammarFile>(&input)
+            .map_err(|e| GrammarError::new(&format!("解析 {} 失败: {}", path.display(), e)))?;
+
+        if let Some(s) = file.s {
+            if !self.s.is_empty() && self.s != s {
+                return Err(GrammarError::new(&format!(
+                    "{} 中的开始符号 \"{}\" 与已有的开始符号 \"{}\" 冲突",
+                    path.display(),
+                    s,
+                    self.s
+                )));
+            }
+            self.s = s;
+        }
+        let extend = |to: &mut Vec<String>, from: Vec<String>| {
+            for x in from {
+                if !to.contains(&x) {
+                    to.push(x);
+                }
+            }
+        };
+        extend(&mut self.v, file.v);
+        extend(&mut self.t, file.t);
+        extend(&mut self.column_order, file.column_order);
+        self.p.extend(file.p);
+        for (k, x) in file.token_terminals {
+            self.token_terminals.entry(k).or_insert(x);
+        }
+        for (k, x) in file.terminal_labels {
+            self.terminal_labels.entry(k).or_insert(x);
+        }
+
+        including.push(path.to_path_buf());
+        for include in file.include {
+            let include_path = path.parent().unwrap_or(Path::new("")).join(include);
+            self.merge_yml_file(&include_path, including, loaded)?;
+        }
+        including.pop();
+        Ok(())
+    }
+
+    /// # 展开产生式右部的EBNF后缀
+    ///
+    /// 右部符号 `X*`、`X+`、`X?` 中的 `X` 为已声明的终结符或非终结符，且该符号本身未被声明时，
+    /// 被替换为新的非终结符 N，N 插入在产生式左部 A 之后：
+    /// - `X*`：N 为 `A_rep`，N -> X N | ε
+    /// - `X+`：N 为 `A_plus`，N -> X N | X
+    /// - `X?`：N 为 `A_opt`，N -> X | ε
+    ///
+    /// ε 产生式的右部为空，新的产生式排在原有产生式之后
+    ///
+    /// 新的非终结符与已有符号重名时在名字后添加序号，如 `A_rep2`
+    pub fn desugar_ebnf(&mut self) {
+        let declared = |g: &Grammar, s: &str| g.v.iter().chain(g.t.iter()).any(|x| x == s);
+
+        let mut p = vec![];
+        let mut added: Vec<Product> = vec![];
+        // 每个左部已经插入的新非终结符数，新的非终结符按出现顺序排在左部之后
+        let mut inserted: HashMap<String, usize> = HashMap::new();
+        for mut product in std::mem::take(&mut self.p) {
+            for i in 0..product.right.len() {
+                let symbol = &product.right[i];
+                let Some(suffix) = symbol.chars().last() else {
+                    continue;
+                };
+                let base = &symbol[..symbol.len() - suffix.len_utf8()];
+                let kind = match suffix {
+                    '*' => "rep",
+                    '+' => "plus",
+                    '?' => "opt",
+                    _ => continue,
+                };
+                if base.is_empty() || declared(self, symbol) || !declared(self, base) {
+                    continue;
+                }
+
+                let base = base.to_string();
+                let mut name = format!("{}_{}", product.left, kind);
+                let mut n = 1;
+                while declared(self, &name) {
+                    n += 1;
+                    name = format!("{}_{}{}", product.left, kind, n);
+                }
+                let count = inserted.entry(product.left.clone()).or_default();
+                let position = match self.v.iter().position(|v| *v == product.left) {
+                    Some(i) => i + 1 + *count,
+                    None => self.v.len(),
+                };
+                self.v.insert(position, name.clone());
+                *count += 1;
+
+                let rights = match kind {
+                    "rep" => [vec![base.clone(), name.clone()], vec![]],
+                    "plus" => [vec![base.clone(), name.clone()], vec![base.clone()]],
+                    _ => [vec![base.clone()], vec![]],
+                };
+                added.extend(rights.into_iter().map(|right| Product {
+                    left: name.clone(),
+                    right,
+                }));
+                product.right[i] = name;
+            }
+            p.push(product);
+        }
+        p.extend(added);
+        self.p = p;
+    }
+
+    /// # 从BNF文本中读取语法定义
+    ///
+    /// 每行一条规则，形如 `E -> T E' | T`，符号之间以空白分隔，`|` 分隔多个候选式
+    /// - 以 `|` 开头的行为上一条规则的续行
+    /// - 空的候选式或只有 ε 的候选式表示空产生式
+    /// - 所有规则的左部构成非终结符集，其余符号构成终结符集，均按第一次出现的顺序排列
+    /// - 第一条规则的左部为开始符号
+    pub fn from_bnf(input: &str) -> Result<Grammar, GrammarError> {
+        let mut v: Vec<String> = vec![];
+        // 规则左部与其候选式，候选式为未拆分的文本
+        let mut rules: Vec<(String, Vec<&str>)> = vec![];
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix('|') {
+                match rules.last_mut() {
+                    Some((_, alternatives)) => alternatives.extend(rest.split('|')),
+                    None => {
+                        return Err(GrammarError::new(&format!(
+                            "第{}行: 续行之前没有规则",
+                            i + 1
+                        )))
+                    }
+                }
+                continue;
+            }
+
+            let Some((left, right)) = line.split_once("->") else {
+                return Err(GrammarError::new(&format!("第{}行: 缺少 `->`", i + 1)));
+            };
+            let left = left.trim();
+            if left.is_empty() || left.contains(char::is_whitespace) {
+                return Err(GrammarError::new(&format!(
+                    "第{}行: 规则左部必须是一个非终结符",
+                    i + 1
+                )));
+            }
+            if !v.iter().any(|x| x == left) {
+                v.push(left.to_string());
+            }
+            rules.push((left.to_string(), right.split('|').collect()));
+        }
+
+        if rules.is_empty() {
+            return Err(GrammarError::new("文法中没有规则"));
+        }
+
+        let mut t: Vec<String> = vec![];
+        let mut p = vec![];
+        for (left, alternatives) in rules.iter() {
+            for alternative in alternatives {
+                let mut right = alternative
+                    .split_whitespace()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>();
+                if right.iter().all(|s| s == EPSILON) {
+                    right.clear();
+                }
+                for symbol in right.iter() {
+                    if !v.contains(symbol) && !t.contains(symbol) {
+                        t.push(symbol.clone());
+                    }
+                }
+                p.push(Product {
+                    left: left.clone(),
+                    right,
+                });
+            }
+        }
+
+        Ok(Grammar {
+            s: rules[0].0.clone(),
+            v,
+            t,
+            p,
+            token_terminals: HashMap::new(),
+            terminal_labels: HashMap::new(),
+            column_order: vec![],
+        })
+    }
+
+    /// 将语法定义写回yml，与 `from_yml` 互逆
+    pub fn to_yml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+
+    /// # 将词法分析得到的token映射为文法中的终结符
+    ///
+    /// 优先使用 `token_terminals` 中该token类型对应的终结符，
+    /// 没有对应项时标识符映射为 `id`，常数映射为 `value`，其余token以其本身作为终结符
+    pub fn terminal_of(&self, token: &Token) -> String {
+        let kind = token.token_type.kind().to_string();
+        if let Some(terminal) = self.token_terminals.get(&kind) {
+            return terminal.clone();
+        }
+        match token.token_type {
+            TokenType::Identifier => "id".to_string(),
+            TokenType::Constant => "value".to_string(),
+            _ => token.token_value.clone(),
+        }
+    }
+
+    /// 终结符在错误信息中显示的名字，没有在 `terminal_labels` 中声明时为终结符本身
+    pub fn terminal_label(&self, terminal: &str) -> String {
+        self.terminal_labels
+            .get(terminal)
+            .cloned()
+            .unwrap_or_else(|| terminal.to_string())
+    }
+
+    /// # ACTION表各列的终结符
+    ///
+    /// 先按 `column_order` 排列其中声明过的终结符(包括输入结束符 `#`)，
+    /// 其余终结符按声明顺序排在其后，`#` 未列出时排在最后，ε 不作为列
+    pub fn table_terminals(&self) -> Vec<&str> {
+        let known = |t: &str| t == END_MARKER || (t != EPSILON && self.t.iter().any(|x| x == t));
+        let mut terminals: Vec<&str> = vec![];
+        for t in self.column_order.iter().map(|t| t.as_str()) {
+            if known(t) && !terminals.contains(&t) {
+                terminals.push(t);
+            }
+        }
+        for t in self.t.iter().map(|t| t.as_str()).chain([END_MARKER]) {
+            if known(t) && !terminals.contains(&t) {
+                terminals.push(t);
+            }
+        }
+        terminals
     }
 
     /// 验证语法定义是否合法
@@ -53,34 +424,240 @@ impl Grammar {
             return Err(GrammarError::new("终结符和非终结符存在重复元素"));
         }
 
+        // 验证输入结束符没有被用作文法符号
+        if s.contains(&END_MARKER.to_string()) {
+            return Err(GrammarError::new(&format!(
+                "\"{}\" 是输入结束符，不能作为终结符或非终结符",
+                END_MARKER
+            )));
+        }
+
         // 验证开始符号是否在非终结符集中
         if !self.v.contains(&self.s) {
             return Err(GrammarError::new("开始符号不在非终结符集中"));
         }
 
         // 验证产生式左部是否在非终结符集中
-        for product in &self.p {
+        for (k, product) in self.p.iter().enumerate() {
             if !self.v.contains(&product.left) {
-                return Err(GrammarError::new("产生式左部不在非终结符集中"));
+                return Err(GrammarError::new(&format!(
+                    "第{}个产生式 `{}` 的左部 \"{}\" 不在非终结符集中",
+                    k,
+                    product.to_string().trim_end(),
+                    product.left
+                )));
             }
         }
 
         // 验证产生式右部是否在非终结符集和终结符集中
-        for product in &self.p {
+        // 注: 右部为空的产生式视为 A -> ε，不需要声明ε
+        for (k, product) in self.p.iter().enumerate() {
             for right in &product.right {
                 if !self.v.contains(right) && !self.t.contains(right) {
-                    return Err(GrammarError::new("产生式右部不在非终结符集和终结符集中"));
+                    return Err(GrammarError::new(&format!(
+                        "第{}个产生式 `{}` 的右部符号 \"{}\" 不在非终结符集和终结符集中",
+                        k,
+                        product.to_string().trim_end(),
+                        right
+                    )));
                 }
             }
         }
 
+        // 验证没有重复的产生式，重复的产生式按相同的方式规约，会造成多余的归约-归约冲突
+        if let Some((i, j)) = self.duplicate_production() {
+            return Err(GrammarError::new(&format!(
+                "第{}个产生式 `{}` 与第{}个产生式重复",
+                j,
+                self.p[j].to_string().trim_end(),
+                i
+            )));
+        }
+
+        // 验证所有非终结符都能推导出终结符串
+        let productive = productive_nonterminals(self);
+        let unproductive = self
+            .v
+            .iter()
+            .filter(|v| !productive.contains(*v))
+            .map(|v| format!("\"{}\"", v))
+            .collect::<Vec<_>>();
+        if !unproductive.is_empty() {
+            return Err(GrammarError::new(&format!(
+                "非终结符 {} 无法推导出终结符串",
+                unproductive.join(", ")
+            )));
+        }
+
+        // 验证所有非终结符都能从开始符号到达
+        let reachable = reachable_nonterminals(self);
+        let unreachable = self
+            .v
+            .iter()
+            .filter(|v| !reachable.contains(*v))
+            .map(|v| format!("\"{}\"", v))
+            .collect::<Vec<_>>();
+        if !unreachable.is_empty() {
+            return Err(GrammarError::new(&format!(
+                "非终结符 {} 无法从开始符号到达",
+                unreachable.join(", ")
+            )));
+        }
+
         Ok(())
     }
+
+    /// 第一对重复的产生式的序号(i, j)，i < j，右部为空与只有ε视为相同
+    fn duplicate_production(&self) -> Option<(usize, usize)> {
+        let mut seen = HashMap::new();
+        for (j, p) in self.p.iter().enumerate() {
+            let right = p.right.iter().filter(|s| *s != EPSILON).collect::<Vec<_>>();
+            if let Some(&i) = seen.get(&(&p.left, right.clone())) {
+                return Some((i, j));
+            }
+            seen.insert((&p.left, right), j);
+        }
+        None
+    }
+
+    /// # 删除重复的产生式
+    ///
+    /// 每组重复的产生式只保留第一个，其余产生式的相对顺序不变，返回被删除的产生式在原产生式集中的序号
+    ///
+    /// 删除后其后的产生式序号会改变，应在构造分析表之前调用
+    pub fn dedup_productions(&mut self) -> Vec<usize> {
+        let mut seen = HashSet::new();
+        let mut removed = vec![];
+        let mut k = 0;
+        self.p.retain(|p| {
+            let right = p.right.iter().filter(|s| *s != EPSILON).cloned();
+            let keep = seen.insert((p.left.clone(), right.collect::<Vec<_>>()));
+            if !keep {
+                removed.push(k);
+            }
+            k += 1;
+            keep
+        });
+        removed
+    }
+
+    /// # 可空非终结符集
+    ///
+    /// 返回所有能推出ε的非终结符，不依赖FIRST集，按不动点迭代计算：
+    /// 若某个产生式右部的每个符号都是ε或已知可空的非终结符(右部为空同样成立)，则其左部可空，
+    /// 直到一轮迭代中集合不再增大
+    pub fn nullable(&self) -> HashSet<String> {
+        let mut nullable = HashSet::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for p in &self.p {
+                if nullable.contains(&p.left) {
+                    continue;
+                }
+                if p.right.iter().all(|s| s == EPSILON || nullable.contains(s)) {
+                    nullable.insert(p.left.clone());
+                    changed = true;
+                }
+            }
+        }
+        nullable
+    }
+
+    /// # 可以作为句子第一个符号的终结符
+    ///
+    /// 即开始符号的FIRST集，不含ε，按字典序排列，第一个token不在其中的输入一定不是句子，
+    /// 开始符号能否推出空串见 `nullable`
+    pub fn valid_start_tokens(&self) -> Vec<String> {
+        let first = get_first_sorted(self);
+        first
+            .get(&self.s)
+            .into_iter()
+            .flatten()
+            .filter(|t| *t != EPSILON)
+            .cloned()
+            .collect()
+    }
+
+    /// # 判断两个文法在结构上是否相同
+    ///
+    /// 见 `structural_diff`
+    pub fn structurally_eq(&self, other: &Grammar) -> bool {
+        self.structural_diff(other).is_none()
+    }
+
+    /// # 比较两个文法的结构
+    ///
+    /// 比较开始符号，将非终结符集与终结符集作为集合比较，将产生式集作为多重集比较，
+    /// 因此与各集合中元素的顺序无关，产生式右部为空与只有ε视为相同
+    ///
+    /// token类型映射、终结符显示名等附加信息不参与比较
+    /// ## 输出
+    /// - 结构相同时返回 `None`
+    /// - 否则返回描述第一处不同的信息
+    pub fn structural_diff(&self, other: &Grammar) -> Option<String> {
+        if self.s != other.s {
+            return Some(format!("开始符号不同: {} 与 {}", self.s, other.s));
+        }
+        let set_diff = |name: &str, a: &[String], b: &[String]| {
+            let a = a.iter().collect::<BTreeSet<_>>();
+            let b = b.iter().collect::<BTreeSet<_>>();
+            if let Some(x) = a.difference(&b).next() {
+                return Some(format!("{} `{}` 只出现在第一个文法中", name, x));
+            }
+            b.difference(&a)
+                .next()
+                .map(|x| format!("{} `{}` 只出现在第二个文法中", name, x))
+        };
+        if let Some(diff) = set_diff("非终结符", &self.v, &other.v) {
+            return Some(diff);
+        }
+        if let Some(diff) = set_diff("终结符", &self.t, &other.t) {
+            return Some(diff);
+        }
+
+        // 每个产生式出现的次数
+        let count = |g: &Grammar| {
+            let mut count = BTreeMap::new();
+            for p in &g.p {
+                let right = p.right.iter().filter(|s| *s != EPSILON).cloned();
+                *count
+                    .entry((p.left.clone(), right.collect::<Vec<_>>()))
+                    .or_insert(0) += 1;
+            }
+            count
+        };
+        let (a, b) = (count(self), count(other));
+        let describe = |(left, right): &(String, Vec<String>)| match right.is_empty() {
+            true => format!("{} -> {}", left, EPSILON),
+            false => format!("{} -> {}", left, right.join(" ")),
+        };
+        for (p, n) in &a {
+            let m = b.get(p).copied().unwrap_or(0);
+            if *n != m {
+                return Some(format!(
+                    "产生式 `{}` 在两个文法中分别出现{}次与{}次",
+                    describe(p),
+                    n,
+                    m
+                ));
+            }
+        }
+        b.keys()
+            .find(|p| !a.contains_key(*p))
+            .map(|p| format!("产生式 `{}` 只出现在第二个文法中", describe(p)))
+    }
 }
 
 const GRAMMAR_YML: &str = "grammar.yml";
 
-pub fn syntax_analysis(tokens: Vec<Token>) -> Result<(), SyntaxError> {
+/// 空串符号
+pub const EPSILON: &str = "ε";
+
+/// 输入结束符，分析时加在输入末尾，也是ACTION表中的一列，因此不能出现在文法的符号中
+pub const END_MARKER: &str = "#";
+
+pub fn syntax_analysis(_tokens: Vec<Token>) -> Result<(), SyntaxError> {
     let grammar_yml = std::fs::read_to_string(GRAMMAR_YML)
         .map_err(|e| SyntaxError::new(&format!("Failed to read grammar file error: {}", e)))?;
     let grammar = Grammar::from_yml(&grammar_yml)
@@ -92,24 +669,306 @@ pub fn syntax_analysis(tokens: Vec<Token>) -> Result<(), SyntaxError> {
     Ok(())
 }
 
-/// # 对输入文法G获取SLR(1)分析表
+/// # SLR(1)分析器
 ///
-/// 获取ACTION表与GOTO表
-/// 1. 将非拓广文法G转换为拓广文法G'
-/// 2. 求解拓广文法G'的FOLLOW集，规约时使用
-/// 3. 求解拓广文法G'的LR(0)项目集族
-/// 4. 遍历项目集族，构造ACTION表与GOTO表
-pub fn get_slr1_table(
-    g: &Grammar,
-) -> Result<(Vec<HashMap<String, String>>, Vec<HashMap<String, String>>), SyntaxError> {
-    let mut outreach_g = g.clone();
-    // 获取非拓广文法G的FOLLOW集，进行规约时使用
-    let follow = get_follow(&outreach_g);
+/// 保存文法及其分析表、FIRST集与FOLLOW集，避免每次分析时重新计算
+#[derive(Debug, Clone)]
+pub struct Parser {
+    /// 文法
+    pub grammar: Grammar,
+    /// ACTION表
+    pub action: ActionTable,
+    /// GOTO表
+    pub goto: Table,
+    /// FIRST集，集合中的元素已排序
+    first: HashMap<String, Vec<String>>,
+    /// FOLLOW集，集合中的元素已排序
+    follow: HashMap<String, Vec<String>>,
+}
 
-    // 将非拓广文法G转换为拓广文法G'
-    // 即修改开始符号为S'，添加产生式S' -> S，并将S'加入非终结符集
+impl Parser {
+    /// 验证文法并构造SLR(1)分析表
+    pub fn new(grammar: Grammar) -> Result<Parser, SyntaxError> {
+        grammar
+            .validate()
+            .map_err(|e| SyntaxError::new(&format!("Grammar validate error: {}", e)))?;
+        let (action, goto) = get_slr1_table(&grammar)?;
+
+        let first = get_first(&grammar);
+        let follow = get_follow(&grammar);
+
+        Ok(Parser {
+            grammar,
+            action,
+            goto,
+            first,
+            follow,
+        })
+    }
+
+    /// 符号的FIRST集，符号不在文法中时返回 `None`
+    pub fn first(&self, symbol: &str) -> Option<&[String]> {
+        self.first.get(symbol).map(|v| v.as_slice())
+    }
+
+    /// 非终结符的FOLLOW集，符号不是文法中的非终结符时返回 `None`
+    pub fn follow(&self, symbol: &str) -> Option<&[String]> {
+        self.follow.get(symbol).map(|v| v.as_slice())
+    }
+
+    /// 对token序列进行SLR(1)分析
+    pub fn parse(&self, tokens: Vec<Token>) -> bool {
+        slr1_analysis(&self.grammar, &self.action, &self.goto, tokens)
+    }
+
+    /// 对token序列进行SLR(1)分析，并记录分析过程中的事件
+    pub fn parse_events(&self, tokens: Vec<Token>) -> (bool, Vec<ParseEvent>) {
+        slr1_analysis_events(
+            &self.grammar,
+            &self.action,
+            &self.goto,
+            tokens,
+            &AnalysisOptions::default(),
+        )
+    }
+
+    /// 按照选项对token序列进行SLR(1)分析
+    pub fn parse_with_options(
+        &self,
+        tokens: Vec<Token>,
+        options: &AnalysisOptions,
+    ) -> (bool, Vec<SyntaxError>) {
+        slr1_analysis_with_options(&self.grammar, &self.action, &self.goto, tokens, options)
+    }
+}
+
+/// # 对token序列进行SLR(1)分析，同时收集分析过程中的警告
+///
+/// 分析时跳过出错的输入符号进行错误恢复，被恢复的语法错误作为警告返回
+/// ## 输出
+/// - 恢复后到达接受状态时返回 `Ok`，没有语法错误时为 `true`
+/// - 无法恢复时返回导致分析停止的语法错误
+/// - 分析过程中的警告
+pub fn parse_verbose(
+    parser: &Parser,
+    tokens: Vec<Token>,
+) -> (Result<bool, SyntaxError>, Vec<String>) {
+    let options = AnalysisOptions {
+        recover: true,
+        max_errors: usize::MAX,
+        ..Default::default()
+    };
+    let (accepted, mut errors) = slr1_run(
+        &parser.grammar,
+        &parser.action,
+        &parser.goto,
+        tokens,
+        &options,
+        &mut vec![],
+    );
+    let result = match accepted {
+        true => Ok(errors.is_empty()),
+        false => Err(errors
+            .pop()
+            .unwrap_or_else(|| SyntaxError::new("parse stopped without an error"))),
+    };
+    let warnings = errors
+        .iter()
+        .map(|e| format!("recovered from syntax error: {}", e))
+        .collect();
+    (result, warnings)
+}
+
+/// # 有界地验证分析器在所有短输入上都能停止
+///
+/// 对由文法终结符组成的、长度不超过 `max_len` 的所有符号串运行分析器，
+/// 确认每次分析都在步数上限内接受或报错，并且分析表中引用的状态和产生式都存在
+///
+/// 这只是有界的检查: 通过检查不能保证更长的输入也能停止，但能尽早发现构造错误的分析表
+pub fn verify_termination(parser: &Parser, max_len: usize) -> Result<(), String> {
+    let terminals = parser
+        .grammar
+        .t
+        .iter()
+        .filter(|t| *t != EPSILON)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    // 按长度依次枚举所有符号串
+    let mut inputs: Vec<Vec<String>> = vec![vec![]];
+    for len in 0..=max_len {
+        for input in inputs.iter() {
+            run_bounded(parser, input).map_err(|e| format!("input {:?}: {}", input, e))?;
+        }
+        if len < max_len {
+            inputs = inputs
+                .iter()
+                .flat_map(|input| {
+                    terminals.iter().map(move |t| {
+                        let mut next = input.clone();
+                        next.push(t.clone());
+                        next
+                    })
+                })
+                .collect();
+        }
+    }
+    Ok(())
+}
+
+/// # 在步数上限内对终结符串进行分析
+///
+/// 返回是否接受，超过步数上限或分析表引用了不存在的状态、产生式时返回错误
+fn run_bounded(parser: &Parser, input: &[String]) -> Result<bool, String> {
+    // 没有ε产生式循环时，两次移进之间的规约次数不会超过状态数与产生式数之积
+    let limit = (input.len() + 1) * (parser.action.len() * parser.grammar.p.len() + 1);
+
+    let mut state_stack = vec![0];
+    let mut i = 0;
+    for _ in 0..limit {
+        let state = *state_stack.last().unwrap();
+        let token = input.get(i).map_or(END_MARKER, |t| t.as_str());
+        let row = parser
+            .action
+            .get(state)
+            .ok_or_else(|| format!("state {} is not in the ACTION table", state))?;
+        let action = row.get(token).copied().unwrap_or_default();
+
+        if let Action::Shift(j) = action {
+            state_stack.push(j);
+            i += 1;
+        } else if let Action::Reduce(k) = action {
+            let p =
+                parser.grammar.p.get(k).ok_or_else(|| {
+                    format!("reduce by production {} which is not in the grammar", k)
+                })?;
+            let len = p.arity();
+            if len >= state_stack.len() {
+                return Err(format!("state stack underflow when reducing by {}", p));
+            }
+            state_stack.truncate(state_stack.len() - len);
+            let s = *state_stack.last().unwrap();
+            let goto = parser
+                .goto
+                .get(s)
+                .and_then(|row| row.get(&p.left))
+                .and_then(|j| j.parse::<usize>().ok())
+                .ok_or_else(|| format!("missing GOTO[{}, {}]", s, p.left))?;
+            state_stack.push(goto);
+        } else {
+            return Ok(action == Action::Accept);
+        }
+    }
+    Err(format!("no decision within {} steps", limit))
+}
+
+/// # 以代码构造文法
+///
+/// 依次声明开始符号、终结符与规则，`build` 时推导出非终结符集与终结符集并验证文法：
+/// - 所有规则的左部构成非终结符集，按第一次出现的顺序排列
+/// - 声明的终结符在前，规则右部中其余未声明的符号按第一次出现的顺序排在其后
+/// - 右部为空或只有 ε 的规则表示空产生式，与 `from_bnf` 一致以空的右部表示
+/// - 没有声明开始符号时，第一条规则的左部为开始符号
+#[derive(Debug, Clone, Default)]
+pub struct GrammarBuilder {
+    /// 开始符号
+    start: Option<String>,
+    /// 声明的终结符
+    terminals: Vec<String>,
+    /// 规则，即产生式的左部与右部
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl GrammarBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 声明开始符号
+    pub fn start(mut self, s: &str) -> Self {
+        self.start = Some(s.to_string());
+        self
+    }
+
+    /// 声明终结符，重复声明的终结符只保留一个
+    pub fn terminal(mut self, t: &str) -> Self {
+        if !self.terminals.iter().any(|x| x == t) {
+            self.terminals.push(t.to_string());
+        }
+        self
+    }
+
+    /// 添加产生式 `left -> right`
+    pub fn rule(mut self, left: &str, right: &[&str]) -> Self {
+        self.rules.push((
+            left.to_string(),
+            right.iter().map(|s| s.to_string()).collect(),
+        ));
+        self
+    }
+
+    /// 构造文法并进行验证(见 `Grammar::validate`)
+    pub fn build(self) -> Result<Grammar, GrammarError> {
+        let Some((first_left, _)) = self.rules.first() else {
+            return Err(GrammarError::new("文法中没有规则"));
+        };
+        let s = self.start.clone().unwrap_or_else(|| first_left.clone());
+
+        let mut v: Vec<String> = vec![];
+        for (left, _) in self.rules.iter() {
+            if !v.contains(left) {
+                v.push(left.clone());
+            }
+        }
+
+        let mut t = self.terminals;
+        let mut p = vec![];
+        for (left, mut right) in self.rules {
+            if right.iter().all(|s| s == EPSILON) {
+                right.clear();
+            }
+            for symbol in right.iter() {
+                if !v.contains(symbol) && !t.contains(symbol) {
+                    t.push(symbol.clone());
+                }
+            }
+            p.push(Product { left, right });
+        }
+
+        let g = Grammar {
+            s,
+            v,
+            t,
+            p,
+            token_terminals: HashMap::new(),
+            terminal_labels: HashMap::new(),
+            column_order: vec![],
+        };
+        g.validate()?;
+        Ok(g)
+    }
+}
+
+/// # 将非拓广文法G转换为拓广文法G'
+///
+/// 即修改开始符号为S'，添加产生式S' -> S，并将S'加入非终结符集
+///
+/// G中已有名为S'的符号时继续添加 `'`，直到新的开始符号不与G中的任何符号重复
+///
+/// 新产生式加在产生式集的末尾，因此G中产生式的序号在G'中保持不变
+///
+/// 产生式右部中的ε被去掉，即 A -> ε 统一以空的右部表示，构造项目集时不会出现 A -> ·ε 这样的项目
+pub fn augment(g: &Grammar) -> Grammar {
+    let mut outreach_g = g.clone();
+    for p in outreach_g.p.iter_mut() {
+        p.right.retain(|s| s != EPSILON);
+    }
     let raw_s = outreach_g.s.clone();
-    outreach_g.s = raw_s.clone() + "'";
+    let mut s = raw_s.clone() + "'";
+    while outreach_g.v.contains(&s) || outreach_g.t.contains(&s) {
+        s.push('\'');
+    }
+    outreach_g.s = s;
     outreach_g.v.push(outreach_g.s.clone());
     outreach_g.p.push(Product {
         left: outreach_g.s.clone(),
@@ -118,74 +977,139 @@ pub fn get_slr1_table(
     // 拓广文法的目的是保证文法的开始符号的定义只有一个产生式
     // 并且文法的开始符号不会出现在其他产生式的右部
     // 也保证了G'只有唯一的接受状态
+    outreach_g
+}
 
-    // 求解G'的LR(0)项目集族
-    let lr0_items = get_lr0_collection(&outreach_g);
+/// 分析表，每个状态对应一行，行内以符号为键
+///
+/// GOTO表的单元格为规约后转移到的状态 `N` 或空。移进与GOTO都是LR(0)自动机上的状态转移，
+/// 只是移进发生在终结符上，需要与同在ACTION表中的规约区分，因此ACTION表的单元格为 `Action`，
+/// 两者统一的视图见 `combined_goto_table`
+pub type Table = Vec<HashMap<String, String>>;
 
-    // Action表初始化
-    let mut ACTION = Vec::new();
-    let mut row = HashMap::new();
-    outreach_g.t.iter().for_each(|t| {
-        row.insert(t.clone(), "".to_string());
-    });
-    row.insert("#".to_string(), "".to_string());
-    lr0_items.iter().for_each(|_| {
-        ACTION.push(row.clone());
-    });
+/// ACTION表，每个状态对应一行，行内以终结符或 `#` 为键
+pub type ActionTable = Vec<HashMap<String, Action>>;
 
-    // Goto表初始化
-    let mut GOTO = Vec::new();
-    let mut temp_v = outreach_g.v.clone().into_iter().collect::<HashSet<_>>();
-    temp_v.remove((outreach_g.s.clone()).as_str());
-    let mut row = HashMap::new();
-    temp_v.iter().for_each(|v| {
-        row.insert(v.clone(), "".to_string());
-    });
-    lr0_items.iter().for_each(|_| {
-        GOTO.push(row.clone());
-    });
+/// # ACTION表的单元格
+///
+/// 显示为 `sN`、`rN`、`acc` 或空，与导出的分析表相同，可以由显示的字符串解析得到
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Action {
+    /// 移进并转移到状态N
+    Shift(usize),
+    /// 按第N个产生式规约
+    Reduce(usize),
+    /// 接受
+    Accept,
+    /// 空的单元格，代表出现错误
+    #[default]
+    Error,
+}
 
-    // 遍历LR(0)项目集族，填充Action表和Goto表
-    // 1. 若项目A->α.aβ属于I_k，且GO(I_k,a)=I_j，a为终结符，则置ACTION[k,a]为sj
-    // 2. 若项目A->α.属于I_k，那么对任何终结符a∈FOLLOW(A),置ACTION[k,a]为rj，假定A->α为G'的第j个产生式
-    // 3. 若项目S'->S.属于I_k，则置ACTION[k,#]为“acc”
-    // 4. 若GO(I_k,A)=I_j，A为非终结符，则置GOTO[k,A]=j
-    // 5. 若不为以上情况，则ACTION与GOTO表剩余单元格置为空，代表出现错误
-    for (i, items) in lr0_items.iter().enumerate() {
-        for item in items.iter() {
-            // 圆点不在LR(0)项目的最后，则需要移进
-            if item.dot < item.right.len() {
-                // 获取圆点项目的下一个字符
-                let ch = &item.right[item.dot];
-                // 找出项目集items在读入下一个字符ch后，转移到的项目集
-                // 即找到使得goto(I, ch) = lr0_items[j]成立的j
-                for (j, items1) in lr0_items.iter().enumerate() {
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Shift(j) => write!(f, "s{}", j),
+            Self::Reduce(k) => write!(f, "r{}", k),
+            Self::Accept => write!(f, "acc"),
+            Self::Error => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |n: &str| {
+            n.parse::<usize>()
+                .map_err(|_| format!("malformed action `{}`", s))
+        };
+        match s {
+            "" => Ok(Self::Error),
+            "acc" => Ok(Self::Accept),
+            _ => match (s.strip_prefix('s'), s.strip_prefix('r')) {
+                (Some(j), _) => number(j).map(Self::Shift),
+                (_, Some(k)) => number(k).map(Self::Reduce),
+                _ => Err(format!("malformed action `{}`", s)),
+            },
+        }
+    }
+}
+
+/// # 对输入文法G获取SLR(1)分析表
+///
+/// 获取ACTION表与GOTO表
+/// 1. 将非拓广文法G转换为拓广文法G'
+/// 2. 求解拓广文法G'的FOLLOW集，规约时使用
+/// 3. 求解拓广文法G'的LR(0)项目集族
+/// 4. 遍历项目集族，构造ACTION表与GOTO表
+///
+/// 单元格出现冲突时报告冲突并使用后填入的动作，要求文法必须是SLR(1)文法时使用 `get_slr1_table_strict`
+pub fn get_slr1_table(g: &Grammar) -> Result<(ActionTable, Table), SyntaxError> {
+    get_slr1_table_augmented(g).map(|(_, action, goto)| (action, goto))
+}
+
+/// # 对输入文法G获取SLR(1)分析表，并返回构造分析表时使用的拓广文法G'
+///
+/// ACTION表中的 `Action::Reduce(k)` 按照G'的第k个产生式规约，G'的产生式集即为分析表所使用的产生式列表，
+/// 将G'传给 `slr1_analysis` 等分析函数可以保证规约时使用的产生式与构造分析表时一致，
+/// 分析函数会识别出G'已经是拓广文法而不再次拓广，出错时报告的正在分析的产生式与传入G时相同
+///
+/// G'在G的产生式之后添加了 S' -> S，G中产生式的序号保持不变，但右部中的ε已被去掉(见 `augment`)
+pub fn get_slr1_table_augmented(g: &Grammar) -> Result<(Grammar, ActionTable, Table), SyntaxError> {
+    build_slr1_table(g, false).map_err(|e| SyntaxError::new(&e.message))
+}
+
+/// # 对输入文法G获取SLR(1)分析表，文法不是SLR(1)文法时返回错误
+///
+/// 与 `get_slr1_table` 的构造过程相同，但在发现第一个冲突时立即返回描述该冲突的错误，
+/// 而不是覆盖单元格后继续构造
+pub fn get_slr1_table_strict(g: &Grammar) -> Result<(ActionTable, Table), GrammarError> {
+    build_slr1_table(g, true).map(|(_, action, goto)| (action, goto))
+}
+
+/// 构造拓广文法与SLR(1)分析表，`strict` 为真时遇到冲突返回错误
+#[allow(non_snake_case)]
+fn build_slr1_table(
+    g: &Grammar,
+    strict: bool,
+) -> Result<(Grammar, ActionTable, Table), GrammarError> {
+    // 获取非拓广文法G的FOLLOW集，进行规约时使用
+    let follow = get_follow(g);
+
+    // 将非拓广文法G转换为拓广文法G'
+    let outreach_g = augment(g);
+
+    // 求解G'的LR(0)项目集族
+    let lr0_items = get_lr0_collection(&outreach_g);
+
+    // Action表与Goto表初始化
+    let (mut ACTION, mut GOTO) = empty_tables(&outreach_g, lr0_items.len());
+
+    // 遍历LR(0)项目集族，填充Action表和Goto表
+    // 1. 若项目A->α.aβ属于I_k，且GO(I_k,a)=I_j，a为终结符，则置ACTION[k,a]为sj
+    // 2. 若项目A->α.属于I_k，那么对任何终结符a∈FOLLOW(A),置ACTION[k,a]为rj，假定A->α为G'的第j个产生式
+    // 3. 若项目S'->S.属于I_k，则置ACTION[k,#]为“acc”
+    // 4. 若GO(I_k,A)=I_j，A为非终结符，则置GOTO[k,A]=j
+    // 5. 若不为以上情况，则ACTION与GOTO表剩余单元格置为空，代表出现错误
+    for (i, items) in lr0_items.iter().enumerate() {
+        for item in items.iter() {
+            // 圆点不在LR(0)项目的最后，则需要移进
+            if item.dot < item.right.len() {
+                // 获取圆点项目的下一个字符
+                let ch = &item.right[item.dot];
+                // 找出项目集items在读入下一个字符ch后，转移到的项目集
+                // 即找到使得goto(I, ch) = lr0_items[j]成立的j
+                for (j, items1) in lr0_items.iter().enumerate() {
                     if items_eq(&goto(items, ch, &outreach_g), items1) {
                         // 如果ch为终结符，则将ACTION[i, ch]置为sj
                         if outreach_g.t.contains(ch) {
-                            let action = format!("s{}", j);
-                            match ACTION[i].insert(ch.clone(), action.clone()) {
-                                Some(a) if a != "" && a != action => {
-                                    warn!(
-                                        "SLR action conflict: ACTION[{},\"{}\"] = {} or {}, use {}",
-                                        i, ch, a, action, action
-                                    );
-                                }
-                                _ => {}
-                            }
+                            set_slr1_cell(&mut ACTION, "ACTION", i, ch, Action::Shift(j), strict)?;
                         }
                         // 如果ch为非终结符，则将GOTO[i, ch]置为j
                         else {
-                            let goto = format!("{}", j);
-                            match GOTO[i].insert(ch.clone(), goto.clone()) {
-                                Some(g) if g != "" && g != goto => {
-                                    warn!(
-                                        "SLR goto conflict: GOTO[{},\"{}\"] = {} or {}, use {}",
-                                        i, ch, g, goto, goto
-                                    );
-                                }
-                                _ => {}
-                            }
+                            set_slr1_cell(&mut GOTO, "GOTO", i, ch, format!("{}", j), strict)?;
                         }
                         break;
                     }
@@ -195,7 +1119,7 @@ pub fn get_slr1_table(
             else {
                 // 如果是S'->S.，则将ACTION[k, #]置为acc
                 if item.left == outreach_g.s {
-                    ACTION[i].insert("#".to_string(), "acc".to_string());
+                    set_slr1_cell(&mut ACTION, "ACTION", i, END_MARKER, Action::Accept, strict)?;
                 }
                 // 否则，对于任何终结符a∈FOLLOW(A)，将ACTION[k, a]置为rj
                 else {
@@ -206,28 +1130,246 @@ pub fn get_slr1_table(
                         .unwrap();
                     let follow_left = follow.get(&item.left).unwrap();
                     for f in follow_left {
-                        if outreach_g.t.contains(f) {
-                            let action = format!("r{}", j);
-                            match ACTION[i].insert(f.clone(), action.clone()) {
-                                Some(a) if a != "" && a != action => {
-                                    warn!(
-                                        "SLR action conflict: ACTION[{},\"{}\"] = {} or {}, use {}",
-                                        i, f, a, action, action
-                                    );
-                                }
-                                _ => {}
-                            }
+                        if outreach_g.t.contains(f) || f == END_MARKER {
+                            set_slr1_cell(&mut ACTION, "ACTION", i, f, Action::Reduce(j), strict)?;
                         }
                     }
-                    if follow_left.contains(&"#".to_string()) {
-                        ACTION[i].insert("#".to_string(), format!("r{}", j));
-                    }
                 }
             }
         }
     }
 
-    Ok((ACTION, GOTO))
+    Ok((outreach_g, ACTION, GOTO))
+}
+
+/// # 填写SLR(1)分析表的单元格
+///
+/// 单元格中已有不同的内容时为冲突，`strict` 为真时返回描述冲突的错误，否则报告冲突并使用新的内容
+///
+/// ACTION表与GOTO表共用，单元格显示为空串时为空
+fn set_slr1_cell<V: PartialEq + Display>(
+    table: &mut [HashMap<String, V>],
+    name: &str,
+    state: usize,
+    symbol: &str,
+    value: V,
+    strict: bool,
+) -> Result<(), GrammarError> {
+    match table[state].get(symbol) {
+        Some(old) if !old.to_string().is_empty() && *old != value => {
+            let kind = if name == "GOTO" { "goto" } else { "action" };
+            let conflict = format!(
+                "SLR {} conflict: {}[{},\"{}\"] = {} or {}",
+                kind, name, state, symbol, old, value
+            );
+            if strict {
+                return Err(GrammarError::new(&conflict));
+            }
+            warn!("{}, use {}", conflict, value);
+        }
+        _ => {}
+    }
+    table[state].insert(symbol.to_string(), value);
+    Ok(())
+}
+
+/// # 初始化拓广文法G'的ACTION表与GOTO表
+///
+/// ACTION表的列为终结符和 #，GOTO表的列为除S'外的非终结符，单元格均为空
+#[allow(non_snake_case)]
+pub(crate) fn empty_tables(outreach_g: &Grammar, states: usize) -> (ActionTable, Table) {
+    // Action表初始化
+    let mut row = HashMap::new();
+    outreach_g.t.iter().for_each(|t| {
+        row.insert(t.clone(), Action::Error);
+    });
+    row.insert(END_MARKER.to_string(), Action::Error);
+    let ACTION = vec![row; states];
+
+    // Goto表初始化
+    let mut temp_v = outreach_g.v.clone().into_iter().collect::<HashSet<_>>();
+    temp_v.remove((outreach_g.s.clone()).as_str());
+    let mut row = HashMap::new();
+    temp_v.iter().for_each(|v| {
+        row.insert(v.clone(), "".to_string());
+    });
+    let GOTO = vec![row; states];
+
+    (ACTION, GOTO)
+}
+
+/// 分析表的规模统计
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableMetrics {
+    /// 状态数
+    pub states: usize,
+    /// ACTION表中非空单元格数
+    pub action_entries: usize,
+    /// GOTO表中非空单元格数
+    pub goto_entries: usize,
+    /// 填充密度，即非空单元格数 / 单元格总数
+    pub density: f64,
+}
+
+/// # 统计分析表的规模
+///
+/// 单元格总数为状态数乘以ACTION表与GOTO表的列数之和
+#[allow(non_snake_case)]
+pub fn table_metrics(
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+) -> TableMetrics {
+    let action_entries = ACTION
+        .iter()
+        .map(|row| row.values().filter(|a| **a != Action::Error).count())
+        .sum::<usize>();
+    let goto_entries = GOTO
+        .iter()
+        .map(|row| row.values().filter(|j| !j.is_empty()).count())
+        .sum::<usize>();
+
+    let cells = ACTION.iter().map(|row| row.len()).sum::<usize>()
+        + GOTO.iter().map(|row| row.len()).sum::<usize>();
+    let density = if cells == 0 {
+        0.0
+    } else {
+        (action_entries + goto_entries) as f64 / cells as f64
+    };
+
+    TableMetrics {
+        states: ACTION.len(),
+        action_entries,
+        goto_entries,
+        density,
+    }
+}
+
+/// # 将ACTION表中的移进与GOTO表合并为一张状态转移表
+///
+/// 每个状态对应一行，行内以终结符或非终结符为键，值为转移到的状态 `N`，
+/// 即终结符上的移进 `Action::Shift(N)`，非终结符上的GOTO保持不变，规约、接受与空的单元格不出现在表中
+///
+/// 得到的表与LR(0)自动机的状态转移一一对应
+#[allow(non_snake_case)]
+pub fn combined_goto_table(
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+) -> Table {
+    ACTION
+        .iter()
+        .zip(GOTO.iter())
+        .map(|(action, goto)| {
+            let shifts = action.iter().filter_map(|(t, a)| match a {
+                Action::Shift(j) => Some((t.clone(), j.to_string())),
+                _ => None,
+            });
+            let gotos = goto
+                .iter()
+                .filter(|(_, j)| !j.is_empty())
+                .map(|(v, j)| (v.clone(), j.clone()));
+            shifts.chain(gotos).collect()
+        })
+        .collect()
+}
+
+/// # 合并等价的状态
+///
+/// LR(0)项目集族中不会有项目集相同的两个状态，但不同的项目集在分析表中的行为可能完全相同，
+/// 这样的状态可以合并为一个以减小分析表，便于展示：
+/// 1. 去掉从状态0出发不可达的状态
+/// 2. 类似DFA最小化，反复按照ACTION表与GOTO表的行划分状态，移进与GOTO的目标状态以其所在的类代替，
+///    直到划分不再变化，同一类中的状态在任何输入上的行为都相同
+/// 3. 每一类合并为一个状态，按类中最小的原状态号排序重新编号，状态0仍为初始状态
+///
+/// 合并后的分析表与原分析表接受相同的输入，出错时报告的期望符号也相同
+#[allow(non_snake_case)]
+pub fn merge_compatible_states(
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+) -> (ActionTable, Table) {
+    let n = ACTION.len();
+    let transitions = combined_goto_table(ACTION, GOTO);
+    let mut reachable = vec![false; n];
+    let mut stack = if n > 0 { vec![0] } else { vec![] };
+    while let Some(i) = stack.pop() {
+        if std::mem::replace(&mut reachable[i], true) {
+            continue;
+        }
+        stack.extend(
+            transitions[i]
+                .values()
+                .filter_map(|j| j.parse::<usize>().ok()),
+        );
+    }
+    let states = (0..n).filter(|i| reachable[*i]).collect::<Vec<_>>();
+
+    // 将一行中的移进与GOTO的目标状态替换为其所在的类
+    let renumber_action = |row: &HashMap<String, Action>, class: &[usize]| {
+        row.iter()
+            .map(|(t, a)| {
+                let a = match a {
+                    Action::Shift(j) => Action::Shift(class[*j]),
+                    _ => *a,
+                };
+                (t.clone(), a)
+            })
+            .collect::<HashMap<_, _>>()
+    };
+    let renumber_goto = |row: &HashMap<String, String>, class: &[usize]| {
+        row.iter()
+            .map(|(v, j)| {
+                let j = match j.parse::<usize>() {
+                    Ok(j) => class[j].to_string(),
+                    Err(_) => j.clone(),
+                };
+                (v.clone(), j)
+            })
+            .collect::<HashMap<_, _>>()
+    };
+    fn sorted<V>(row: HashMap<String, V>) -> Vec<(String, V)> {
+        let mut row = row.into_iter().collect::<Vec<_>>();
+        row.sort_by(|a, b| a.0.cmp(&b.0));
+        row
+    }
+
+    // 开始时所有状态属于同一类，每一轮按(所在的类, 替换后的行)细分
+    let mut class = vec![0; n];
+    let mut classes = 1;
+    loop {
+        let mut signatures = HashMap::new();
+        let mut next = vec![0; n];
+        for &i in &states {
+            let signature = (
+                class[i],
+                sorted(renumber_action(&ACTION[i], &class)),
+                sorted(renumber_goto(&GOTO[i], &class)),
+            );
+            let len = signatures.len();
+            next[i] = *signatures.entry(signature).or_insert(len);
+        }
+        class = next;
+        if signatures.len() == classes {
+            break;
+        }
+        classes = signatures.len();
+    }
+
+    // 类按照出现的顺序编号，即按类中最小的原状态号排序
+    let mut representatives: Vec<usize> = vec![];
+    for &i in &states {
+        if class[i] == representatives.len() {
+            representatives.push(i);
+        }
+    }
+    let action = representatives
+        .iter()
+        .map(|i| renumber_action(&ACTION[*i], &class))
+        .collect();
+    let goto = representatives
+        .iter()
+        .map(|i| renumber_goto(&GOTO[*i], &class))
+        .collect();
+    (action, goto)
 }
 
 /// # SLR1 分析
@@ -239,26 +1381,410 @@ pub fn get_slr1_table(
 /// ## 输出
 /// - `true`: 分析成功
 /// - `false`: 分析失败
+#[allow(non_snake_case)]
 pub fn slr1_analysis(
     g: &Grammar,
-    ACTION: &Vec<HashMap<String, String>>,
-    GOTO: &Vec<HashMap<String, String>>,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
     tokens: Vec<Token>,
 ) -> bool {
+    slr1_analysis_with_options(g, ACTION, GOTO, tokens, &AnalysisOptions::default()).0
+}
+
+/// SLR1 分析选项
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    /// 遇到语法错误后是否进行错误恢复并继续分析，未指定 `sync` 时跳过出错的输入符号
+    pub recover: bool,
+    /// 恐慌模式错误恢复使用的同步非终结符，为 `None` 时不使用恐慌模式
+    pub sync: Option<String>,
+    /// 最多收集的语法错误数，达到后停止分析
+    pub max_errors: usize,
+    /// 将所有关键字映射为同一个终结符，为 `None` 时关键字以其本身作为终结符
+    pub keyword_terminal: Option<String>,
+    /// 已读入的符号构成完整句子后，是否将剩余的输入视为错误，为 `false` 时忽略剩余输入并接受
+    pub strict_eof: bool,
+    /// 判断token是否匹配终结符的函数，优先于 `keyword_terminal` 和文法中的映射
+    pub matcher: Option<TerminalMatcher>,
+}
+
+/// # 判断token是否匹配终结符的函数
+///
+/// token映射为终结符集中第一个与之匹配的终结符，没有匹配的终结符时按照默认的方式映射
+#[derive(Clone)]
+pub struct TerminalMatcher(Rc<MatchFn>);
+
+type MatchFn = dyn Fn(&Token, &str) -> bool;
+
+impl TerminalMatcher {
+    pub fn new(matcher: impl Fn(&Token, &str) -> bool + 'static) -> Self {
+        Self(Rc::new(matcher))
+    }
+
+    /// token是否匹配终结符 `terminal`
+    pub fn matches(&self, token: &Token, terminal: &str) -> bool {
+        (self.0)(token, terminal)
+    }
+}
+
+impl Debug for TerminalMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TerminalMatcher")
+    }
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            recover: false,
+            sync: None,
+            max_errors: 1,
+            keyword_terminal: None,
+            strict_eof: true,
+            matcher: None,
+        }
+    }
+}
+
+impl AnalysisOptions {
+    /// 将词法分析得到的token映射为文法中的终结符，
+    /// 优先级依次为 `matcher`、`keyword_terminal` 和文法中的映射
+    fn terminal_of(&self, g: &Grammar, token: &Token) -> String {
+        if let Some(matcher) = &self.matcher {
+            let terminal =
+                g.t.iter()
+                    .find(|t| *t != EPSILON && matcher.matches(token, t));
+            if let Some(terminal) = terminal {
+                return terminal.clone();
+            }
+        }
+        match (&token.token_type, &self.keyword_terminal) {
+            (TokenType::Keyword, Some(terminal)) => terminal.clone(),
+            _ => g.terminal_of(token),
+        }
+    }
+}
+
+/// # 按照选项进行 SLR1 分析
+/// ## 输入
+/// - `g`: 文法
+/// - `ACTION`: Action表
+/// - `GOTO`: Goto表
+/// - `token`: 词法分析得到的token序列
+/// - `options`: 分析选项
+/// ## 输出
+/// - 是否分析成功，存在语法错误时即使恢复后接受也视为失败
+/// - 分析过程中收集到的语法错误
+#[allow(non_snake_case)]
+pub fn slr1_analysis_with_options(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    tokens: Vec<Token>,
+    options: &AnalysisOptions,
+) -> (bool, Vec<SyntaxError>) {
+    let (accepted, errors) = slr1_run(g, ACTION, GOTO, tokens, options, &mut vec![]);
+    (accepted && errors.is_empty(), errors)
+}
+
+/// 分析过程中的一个事件
+///
+/// 事件序列完整地记录了分析过程，可以据此重放分析或构造语法树
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEvent {
+    /// 移进终结符 `token`，并转移到状态 `state`
+    Shift { state: usize, token: String },
+    /// 按照第 `production` 个产生式规约
+    Reduce { production: usize },
+    /// 规约后根据GOTO表转移到状态 `state`
+    Goto { state: usize },
+    /// 接受
+    Accept,
+    /// 在状态 `state` 遇到无法处理的终结符 `token`
+    Error { state: usize, token: String },
+    /// 错误恢复或忽略剩余输入时丢弃了终结符 `token`，不包括 `#`
+    Skip { token: String },
+}
+
+/// # 按照选项进行 SLR1 分析，并记录分析过程中的事件
+/// ## 输出
+/// - 是否分析成功，与 `slr1_analysis_with_options` 相同
+/// - 分析过程中的事件序列
+#[allow(non_snake_case)]
+pub fn slr1_analysis_events(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    tokens: Vec<Token>,
+    options: &AnalysisOptions,
+) -> (bool, Vec<ParseEvent>) {
+    let mut events = vec![];
+    let (accepted, errors) = slr1_run(g, ACTION, GOTO, tokens, options, &mut events);
+    (accepted && errors.is_empty(), events)
+}
+
+/// # SLR1 分析过程中状态栈与符号栈的变化
+///
+/// 返回每一步分析开始时的(状态栈, 符号栈)，第一个为初始的 `([0], ["#"])`，
+/// 每次移进或规约(及其后的GOTO)后记录一次，接受或出错的一步之后不再记录，
+/// 因此快照数与分析的步数相同
+#[allow(non_snake_case)]
+pub fn stack_evolution(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    tokens: Vec<Token>,
+) -> Vec<(Vec<usize>, Vec<String>)> {
+    let (_, events) = slr1_analysis_events(g, ACTION, GOTO, tokens, &AnalysisOptions::default());
+    replay_stacks(g, events)
+}
+
+/// 重放事件序列，返回每一步分析开始时的(状态栈, 符号栈)，含义与 `stack_evolution` 相同
+fn replay_stacks(g: &Grammar, events: Vec<ParseEvent>) -> Vec<(Vec<usize>, Vec<String>)> {
+    let mut state_stack = vec![0];
+    let mut symbol_stack = vec![END_MARKER.to_string()];
+    let mut snapshots = vec![(state_stack.clone(), symbol_stack.clone())];
+    // 最近一次规约的产生式左部，GOTO时压入符号栈
+    let mut left = None;
+    for event in events {
+        match event {
+            ParseEvent::Shift { state, token } => {
+                state_stack.push(state);
+                symbol_stack.push(token);
+            }
+            ParseEvent::Reduce { production } => {
+                let p = &g.p[production];
+                state_stack.truncate(state_stack.len() - p.arity());
+                symbol_stack.truncate(symbol_stack.len() - p.arity());
+                left = Some(p.left.clone());
+                continue;
+            }
+            ParseEvent::Goto { state } => {
+                state_stack.push(state);
+                symbol_stack.extend(left.take());
+            }
+            ParseEvent::Accept | ParseEvent::Error { .. } | ParseEvent::Skip { .. } => continue,
+        }
+        snapshots.push((state_stack.clone(), symbol_stack.clone()));
+    }
+    snapshots
+}
+
+/// 分析过程中一步所做的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepAction {
+    /// 移进并转移到状态N
+    Shift(usize),
+    /// 按照第k个产生式规约
+    Reduce(usize),
+    /// 接受
+    Accept,
+}
+
+/// 分析过程中的一步
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// 这一步开始时的状态栈
+    pub state_stack: Vec<usize>,
+    /// 这一步开始时的符号栈
+    pub symbol_stack: Vec<String>,
+    /// 这一步开始时剩余的输入，以 `#` 结尾
+    pub input: Vec<String>,
+    /// 这一步所做的动作
+    pub action: StepAction,
+}
+
+/// # SLR1 分析的每一步
+///
+/// 与 `slr1_analysis_with_log` 记录的过程相同，但以结构化的形式返回，调用者可以自行渲染为表格等形式
+///
+/// 分析失败时返回遇到的语法错误
+#[allow(non_snake_case)]
+pub fn derivation_steps(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    tokens: Vec<Token>,
+) -> Result<Vec<Step>, SyntaxError> {
+    let options = AnalysisOptions::default();
+    let mut input = tokens
+        .iter()
+        .map(|token| options.terminal_of(g, token))
+        .collect::<VecDeque<_>>();
+    input.push_back(END_MARKER.to_string());
+
+    let mut events = vec![];
+    let (accepted, errors) = slr1_run(g, ACTION, GOTO, tokens, &options, &mut events);
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+    if !accepted {
+        return Err(SyntaxError::new("input was not accepted"));
+    }
+
+    // GOTO是规约的一部分，不单独作为一步，分析成功时也没有出错或丢弃终结符的事件
+    let actions = events
+        .iter()
+        .filter_map(|event| match event {
+            ParseEvent::Shift { state, .. } => Some(StepAction::Shift(*state)),
+            ParseEvent::Reduce { production } => Some(StepAction::Reduce(*production)),
+            ParseEvent::Accept => Some(StepAction::Accept),
+            ParseEvent::Goto { .. } | ParseEvent::Error { .. } | ParseEvent::Skip { .. } => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut steps = vec![];
+    for ((state_stack, symbol_stack), action) in replay_stacks(g, events).into_iter().zip(actions) {
+        let remaining = input.iter().cloned().collect();
+        if let StepAction::Shift(_) = action {
+            input.pop_front();
+        }
+        steps.push(Step {
+            state_stack,
+            symbol_stack,
+            input: remaining,
+            action,
+        });
+    }
+    Ok(steps)
+}
+
+/// # SLR1 分析得到的最右推导
+///
+/// 返回分析过程中依次规约所用的产生式序号，逆序即为输入串的最右推导，
+/// 从开始符号起依次用这些产生式替换句型中最右的非终结符即可得到推导的每一步
+///
+/// 分析失败时返回遇到的语法错误
+#[allow(non_snake_case)]
+pub fn slr1_rightmost_derivation(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    tokens: Vec<Token>,
+) -> Result<Vec<usize>, SyntaxError> {
+    let mut events = vec![];
+    let (accepted, errors) = slr1_run(
+        g,
+        ACTION,
+        GOTO,
+        tokens,
+        &AnalysisOptions::default(),
+        &mut events,
+    );
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+    if !accepted {
+        return Err(SyntaxError::new("input was not accepted"));
+    }
+    Ok(events
+        .into_iter()
+        .filter_map(|event| match event {
+            ParseEvent::Reduce { production } => Some(production),
+            _ => None,
+        })
+        .collect())
+}
+
+/// 语义动作，由产生式右部各符号的值计算左部的值
+pub type SemanticAction<T> = Box<dyn Fn(Vec<T>) -> T>;
+
+/// # 进行SLR1分析，并在每次规约时执行语义动作
+///
+/// 即自底向上地计算综合属性：移进时压入由 `leaf` 根据token计算的值，
+/// 按第k个产生式规约时弹出右部各符号的值，以其调用 `actions[k]` 并压入结果，接受时返回开始符号的值
+///
+/// 没有语义动作的产生式若右部只有一个符号则直接传递该符号的值，如 `E -> T`，否则返回错误
+#[allow(non_snake_case)]
+pub fn slr1_parse_with_actions<T>(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    tokens: Vec<Token>,
+    actions: &HashMap<usize, SemanticAction<T>>,
+    leaf: impl Fn(&Token) -> T,
+) -> Result<T, SyntaxError> {
+    let mut events = vec![];
+    let (accepted, errors) = slr1_run(
+        g,
+        ACTION,
+        GOTO,
+        tokens.clone(),
+        &AnalysisOptions::default(),
+        &mut events,
+    );
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+    if !accepted {
+        return Err(SyntaxError::new("input was not accepted"));
+    }
+
+    let mut tokens = tokens.iter();
+    let mut values: Vec<T> = vec![];
+    for event in events {
+        match event {
+            ParseEvent::Shift { .. } => values.push(leaf(tokens.next().unwrap())),
+            ParseEvent::Skip { .. } => {
+                tokens.next();
+            }
+            ParseEvent::Reduce { production } => {
+                let children = values.split_off(values.len() - g.p[production].arity());
+                let value = match actions.get(&production) {
+                    Some(action) => action(children),
+                    None if children.len() == 1 => children.into_iter().next().unwrap(),
+                    None => {
+                        return Err(SyntaxError::new(&format!(
+                            "no semantic action for production {} ({})",
+                            production, g.p[production]
+                        )))
+                    }
+                };
+                values.push(value);
+            }
+            ParseEvent::Accept => break,
+            ParseEvent::Goto { .. } | ParseEvent::Error { .. } => {}
+        }
+    }
+    values
+        .pop()
+        .ok_or_else(|| SyntaxError::new("input was not accepted"))
+}
+
+/// # 按照选项运行SLR1分析
+///
+/// 返回分析是否到达接受状态(可能经过了错误恢复)以及收集到的语法错误，分析过程中的事件记录在 `events` 中
+#[allow(non_snake_case)]
+fn slr1_run(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    tokens: Vec<Token>,
+    options: &AnalysisOptions,
+    events: &mut Vec<ParseEvent>,
+) -> (bool, Vec<SyntaxError>) {
+    let mut errors = vec![];
+
+    // 恐慌模式下同步非终结符的FOLLOW集
+    let sync_follow = options.sync.as_ref().map(|sync| {
+        let follow = get_follow(g);
+        (sync, follow.get(sync).cloned().unwrap_or_default())
+    });
+    // 上一次恐慌模式恢复时剩余的输入长度，用于保证恢复后分析能够前进
+    let mut last_recovery = None;
+    // LR(0)项目集族，第一次出错时才求解，用于报告出错时正在分析的产生式
+    let mut lr0_items = None;
+
     // 初始化状态栈和符号栈
     let mut state_stack = vec![0];
-    let mut symbol_stack = vec!["#".to_string()];
+    let mut symbol_stack = vec![END_MARKER.to_string()];
 
     // 输入缓冲区
     let mut buffer = tokens
-        .into_iter()
-        .map(|token| match token.token_type {
-            TokenType::Identifier => "id".to_string(),
-            TokenType::Constant => "value".to_string(),
-            _ => token.token_value,
-        })
+        .iter()
+        .map(|token| options.terminal_of(g, token))
         .collect::<VecDeque<String>>();
-    buffer.push_back("#".to_string());
+    buffer.push_back(END_MARKER.to_string());
     debug!("init buffer: {:?}", buffer);
 
     let mut step = 1;
@@ -275,37 +1801,42 @@ pub fn slr1_analysis(
             Some(token) => token,
             None => {
                 error!("输入缓冲区为空");
-                return false;
-            }
-        };
-        // 获取ACTION表中的状态
-        let action = match ACTION[*state].get(token) {
-            Some(action) => action,
-            None => {
-                error!("ACTION表中没有状态({}, {})", state, token);
-                return false;
+                return (false, errors);
             }
         };
+        // 获取ACTION表中的状态，没有对应状态时视为错误
+        let action = ACTION[*state].get(token).copied().unwrap_or_default();
         debug!("state: {}, token: {}, action: {:?}", state, token, action);
         // 如果是移进
-        if action.starts_with("s") {
+        if let Action::Shift(j) = action {
             debug!(
                 "移进: 将 {} 状态压入状态栈，将 {} 符号压入符号栈",
                 action, token
             );
+            events.push(ParseEvent::Shift {
+                state: j,
+                token: token.clone(),
+            });
             // 将状态压入状态栈
-            state_stack.push(action[1..].parse::<usize>().unwrap());
+            state_stack.push(j);
             // 将输入缓冲区第一个元素压入符号栈
             symbol_stack.push(buffer.pop_front().unwrap());
         }
         // 如果是规约
-        else if action.starts_with("r") {
-            // 获取产生式
-            let k = action[1..].parse::<usize>().unwrap();
-            let p = &g.p[k];
+        else if let Action::Reduce(k) = action {
+            // 获取产生式，分析表来自其他文法时产生式的序号可能超出范围
+            let Some(p) = g.p.get(k) else {
+                error!("第{}个产生式不在文法中，分析表与文法不一致", k);
+                errors.push(SyntaxError::new(&format!(
+                    "reduce by production {} which is not in the grammar",
+                    k
+                )));
+                return (false, errors);
+            };
             debug!("规约: 按照第{}个产生式 {} 进行规约", k, p);
+            events.push(ParseEvent::Reduce { production: k });
             // 弹出状态栈中与产生式右部长度相同的元素
-            for _ in 0..p.right.len() {
+            for _ in 0..p.arity() {
                 state_stack.pop();
                 symbol_stack.pop();
             }
@@ -313,43 +1844,414 @@ pub fn slr1_analysis(
             symbol_stack.push(p.left.clone());
             // 获取GOTO表中的状态
             let s = state_stack.last().unwrap();
-            let state = GOTO[*s].get(&p.left).unwrap();
+            let state = GOTO[*s].get(&p.left).unwrap().parse::<usize>().unwrap();
+            events.push(ParseEvent::Goto { state });
             // 将状态压入状态栈
-            state_stack.push(state.parse::<usize>().unwrap());
+            state_stack.push(state);
         }
         // 如果是接受
-        else if action == "acc" {
+        else if action == Action::Accept {
             debug!("接受");
-            return true;
+            events.push(ParseEvent::Accept);
+            return (true, errors);
         }
         // 如果是错误
         else {
-            error!("错误");
-            return false;
-        }
-    }
-}
+            events.push(ParseEvent::Error {
+                state: *state,
+                token: token.clone(),
+            });
+            // 已读入的符号构成一个完整的句子，剩余的输入为多余的符号
+            // 错误恢复时剩余的输入可能在跳过出错符号后继续构成句子，按一般错误处理
+            if token != END_MARKER && accepts_at_end(g, ACTION, GOTO, &state_stack) {
+                if !options.strict_eof {
+                    debug!("忽略剩余输入: {:?}", buffer);
+                    // 丢弃剩余输入后继续分析，完成剩余的规约并接受
+                    events.pop();
+                    while buffer.front().is_some_and(|t| t != END_MARKER) {
+                        skip_input(&mut buffer, events);
+                    }
+                    continue;
+                }
+                if !options.recover {
+                    error!("错误: 句子已结束，剩余输入 {:?}", buffer);
+                    let mut syntax_error = SyntaxError::new(&format!(
+                        "trailing input `{}` after a complete sentence",
+                        token
+                    ));
+                    // 输入缓冲区总是原始输入的后缀加上 #
+                    syntax_error.token_index = Some(tokens.len() + 1 - buffer.len());
+                    errors.push(syntax_error);
+                    return (false, errors);
+                }
+            }
 
+            let expected = expected_terminals(g, &ACTION[*state]);
+            error!(
+                "错误: ACTION表中没有状态({}, {})，可以接受 {:?}",
+                state, token, expected
+            );
+            let mut message = format!("unexpected `{}` in state {}", token, state);
+            if !expected.is_empty() {
+                let expected = expected
+                    .iter()
+                    .map(|t| format!("`{}`", t))
+                    .collect::<Vec<_>>();
+                message.push_str(&format!(", expected one of {}", expected.join(", ")));
+            }
+            let mut syntax_error = SyntaxError::with_expected(&message, expected);
+            let lr0_items = lr0_items.get_or_insert_with(|| table_lr0_collection(g, ACTION, GOTO));
+            if let Some((start, lr0_items)) = lr0_items {
+                syntax_error.in_progress = in_progress_items(start, lr0_items, *state);
+            }
+            syntax_error.token_index =
+                Some(tokens.len() + 1 - buffer.len()).filter(|i| *i < tokens.len());
+            errors.push(syntax_error);
+            // 不恢复、错误数达到上限或已到达输入末尾时停止分析
+            if !options.recover || errors.len() >= options.max_errors || token == END_MARKER {
+                return (false, errors);
+            }
+            let Some((sync, follow)) = &sync_follow else {
+                // 跳过出错的输入符号
+                debug!("跳过输入符号 {}", token);
+                skip_input(&mut buffer, events);
+                continue;
+            };
+
+            // 恐慌模式: 弹出状态直到栈顶状态存在同步非终结符的GOTO
+            let goto = loop {
+                let Some(s) = state_stack.last() else {
+                    return (false, errors);
+                };
+                if let Some(j) = GOTO[*s].get(*sync).and_then(|j| j.parse::<usize>().ok()) {
+                    break j;
+                }
+                state_stack.pop();
+                symbol_stack.pop();
+            };
+            // 上次恢复后没有读入任何符号时先丢弃一个输入符号，避免在同一位置反复恢复
+            if last_recovery == Some(buffer.len()) {
+                skip_input(&mut buffer, events);
+            }
+            // 丢弃输入符号直到遇到同步非终结符FOLLOW集中的符号
+            while let Some(token) = buffer.front() {
+                if follow.contains(token) {
+                    break;
+                }
+                debug!("丢弃输入符号 {}", token);
+                skip_input(&mut buffer, events);
+            }
+            if buffer.is_empty() {
+                return (false, errors);
+            }
+            debug!("同步: 将 {} 压入符号栈，状态 {} 压入状态栈", sync, goto);
+            last_recovery = Some(buffer.len());
+            events.push(ParseEvent::Goto { state: goto });
+            state_stack.push(goto);
+            symbol_stack.push(sync.to_string());
+        }
+    }
+}
+
+/// 丢弃输入缓冲区的第一个符号，丢弃的不是 `#` 时记录 `Skip` 事件
+fn skip_input(buffer: &mut VecDeque<String>, events: &mut Vec<ParseEvent>) {
+    if let Some(token) = buffer.pop_front() {
+        if token != END_MARKER {
+            events.push(ParseEvent::Skip { token });
+        }
+    }
+}
+
+/// # 分析过程中没有被移进的token
+///
+/// 按照选项进行分析，返回输入中没有被移进的token，按在输入中的顺序排列，
+/// 包括错误恢复时跳过的token、被忽略的剩余输入，以及分析停止时尚未读入的token
+///
+/// 没有语法错误的分析返回空，不为空时可以据此了解错误恢复忽略了哪些输入
+#[allow(non_snake_case)]
+pub fn unshifted_tokens(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    tokens: Vec<Token>,
+    options: &AnalysisOptions,
+) -> Vec<Token> {
+    let mut events = vec![];
+    slr1_run(g, ACTION, GOTO, tokens.clone(), options, &mut events);
+
+    let mut tokens = tokens.into_iter();
+    let mut unshifted = vec![];
+    for event in events {
+        match event {
+            ParseEvent::Shift { .. } => {
+                tokens.next();
+            }
+            ParseEvent::Skip { .. } => unshifted.extend(tokens.next()),
+            _ => {}
+        }
+    }
+    unshifted.extend(tokens);
+    unshifted
+}
+
+/// # 状态中可以接受的终结符
+///
+/// 即ACTION表该行中非空的列，按终结符集的顺序排列，# 在最后，终结符以 `terminal_label` 显示
+fn expected_terminals(g: &Grammar, row: &HashMap<String, Action>) -> Vec<String> {
+    g.t.iter()
+        .map(|t| t.as_str())
+        .chain([END_MARKER])
+        .filter(|t| row.get(*t).is_some_and(|a| *a != Action::Error))
+        .map(|t| g.terminal_label(t))
+        .collect()
+}
+
+/// # 构造分析表所用的LR(0)项目集族
+///
+/// 返回拓广文法的开始符号与LR(0)项目集族
+///
+/// 分析表也可能由LR(1)、LALR(1)等方法构造，其状态数可能与LR(0)项目集族相同但编号不同，
+/// 因此只有分析表中每个移进与GOTO的目标状态都与LR(0)自动机的状态转移一致时才返回项目集族，否则返回 `None`
+///
+/// 出现冲突时移进可能被规约覆盖，此时不检查该单元格
+///
+/// `g` 已经是拓广文法(如 `get_slr1_table_augmented` 返回的G')时同样尝试直接使用 `g` 的项目集族，
+/// 而不是再次拓广
+#[allow(non_snake_case)]
+fn table_lr0_collection(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+) -> Option<(String, Vec<Vec<Item>>)> {
+    let mut candidates = vec![augment(g)];
+    if is_augmented(g) {
+        candidates.push(g.clone());
+    }
+    candidates.into_iter().find_map(|outreach_g| {
+        let lr0_items = get_lr0_collection(&outreach_g);
+        lr0_matches_table(&outreach_g, &lr0_items, ACTION, GOTO)
+            .then_some((outreach_g.s, lr0_items))
+    })
+}
+
+/// 文法是否已经是拓广文法，即开始符号只有一个右部为单个符号的产生式，且不出现在任何产生式的右部
+fn is_augmented(g: &Grammar) -> bool {
+    let mut start_productions = g.p.iter().filter(|p| p.left == g.s);
+    let single = start_productions.next().is_some_and(|p| p.right.len() == 1)
+        && start_productions.next().is_none();
+    single && !g.p.iter().any(|p| p.right.contains(&g.s))
+}
+
+/// 分析表中移进与GOTO的目标状态是否都与LR(0)自动机的状态转移一致
+#[allow(non_snake_case)]
+fn lr0_matches_table(
+    outreach_g: &Grammar,
+    lr0_items: &[Vec<Item>],
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+) -> bool {
+    if lr0_items.len() != ACTION.len() || lr0_items.len() != GOTO.len() {
+        return false;
+    }
+    for (i, items) in lr0_items.iter().enumerate() {
+        let symbols = items
+            .iter()
+            .filter_map(|item| item.right.get(item.dot))
+            .collect::<HashSet<_>>();
+        for x in symbols {
+            let target = goto(items, x, outreach_g);
+            let j = lr0_items.iter().position(|items| items_eq(items, &target));
+            let consistent = match ACTION[i].get(x) {
+                Some(Action::Shift(k)) => Some(*k) == j,
+                Some(_) => true,
+                None => GOTO[i].get(x).and_then(|k| k.parse::<usize>().ok()) == j,
+            };
+            if !consistent {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// # 状态中正在分析的产生式
+///
+/// 即该状态的项目中圆点不在最左端的项目，不包括拓广时添加的以 `start` 为左部的产生式
+///
+/// 状态编号按照 `table_lr0_collection` 返回的LR(0)项目集族的编号
+fn in_progress_items(start: &str, lr0_items: &[Vec<Item>], state: usize) -> Vec<Item> {
+    lr0_items[state]
+        .iter()
+        .filter(|item| item.dot > 0 && item.left != start)
+        .cloned()
+        .collect()
+}
+
+/// # 判断当前状态栈在读到输入末尾时是否会被接受
+///
+/// 以 # 为下一个输入符号模拟规约，直到接受或出错
+#[allow(non_snake_case)]
+fn accepts_at_end(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    state_stack: &[usize],
+) -> bool {
+    let mut state_stack = state_stack.to_vec();
+    reduce_until(g, ACTION, GOTO, &mut state_stack, END_MARKER) == Action::Accept
+}
+
+/// # 以 `terminal` 为下一个输入符号进行所有的规约
+///
+/// 返回规约完成后ACTION表中的动作，即移进、接受或空(出错)，规约无法进行时同样返回空
+#[allow(non_snake_case)]
+fn reduce_until(
+    g: &Grammar,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
+    state_stack: &mut Vec<usize>,
+    terminal: &str,
+) -> Action {
+    loop {
+        let state = *state_stack.last().unwrap();
+        let action = ACTION[state].get(terminal).copied().unwrap_or_default();
+        let Action::Reduce(k) = action else {
+            return action;
+        };
+        let Some(p) = g.p.get(k) else {
+            return Action::Error;
+        };
+        state_stack.truncate(state_stack.len() - p.arity());
+        let s = state_stack.last().unwrap();
+        match GOTO[*s].get(&p.left).and_then(|j| j.parse::<usize>().ok()) {
+            Some(j) => state_stack.push(j),
+            None => return Action::Error,
+        }
+    }
+}
+
+/// # 按顺序读入token直到出错
+///
+/// 返回成功移进的token数与此时的状态栈，读入的token总是某个句子的前缀
+fn read_prefix(parser: &Parser, tokens: &[Token]) -> (usize, Vec<usize>) {
+    let g = &parser.grammar;
+    let mut state_stack = vec![0];
+    for (i, token) in tokens.iter().enumerate() {
+        let terminal = g.terminal_of(token);
+        let action = reduce_until(g, &parser.action, &parser.goto, &mut state_stack, &terminal);
+        match action {
+            Action::Shift(j) => state_stack.push(j),
+            _ => return (i, state_stack),
+        }
+    }
+    (tokens.len(), state_stack)
+}
+
+/// # 求能够被分析的最长前缀
+///
+/// 返回在出错之前读入的token数，即仍可能被扩展为句子的最长前缀的长度，以及整个输入是否被接受
+///
+/// 整个输入都是句子的前缀但不是完整的句子时，前缀长度为输入长度，且不被接受
+pub fn parse_longest_prefix(parser: &Parser, tokens: &[Token]) -> (usize, bool) {
+    let (read, state_stack) = read_prefix(parser, tokens);
+    let accepted = read == tokens.len()
+        && accepts_at_end(&parser.grammar, &parser.action, &parser.goto, &state_stack);
+    (read, accepted)
+}
+
+/// 补全时的一个候选终结符
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// 可以接在已读入的token之后的终结符
+    pub terminal: String,
+    /// 以该终结符开始的非终结符，为空时该终结符只是继续当前的产生式
+    pub starts: Vec<String>,
+}
+
+/// 已读入部分输入后的补全上下文
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionContext {
+    /// 已读入的token是否为某个句子的前缀，不是时没有候选终结符
+    pub valid_prefix: bool,
+    /// 已读入的token是否已经构成一个完整的句子
+    pub complete: bool,
+    /// 候选终结符，按终结符集的顺序排列
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// # 求已读入部分输入后的补全上下文
+///
+/// 对每个终结符模拟规约，能够被移进的终结符即为候选终结符
+///
+/// 在移进该终结符的状态中，若存在项目 B -> ·γ 且该终结符属于 FIRST(γ)，则该终结符开始了一个新的非终结符 B
+pub fn completion_context(parser: &Parser, consumed: &[Token]) -> CompletionContext {
+    let g = &parser.grammar;
+    let (action, goto) = (&parser.action, &parser.goto);
+
+    // 分析已读入的token
+    let (read, state_stack) = read_prefix(parser, consumed);
+    if read < consumed.len() {
+        return CompletionContext {
+            valid_prefix: false,
+            complete: false,
+            suggestions: vec![],
+        };
+    }
+
+    let outreach_g = augment(g);
+    let lr0_items = get_lr0_collection(&outreach_g);
+    let first = get_first_sets(g);
+    let mut first = SequenceFirst::new(&first);
+
+    let mut suggestions = vec![];
+    for t in g.t.iter().filter(|t| *t != EPSILON) {
+        let mut stack = state_stack.clone();
+        if !matches!(
+            reduce_until(g, action, goto, &mut stack, t),
+            Action::Shift(_)
+        ) {
+            continue;
+        }
+        // 移进该终结符的状态中以该终结符开始的非终结符
+        let mut starts = vec![];
+        for item in lr0_items[*stack.last().unwrap()].iter() {
+            if item.dot != 0 || item.left == outreach_g.s || starts.contains(&item.left) {
+                continue;
+            }
+            if !item.right.is_empty() && first.get(&item.right).contains(t) {
+                starts.push(item.left.clone());
+            }
+        }
+        suggestions.push(Suggestion {
+            terminal: t.clone(),
+            starts,
+        });
+    }
+
+    CompletionContext {
+        valid_prefix: true,
+        complete: accepts_at_end(g, action, goto, &state_stack),
+        suggestions,
+    }
+}
+
+#[allow(non_snake_case)]
 pub fn slr1_analysis_with_log(
     g: &Grammar,
-    ACTION: &Vec<HashMap<String, String>>,
-    GOTO: &Vec<HashMap<String, String>>,
+    ACTION: &[HashMap<String, Action>],
+    GOTO: &[HashMap<String, String>],
     tokens: Vec<Token>,
 ) -> bool {
     // 初始化状态栈和符号栈
     let mut state_stack = vec![0];
-    let mut symbol_stack = vec!["#".to_string()];
+    let mut symbol_stack = vec![END_MARKER.to_string()];
 
     // 输入缓冲区
     let mut buffer = tokens
         .into_iter()
-        .map(|token| match token.token_type {
-            TokenType::Identifier => "id".to_string(),
-            TokenType::Constant => "value".to_string(),
-            _ => token.token_value,
-        })
+        .map(|token| g.terminal_of(&token))
         .collect::<VecDeque<String>>();
-    buffer.push_back("#".to_string());
+    buffer.push_back(END_MARKER.to_string());
     info!("init buffer: {:?}", buffer);
 
     let mut step = 1;
@@ -371,36 +2273,40 @@ pub fn slr1_analysis_with_log(
         };
         // 获取ACTION表中的状态
         let action = match ACTION[*state].get(token) {
-            Some(action) => action,
+            Some(action) => *action,
             None => {
-                error!("ACTION表中没有状态({}, {})", state, token);
+                error!(
+                    "ACTION表中没有状态({}, {})，可以接受 {:?}",
+                    state,
+                    token,
+                    expected_terminals(g, &ACTION[*state])
+                );
                 return false;
             }
         };
         info!("state: {}, token: {}, action: {:?}", state, token, action);
         // 如果是移进
-        if action.starts_with("s") {
+        if let Action::Shift(j) = action {
             info!(
                 "移进: 将 {} 状态压入状态栈，将 {} 符号压入符号栈",
                 action, token
             );
             // 将状态压入状态栈
-            state_stack.push(action[1..].parse::<usize>().unwrap());
+            state_stack.push(j);
             // 将输入缓冲区第一个元素压入符号栈
             symbol_stack.push(buffer.pop_front().unwrap());
         }
         // 如果是规约
-        else if action.starts_with("r") {
+        else if let Action::Reduce(k) = action {
             // 获取产生式
-            let k = action[1..].parse::<usize>().unwrap();
             let p = &g.p[k];
             info!("规约: 按照第{}个产生式 {} 进行规约", k, p);
             // 弹出状态栈中与产生式右部长度相同的元素
-            for _ in 0..p.right.len() {
+            for _ in 0..p.arity() {
                 state_stack.pop();
                 symbol_stack.pop();
             }
-            info!("弹出{}个状态栈和符号栈中的元素", p.right.len());
+            info!("弹出{}个状态栈和符号栈中的元素", p.arity());
             info!("state_stack: {:?}", state_stack);
             info!("symbol_stack: {:?}", symbol_stack);
             // 获取GOTO表中的状态
@@ -419,7 +2325,7 @@ pub fn slr1_analysis_with_log(
             info!("symbol_stack: {:?}", symbol_stack);
         }
         // 如果是接受
-        else if action == "acc" {
+        else if action == Action::Accept {
             info!("接受");
             return true;
         }
@@ -431,173 +2337,278 @@ pub fn slr1_analysis_with_log(
     }
 }
 
+/// 符号到终结符集合的映射，用于表示FIRST集与FOLLOW集
+pub type SymbolSets = HashMap<String, HashSet<String>>;
+
+/// # 将集合转换为元素已排序的 `Vec`
+pub fn sorted_sets(sets: &SymbolSets) -> HashMap<String, Vec<String>> {
+    sets.iter()
+        .map(|(symbol, set)| {
+            let mut set = set.iter().cloned().collect::<Vec<_>>();
+            set.sort();
+            (symbol.clone(), set)
+        })
+        .collect()
+}
+
+/// # 求文法G中所有符号的FIRST集，集合中的元素已排序
 pub fn get_first(g: &Grammar) -> HashMap<String, Vec<String>> {
+    sorted_sets(&get_first_sets(g))
+}
+
+/// # 求文法G中所有符号的FIRST集，符号与集合中的元素均已排序
+///
+/// 遍历顺序与输出都是确定的，可以直接用于打印或比较
+pub fn get_first_sorted(g: &Grammar) -> BTreeMap<String, Vec<String>> {
+    get_first(g).into_iter().collect()
+}
+
+/// # 求文法G中所有符号的FIRST集
+pub fn get_first_sets(g: &Grammar) -> SymbolSets {
+    first_sets(g, None)
+}
+
+/// # 求文法G中所有符号的FIRST集，并记录求解过程
+///
+/// 迭代过程中每个产生式使FIRST集增加的元素都按顺序记录在 `trace` 中，
+/// 形如 ``pass 1: added `+` to FIRST(E') from production 1 (E' -> + T E')``
+///
+/// 返回的FIRST集中的元素已排序
+pub fn get_first_with_trace(g: &Grammar, trace: &mut Vec<String>) -> HashMap<String, Vec<String>> {
+    sorted_sets(&first_sets(g, Some(trace)))
+}
+
+/// # 求文法G中所有符号的FIRST集，同时返回求解过程
+///
+/// 与 `get_first_with_trace` 相同，求解过程作为返回值的第二个元素
+pub fn get_first_traced(g: &Grammar) -> (HashMap<String, Vec<String>>, Vec<String>) {
+    let mut trace = vec![];
+    let first = get_first_with_trace(g, &mut trace);
+    (first, trace)
+}
+
+/// 求FIRST集，`trace` 不为 `None` 时记录求解过程
+fn first_sets(g: &Grammar, mut trace: Option<&mut Vec<String>>) -> SymbolSets {
     let mut first = HashMap::new();
     // 终结符的 first 集合为自身
     g.t.iter().for_each(|t| {
-        first.insert(t.clone(), vec![t.clone()]);
+        first.insert(t.clone(), HashSet::from([t.clone()]));
     });
 
     // 初始化非终结符的 first 集合为空
     g.v.iter().for_each(|v| {
-        first.insert(v.clone(), vec![]);
-    });
-
-    // 若产生式右部第一个符号为终结符或右部只有有ε，则将其加入该非终结符的 first 集合
-    g.p.iter().for_each(|p| {
-        if g.t.contains(&p.right[0]) {
-            first.get_mut(&p.left).unwrap().push(p.right[0].clone());
-        }
-        if p.right == vec!["ε".to_string()] {
-            first.get_mut(&p.left).unwrap().push("ε".to_string());
-        }
+        first.insert(v.clone(), HashSet::new());
     });
 
     // 对V中所有非终结符 X，检查产生式右部，添加 First(X) 中的终结符
     let mut changed = true;
+    let mut pass = 0;
     while changed {
         changed = false;
-        g.p.iter().for_each(|p| {
-            // 若产生式右部第一个符号为非终结符，即 X->Y...
-            // 则将其加入该非终结符的 first 集合
-            if g.v.contains(&p.right[0]) {
-                changed = union_first(&mut first, &p.left, &p.right[0], true);
+        pass += 1;
+        g.p.iter().enumerate().for_each(|(k, p)| {
+            let before = trace.is_some().then(|| first[&p.left].clone());
+            changed |= first_of_production(&mut first, p);
+            if let (Some(trace), Some(before)) = (trace.as_mut(), before) {
+                record_additions(
+                    trace,
+                    pass,
+                    "FIRST",
+                    &p.left,
+                    &before,
+                    &first[&p.left],
+                    k,
+                    p,
+                );
             }
+        });
+    }
 
-            // 若产生式右部长度为一，即 X->Y，则不需要检查后续符号
-            if p.right.len() == 1 {
-                return;
-            }
+    first
+}
 
-            // 产生式右部从第一个字符开始是连续的符号，即X->Y_1...Y_i...Y_k
-            // 注: 此处Y_i若为终结符，则其 first 集合中不会包含 ε
-            //     所以通过first集判断 Y_i->ε 不需要判断是否为终结符。
+/// # 按照产生式 p 向 FIRST(X) 中添加元素，X 为 p 的左部
+///
+/// @return X 的 first 集是否发生了变化
+fn first_of_production(first: &mut SymbolSets, p: &Product) -> bool {
+    // 产生式右部为空或只有ε，即 X->ε，则将ε加入该非终结符的 first 集合
+    if is_epsilon(&p.right) {
+        return union_first(first, &p.left, EPSILON, false);
+    }
 
-            // 是否从Y_1到Y_k的first集合中均包含ε，即 Y_1...Y_k->ε
-            let mut need_epsilon = true;
+    let mut changed = false;
 
-            // 如果对于任何j，1<=j<=i-1，FIRST(Yj)都含有ε，
-            // 则把FIRST(Yi)中所有非ε元素添加到FIRST(X)中
-            for i in 0..p.right.len() {
-                if first.get(&p.right[i]).unwrap().contains(&"ε".to_string()) {
-                    changed = union_first(&mut first, &p.left, &p.right[i + 1], need_epsilon);
-                } else {
-                    need_epsilon = false;
-                    break;
-                }
-            }
+    // 产生式右部从第一个字符开始是连续的符号，即X->Y_1...Y_i...Y_k
+    // 注: 此处Y_i若为终结符，则其 first 集合中不会包含 ε
+    //     所以通过first集判断 Y_i->ε 不需要判断是否为终结符。
 
-            // 若 Y_1...Y_k->ε，则将ε加入X的first集合
-            if need_epsilon {
-                changed = union_first(&mut first, &p.left, &"ε".to_string(), false);
-            }
-        });
+    // 是否从Y_1到Y_k的first集合中均包含ε，即 Y_1...Y_k->ε
+    let mut need_epsilon = true;
+
+    // 把FIRST(Y_1)中所有非ε元素添加到FIRST(X)中，
+    // 如果对于任何j，1<=j<=i-1，FIRST(Yj)都含有ε，
+    // 则把FIRST(Yi)中所有非ε元素添加到FIRST(X)中
+    for y in p.right.iter() {
+        changed |= union_first(first, &p.left, y, true);
+        if !derives_epsilon(first, y) {
+            need_epsilon = false;
+            break;
+        }
     }
 
-    first
+    // 若 Y_1...Y_k->ε，则将ε加入X的first集合
+    if need_epsilon {
+        changed |= union_first(first, &p.left, EPSILON, false);
+    }
+    changed
+}
+
+/// 记录第k个产生式使 `set_name`(x) 增加的元素，元素按字典序记录
+#[allow(clippy::too_many_arguments)]
+fn record_additions(
+    trace: &mut Vec<String>,
+    pass: usize,
+    set_name: &str,
+    x: &str,
+    before: &HashSet<String>,
+    after: &HashSet<String>,
+    k: usize,
+    p: &Product,
+) {
+    let mut added = after.difference(before).collect::<Vec<_>>();
+    added.sort();
+    let right = match p.right.is_empty() {
+        true => EPSILON.to_string(),
+        false => p.right.join(" "),
+    };
+    for s in added {
+        trace.push(format!(
+            "pass {}: added `{}` to {}({}) from production {} ({} -> {})",
+            pass, s, set_name, x, k, p.left, right
+        ));
+    }
+}
+
+/// 产生式右部是否为ε，右部为空时同样视为ε
+fn is_epsilon(right: &[String]) -> bool {
+    right.is_empty() || right.iter().all(|s| s == EPSILON)
+}
+
+/// 符号 y 是否能推出ε，即 FIRST(y) 中是否含有ε
+fn derives_epsilon(first: &SymbolSets, y: &str) -> bool {
+    y == EPSILON || first.get(y).is_some_and(|f| f.contains(EPSILON))
 }
 
 /// # 将 y first 集合中的终结符添加到 x first 集合中
 ///
 /// - @param discard 是否丢弃 y first 集合中的 ε
 /// - @return 是否发生了变化
-fn union_first(first: &mut HashMap<String, Vec<String>>, x: &str, y: &str, discard: bool) -> bool {
-    let mut x_first = first.get(x).unwrap().clone();
+fn union_first(first: &mut SymbolSets, x: &str, y: &str, discard: bool) -> bool {
+    if y == EPSILON {
+        return !discard && first.get_mut(x).unwrap().insert(EPSILON.to_string());
+    }
+    if x == y {
+        return false;
+    }
+
+    // 暂时取出 x 的集合，避免复制 y 的集合
+    let mut x_first = first.remove(x).unwrap();
     let before = x_first.len();
+    if let Some(y_first) = first.get(y) {
+        union(
+            &mut x_first,
+            y_first.iter().filter(|s| !discard || *s != EPSILON),
+        );
+    }
+    let changed = before < x_first.len();
+    first.insert(x.to_string(), x_first);
 
-    let y_first = match (y, discard) {
-        ("ε", false) => vec!["ε".to_string()],
-        ("ε", true) => vec![],
-        (_, true) => first
-            .get(y)
-            .unwrap()
-            .clone()
-            .into_iter()
-            .filter(|s| s != "ε")
-            .collect(),
-        (_, false) => first.get(y).unwrap().clone(),
-    };
+    changed
+}
 
-    x_first = x_first
-        .into_iter()
-        .chain(y_first.into_iter())
-        .collect::<HashSet<String>>()
-        .into_iter()
-        .collect();
-    let after = x_first.len();
+/// 将 from 中 to 尚未包含的元素加入 to
+fn union<'a>(to: &mut HashSet<String>, from: impl Iterator<Item = &'a String>) {
+    for s in from {
+        if !to.contains(s) {
+            to.insert(s.clone());
+        }
+    }
+}
 
-    first.insert(x.to_string(), x_first);
+/// # 求文法G中所有非终结符的FOLLOW集，集合中的元素已排序
+pub fn get_follow(g: &Grammar) -> HashMap<String, Vec<String>> {
+    sorted_sets(&get_follow_sets(g))
+}
 
-    before < after
+/// # 求文法G中所有非终结符的FOLLOW集，符号与集合中的元素均已排序
+///
+/// 遍历顺序与输出都是确定的，可以直接用于打印或比较
+pub fn get_follow_sorted(g: &Grammar) -> BTreeMap<String, Vec<String>> {
+    get_follow(g).into_iter().collect()
 }
 
-pub fn get_follow(g: &Grammar) -> HashMap<String, Vec<String>> {
-    let mut first = get_first(g);
-    get_follow_with_first(g, &mut first)
+/// # 求文法G中所有非终结符的FOLLOW集
+pub fn get_follow_sets(g: &Grammar) -> SymbolSets {
+    let first = get_first_sets(g);
+    get_follow_with_first(g, &first)
 }
 
-pub fn get_follow_with_first(
-    g: &Grammar,
-    first: &mut HashMap<String, Vec<String>>,
-) -> HashMap<String, Vec<String>> {
+/// # 求文法G中所有非终结符的FOLLOW集，并记录求解过程
+///
+/// 与 `get_first_with_trace` 相同，迭代过程中每个产生式使FOLLOW集增加的元素都按顺序记录在 `trace` 中，
+/// 开始符号的 `#` 最先记录。只记录FOLLOW集的求解过程，不包括其所用的FIRST集
+///
+/// 返回的FOLLOW集中的元素已排序
+pub fn get_follow_with_trace(g: &Grammar, trace: &mut Vec<String>) -> HashMap<String, Vec<String>> {
+    let first = get_first_sets(g);
+    sorted_sets(&follow_sets(g, &first, Some(trace)))
+}
+
+/// # 求文法G中所有非终结符的FOLLOW集，同时返回求解过程
+///
+/// 与 `get_follow_with_trace` 相同，求解过程作为返回值的第二个元素
+pub fn get_follow_traced(g: &Grammar) -> (HashMap<String, Vec<String>>, Vec<String>) {
+    let mut trace = vec![];
+    let follow = get_follow_with_trace(g, &mut trace);
+    (follow, trace)
+}
+
+/// # 使用已求得的FIRST集求FOLLOW集
+///
+/// 求解过程中用到的符号串的FIRST集缓存在 `SequenceFirst` 中，不会加入到 `first` 中
+pub fn get_follow_with_first(g: &Grammar, first: &SymbolSets) -> SymbolSets {
+    follow_sets(g, first, None)
+}
+
+/// 求FOLLOW集，`trace` 不为 `None` 时记录求解过程
+fn follow_sets(g: &Grammar, first: &SymbolSets, mut trace: Option<&mut Vec<String>>) -> SymbolSets {
     let mut follow = HashMap::new();
+    // FIRST集在求FOLLOW集之前已经确定，符号串的FIRST集在各轮迭代之间复用
+    let mut first = SequenceFirst::new(first);
 
     // 初始化 follow 集合
     g.v.iter().for_each(|v| {
-        follow.insert(v.clone(), vec![]);
+        follow.insert(v.clone(), HashSet::new());
     });
 
     // 将 #(句子结束符) 加入 S 的 follow 集合
-    follow.get_mut(&g.s).unwrap().push("#".to_string());
+    follow.get_mut(&g.s).unwrap().insert(END_MARKER.to_string());
+    if let Some(trace) = trace.as_mut() {
+        trace.push(format!("added `#` to FOLLOW({}) as the start symbol", g.s));
+    }
 
     // 对于每个产生式 A->αBβ，将 FIRST(β) 去掉ε后加入 FOLLOW(B)
     let mut changed = true;
+    let mut pass = 0;
     while changed {
         changed = false;
-        g.p.iter().for_each(|p| {
-            for i in 0..p.right.len() {
-                // 若产生式右部第一个符号为非终结符，即 X->Y...
-                // 则继续向后遍历直到找到非终结符
-                if !g.v.contains(&p.right[i]) {
-                    continue;
-                }
-
-                // 找到第一个非终结符B
-                // 若产生式为 A -> αB 型，将FOLLOW(A)加入FOLLOW(B)
-                if i == p.right.len() - 1 {
-                    changed = union_follow(&mut follow, &p.right[i], &p.left);
-                    continue;
-                }
-                // 若产生式为 A -> αBβ 型，进行讨论
-                else {
-                    let beta = &p.right[i + 1..].to_vec();
-                    let beta_first = get_first_all(first, beta);
-
-                    // 若β的first集合中含有ε，则同 A -> αB 型，将FOLLOW(A)加入FOLLOW(B)
-                    if beta_first.contains(&"ε".to_string()) {
-                        changed = union_follow(&mut follow, &p.right[i], &p.left);
-                    }
-
-                    // 否则将FITST(β)去除ε加入FOLLOW(B)
-                    let mut b_follow = follow.get(&p.right[i]).unwrap().clone();
-                    let before = b_follow.len();
-
-                    b_follow = b_follow
-                        .into_iter()
-                        .chain(
-                            beta_first
-                                .into_iter()
-                                .filter(|s| s != &&"ε".to_string())
-                                .cloned(),
-                        )
-                        .collect::<HashSet<String>>()
-                        .into_iter()
-                        .collect();
-                    let after = b_follow.len();
-
-                    follow.insert(p.right[i].clone(), b_follow);
-
-                    changed = changed || before < after;
+        pass += 1;
+        g.p.iter().enumerate().for_each(|(k, p)| {
+            let before = trace.is_some().then(|| follow.clone());
+            changed |= follow_of_production(g, &mut first, &mut follow, p);
+            if let (Some(trace), Some(before)) = (trace.as_mut(), before) {
+                for v in g.v.iter() {
+                    record_additions(trace, pass, "FOLLOW", v, &before[v], &follow[v], k, p);
                 }
             }
         });
@@ -606,91 +2617,158 @@ pub fn get_follow_with_first(
     follow
 }
 
-/// 将 y follow 集合中的终结符添加到 x follow 集合中
+/// # 按照产生式 p 向其右部中非终结符的FOLLOW集中添加元素
 ///
-/// @return x 的 follow 集是否发生了变化
-fn union_follow(follow: &mut HashMap<String, Vec<String>>, x: &str, y: &str) -> bool {
-    let mut x_first = follow.get(x).unwrap().clone();
-    let before = x_first.len();
+/// @return 是否有 follow 集发生了变化
+fn follow_of_production(
+    g: &Grammar,
+    first: &mut SequenceFirst,
+    follow: &mut SymbolSets,
+    p: &Product,
+) -> bool {
+    let mut changed = false;
+    for i in 0..p.right.len() {
+        // 若产生式右部第一个符号为非终结符，即 X->Y...
+        // 则继续向后遍历直到找到非终结符
+        if !g.v.contains(&p.right[i]) {
+            continue;
+        }
 
-    let y_first = match y {
-        "ε" => vec!["ε".to_string()],
-        _ => follow.get(y).unwrap().clone(),
-    };
+        // 找到第一个非终结符B
+        // 若产生式为 A -> αB 型，将FOLLOW(A)加入FOLLOW(B)
+        if i == p.right.len() - 1 {
+            changed |= union_follow(follow, &p.right[i], &p.left);
+            continue;
+        }
+        // 若产生式为 A -> αBβ 型，进行讨论
+        else {
+            let beta_first = first.get(&p.right[i + 1..]);
 
-    x_first = x_first
-        .into_iter()
-        .chain(y_first.into_iter())
-        .collect::<HashSet<String>>()
-        .into_iter()
-        .collect();
-    let after = x_first.len();
+            // 若β的first集合中含有ε，则同 A -> αB 型，将FOLLOW(A)加入FOLLOW(B)
+            if beta_first.contains(EPSILON) {
+                changed |= union_follow(follow, &p.right[i], &p.left);
+            }
 
-    follow.insert(x.to_string(), x_first);
+            // 否则将FITST(β)去除ε加入FOLLOW(B)
+            let b_follow = follow.get_mut(&p.right[i]).unwrap();
+            let before = b_follow.len();
+            union(b_follow, beta_first.iter().filter(|s| *s != EPSILON));
 
-    before < after
+            changed |= before < b_follow.len();
+        }
+    }
+    changed
 }
 
-/// 求a的所有first集，a = Y_1...Y_n
-/// 并将其加入到first集中
-fn get_first_all<'a>(
-    first: &'a mut HashMap<String, Vec<String>>,
-    a: &Vec<String>,
-) -> &'a Vec<String> {
-    // 如果a是单个非终结符或者终结符，则FIRST(a)之前已经求过，直接返回即可
-    if a.len() == 1 {
-        // a = ε 特殊情况
-        // if a.contains(&"ε".to_string()) {
-        //     first.insert("ε".to_string(), vec!["ε".to_string()]);
-        // }
-        return first.get(&a[0]).unwrap();
+/// 将 y follow 集合中的终结符添加到 x follow 集合中
+///
+/// @return x 的 follow 集是否发生了变化
+fn union_follow(follow: &mut SymbolSets, x: &str, y: &str) -> bool {
+    if x == y {
+        return false;
     }
+    // 暂时取出 x 的集合，避免复制 y 的集合
+    let mut x_follow = follow.remove(x).unwrap();
+    let before = x_follow.len();
+    union(&mut x_follow, follow[y].iter());
+    let changed = before < x_follow.len();
+    follow.insert(x.to_string(), x_follow);
 
-    let a_key = a.join(" ");
-    if first.contains_key(&a_key) {
-        return first.get(&a_key).unwrap();
-    }
+    changed
+}
 
-    first.insert(a_key.clone(), vec![]);
+/// # 符号串的FIRST集
+///
+/// 由已求得的各符号的FIRST集求符号串 Y_1...Y_n 的FIRST集，结果缓存在独立的表中，
+/// 不会加入到符号的FIRST集中
+pub(crate) struct SequenceFirst<'a> {
+    /// 各符号的FIRST集
+    first: &'a SymbolSets,
+    /// 已求过的符号串的FIRST集，键为符号串本身而不是拼接得到的字符串，因此符号中可以含有空格
+    cache: HashMap<Vec<String>, HashSet<String>>,
+}
 
-    let mut need_epsilon = true;
-    for i in 0..a.len() {
-        if first.get(&a[i]).unwrap().contains(&"ε".to_string()) {
-            union_first(first, &a_key, &a[i], true);
-        } else {
-            need_epsilon = false;
-            union_first(first, &a_key, &a[i], true);
-            break;
+impl<'a> SequenceFirst<'a> {
+    pub(crate) fn new(first: &'a SymbolSets) -> Self {
+        Self {
+            first,
+            cache: HashMap::new(),
         }
     }
 
-    if need_epsilon {
-        union_first(first, &a_key, "ε", false);
-    }
+    /// 求a的FIRST集，a = Y_1...Y_n
+    pub(crate) fn get(&mut self, a: &[String]) -> &HashSet<String> {
+        let first = self.first;
+        // 如果a是单个非终结符或者终结符，则FIRST(a)之前已经求过，直接返回即可
+        if let [y] = a {
+            if let Some(y_first) = first.get(y) {
+                return y_first;
+            }
+        }
 
-    first.get(&a_key).unwrap()
+        self.cache.entry(a.to_vec()).or_insert_with(|| {
+            let mut a_first = HashSet::new();
+            let mut need_epsilon = true;
+            for y in a {
+                if let Some(y_first) = first.get(y) {
+                    union(&mut a_first, y_first.iter().filter(|s| *s != EPSILON));
+                }
+                if !derives_epsilon(first, y) {
+                    need_epsilon = false;
+                    break;
+                }
+            }
+            if need_epsilon {
+                a_first.insert(EPSILON.to_string());
+            }
+            a_first
+        })
+    }
 }
 
 /// LR(0)项目
 ///
 /// 一个LR(0)项目是带圆点的产生式
 /// 项目的形式为 A -> α·Bβ
-#[derive(Debug, Clone, PartialEq)]
-struct Item {
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Item {
     /// 产生式左部
-    left: String,
+    pub left: String,
     /// 产生式右部
-    right: Vec<String>,
+    pub right: Vec<String>,
     /// ·的位置，在对应坐标字符的左边
-    dot: usize,
+    pub dot: usize,
 }
 
-/// # 求LR(0)项目集族
-///
+impl Display for Item {
+    /// 形如 `A -> α ·β`，圆点紧贴在其右边的符号之前，圆点在最右端时为 `A -> α ·`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ->", self.left)?;
+        for (i, symbol) in self.right.iter().enumerate() {
+            if i == self.dot {
+                write!(f, " ·{}", symbol)?;
+            } else {
+                write!(f, " {}", symbol)?;
+            }
+        }
+        if self.dot >= self.right.len() {
+            write!(f, " ·")?;
+        }
+        Ok(())
+    }
+}
+
+/// # 求LR(0)项目集族
+///
 /// 每个项目集都是一个状态，项目集族就是所有状态的集合
 ///
 /// 即求出识别过程中的所有状态
-fn get_lr0_collection(g: &Grammar) -> Vec<Vec<Item>> {
+///
+/// `g` 应为拓广文法(见 `augment`)，项目集族的编号与 `get_slr1_table` 构造的分析表的状态编号一致
+///
+/// 状态按照开始状态、再按非终结符集与终结符集的声明顺序转移得到的顺序编号，
+/// 每个项目集中的项目按 `sort_items` 的规范顺序排列，因此相同的文法总是得到相同的项目集族
+pub fn get_lr0_collection(g: &Grammar) -> Vec<Vec<Item>> {
     // 项目集规范族，所有状态的集合
     let mut c = vec![];
 
@@ -704,7 +2782,9 @@ fn get_lr0_collection(g: &Grammar) -> Vec<Vec<Item>> {
     });
 
     // 将开始项目集的完整表达加入到项目集规范族中
-    c.push(closure(&i, g));
+    let mut i = closure(&i, g);
+    sort_items(&mut i, g);
+    c.push(i);
 
     // 终结符集和非终结符集
     let v_t =
@@ -717,14 +2797,15 @@ fn get_lr0_collection(g: &Grammar) -> Vec<Vec<Item>> {
     // 这里的处理是指求项目集(状态)接受任意终结符或非终结符能转移到的其他项目集(状态)
     let mut e = c.clone().into_iter().collect::<VecDeque<Vec<Item>>>();
 
-    while e.len() > 0 {
+    while !e.is_empty() {
         // 取出一个项目集
         let items = e.pop_front().unwrap();
         // 对于每个终结符或非终结符 x
         v_t.iter().for_each(|x| {
             // 求项目集 IT 在接受符号 x 时转移到的项目集
-            let to_items = goto(&items, x, g);
-            if to_items.len() > 0 {
+            let mut to_items = goto(&items, x, g);
+            sort_items(&mut to_items, g);
+            if !to_items.is_empty() {
                 // 如果项目集 to_items 不在 C 中
                 if !c.contains(&to_items) {
                     // 将 to_items 加入到 C 中
@@ -739,6 +2820,22 @@ fn get_lr0_collection(g: &Grammar) -> Vec<Vec<Item>> {
     c
 }
 
+/// # 将项目集中的项目按规范顺序排列
+///
+/// 核心项目(圆点不在最左端的项目与 S' -> ·S)在前，闭包加入的项目在后，
+/// 各部分中再依次按产生式的序号与圆点的位置排列
+fn sort_items(items: &mut [Item], g: &Grammar) {
+    let production = |item: &Item| {
+        g.p.iter()
+            .position(|p| p.left == item.left && p.right == item.right)
+            .unwrap_or(usize::MAX)
+    };
+    items.sort_by_cached_key(|item| {
+        let kernel = item.dot > 0 || item.left == g.s;
+        (!kernel, production(item), item.dot)
+    });
+}
+
 /// # 项目集的状态转移函数
 ///
 /// 求解项目集 I 接受 x 后转移到的项目集 J
@@ -746,7 +2843,10 @@ fn get_lr0_collection(g: &Grammar) -> Vec<Vec<Item>> {
 /// 找到项目集中形如 A -> α·xβ 的项目，将 A -> αx·β 加入到 J 中
 ///
 /// 然后求J的完整表示，即求闭包
-fn goto(items: &Vec<Item>, x: &str, g: &Grammar) -> Vec<Item> {
+///
+/// `g` 应为拓广文法，`x` 不在文法的符号集中或没有项目能接受 `x` 时返回空的项目集；
+/// 结果中项目的顺序由 `items` 的顺序决定，比较项目集时应使用 `items_eq`
+pub fn goto(items: &[Item], x: &str, g: &Grammar) -> Vec<Item> {
     let mut j = vec![];
 
     items.iter().for_each(|item| {
@@ -774,13 +2874,16 @@ fn goto(items: &Vec<Item>, x: &str, g: &Grammar) -> Vec<Item> {
 /// 即完善项目集I中的状态，将非终结符展开，找出下一步能接受的终结符
 ///
 /// 可以理解为求出项目集I的完整表达，便于求出下一步能接受的终结符
-fn closure(i: &[Item], g: &Grammar) -> Vec<Item> {
+///
+/// `g` 应为拓广文法，A -> ε 以空的右部表示(见 `augment`)；
+/// 结果以 `i` 中的项目开头，展开得到的项目按加入的顺序排在其后，不含重复的项目
+pub fn closure(i: &[Item], g: &Grammar) -> Vec<Item> {
     // 用于存储闭包
     let mut j = i.to_vec();
     // 模拟队列，用于存储还未处理的项目
-    let mut e = i.to_vec().into_iter().collect::<VecDeque<Item>>();
+    let mut e = i.iter().cloned().collect::<VecDeque<Item>>();
 
-    while e.len() > 0 {
+    while !e.is_empty() {
         // 取出队列中的第一个项目
         let item = e.pop_front().unwrap();
         if item.dot < item.right.len() {
@@ -814,7 +2917,9 @@ fn closure(i: &[Item], g: &Grammar) -> Vec<Item> {
 /// # 对比两个项目集是否相同
 ///
 /// 当两个项目集长度相同且对一个项目集中的每个项目都能在另一个项目集中找到对应的项目时，两个项目集相同
-fn items_eq(items1: &Vec<Item>, items2: &Vec<Item>) -> bool {
+///
+/// 即不考虑项目的顺序，要求项目集中没有重复的项目，`goto` 与 `closure` 的结果满足这一点
+pub fn items_eq(items1: &[Item], items2: &[Item]) -> bool {
     if items1.len() != items2.len() {
         return false;
     }
@@ -828,17 +2933,72 @@ fn items_eq(items1: &Vec<Item>, items2: &Vec<Item>) -> bool {
     true
 }
 
+/// 测试用的表达式文法 `E -> E + T | T, T -> T * F | F, F -> ( E ) | id`
+#[cfg(test)]
+pub(crate) const EXPR_GRAMMAR_YML: &str = r#"
+    s: E
+    v:
+      - E
+      - T
+      - F
+    t:
+      - +
+      - "*"
+      - (
+      - )
+      - id
+    p:
+      - left: E
+        right:
+          - E
+          - +
+          - T
+      - left: E
+        right:
+          - T
+      - left: T
+        right:
+          - T
+          - "*"
+          - F
+      - left: T
+        right:
+          - F
+      - left: F
+        right:
+          - (
+          - E
+          - )
+      - left: F
+        right:
+          - id
+"#;
+
 #[cfg(test)]
 mod tests {
-    use std::fs::File;
+    use std::{
+        collections::{HashMap, HashSet},
+        fs::File,
+    };
 
     use log::info;
     use simplelog::*;
 
-    use super::Grammar;
+    use super::{Grammar, GrammarBuilder, Product, EXPR_GRAMMAR_YML};
     use crate::parser::{
-        lexical_analysis::lexical_analysis,
-        syntax_analysis::{get_first, get_follow, get_slr1_table, slr1_analysis},
+        lexical_analysis::{lexical_analysis, Token, TokenType},
+        lr1_analysis::get_lalr1_table,
+        syntax_analysis::{
+            augment, closure, combined_goto_table, completion_context, derivation_steps, get_first,
+            get_first_sets, get_first_sorted, get_first_traced, get_first_with_trace, get_follow,
+            get_follow_sets, get_follow_sorted, get_follow_traced, get_follow_with_first,
+            get_follow_with_trace, get_lr0_collection, get_slr1_table, get_slr1_table_augmented,
+            get_slr1_table_strict, goto, items_eq, merge_compatible_states, parse_longest_prefix,
+            parse_verbose, slr1_analysis, slr1_analysis_events, slr1_analysis_with_options,
+            slr1_parse_with_actions, slr1_rightmost_derivation, stack_evolution, table_metrics,
+            unshifted_tokens, verify_termination, Action, AnalysisOptions, Item, ParseEvent,
+            Parser, SemanticAction, SequenceFirst, StepAction, Suggestion, TerminalMatcher,
+        },
     };
 
     #[test]
@@ -912,6 +3072,39 @@ mod tests {
           - id
     "#;
 
+    #[test]
+    fn test_from_bnf() {
+        let bnf = "
+            E -> T E'
+            E' -> + T E' |
+            T -> F T'
+            T' -> * F T' | ε
+            F -> ( E )
+               | id
+        ";
+        let g = Grammar::from_bnf(bnf).unwrap();
+        g.validate().unwrap();
+        assert_eq!(g.s, "E");
+        assert_eq!(g.v, vec![s!("E"), s!("E'"), s!("T"), s!("T'"), s!("F")]);
+        assert_eq!(g.t, vec![s!("+"), s!("*"), s!("("), s!(")"), s!("id")]);
+        assert_eq!(g.p.len(), 8);
+        assert!(g.p[2].right.is_empty());
+
+        // 与等价的yml文法的非终结符FIRST集相同
+        let yml = Grammar::from_yml(GRAMMAR_YML).unwrap();
+        let (first, yml_first) = (get_first(&g), get_first(&yml));
+        for v in g.v.iter() {
+            assert_eq!(first[v], yml_first[v], "FIRST({})", v);
+        }
+
+        assert_eq!(
+            Grammar::from_bnf("E -> T\nT id").unwrap_err().to_string(),
+            "第2行: 缺少 `->`"
+        );
+        assert!(Grammar::from_bnf("| a").is_err());
+        assert!(Grammar::from_bnf("").is_err());
+    }
+
     #[test]
     fn test_first() {
         let g = Grammar::from_yml(GRAMMAR_YML).unwrap();
@@ -940,6 +3133,264 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_follow_keeps_first_clean() {
+        let g = Grammar::from_yml(GRAMMAR_YML).unwrap();
+        let first = get_first_sets(&g);
+        let before = first.clone();
+        let follow = get_follow_with_first(&g, &first);
+        assert_eq!(first, before);
+        assert_eq!(follow, get_follow_sets(&g));
+
+        // FIRST集中只有单个符号
+        let first = get_first(&g);
+        assert!(first.keys().all(|k| !k.contains(' ')));
+        let mut symbols = g.v.iter().chain(g.t.iter()).collect::<Vec<_>>();
+        symbols.sort();
+        let mut keys = first.keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, symbols);
+
+        // 符号串的FIRST集
+        let seq = |s: &str| s.split(' ').map(|s| s.to_string()).collect::<Vec<_>>();
+        let first = get_first_sets(&g);
+        let mut sequence_first = SequenceFirst::new(&first);
+        let mut t_e = sequence_first
+            .get(&seq("E' T'"))
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        t_e.sort();
+        assert_eq!(t_e, vec![s!("*"), s!("+"), s!("ε")]);
+        assert_eq!(sequence_first.get(&seq("T E'")), &first["T"]);
+    }
+
+    #[test]
+    fn test_from_json() {
+        let json = r#"{
+            "s": "E",
+            "v": ["E", "T", "F"],
+            "t": ["+", "*", "(", ")", "id"],
+            "p": [
+                { "left": "E", "right": ["E", "+", "T"] },
+                { "left": "E", "right": ["T"] },
+                { "left": "T", "right": ["T", "*", "F"] },
+                { "left": "T", "right": ["F"] },
+                { "left": "F", "right": ["(", "E", ")"] },
+                { "left": "F", "right": ["id"] }
+            ]
+        }"#;
+        let g = Grammar::from_json(json).unwrap();
+        g.validate().unwrap();
+        assert_eq!(g, Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap());
+
+        assert!(Grammar::from_json(r#"{ "s": "E" }"#).is_err());
+    }
+
+    #[test]
+    fn test_from_yml_with_includes() {
+        let dir = std::env::temp_dir().join(format!("slr1_grammar_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(
+            dir.join("expr.yml"),
+            r#"
+            include: [sub/term.yml]
+            s: E
+            v: [E]
+            t: [+]
+            p:
+              - left: E
+                right: [E, +, T]
+              - left: E
+                right: [T]
+            "#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("sub/term.yml"),
+            r#"
+            v: [T, F]
+            t: ["*", (, ), id]
+            p:
+              - left: T
+                right: [T, "*", F]
+              - left: T
+                right: [F]
+              - left: F
+                right: [(, E, )]
+              - left: F
+                right: [id]
+            "#,
+        )
+        .unwrap();
+        // 开始符号冲突
+        std::fs::write(dir.join("conflict.yml"), "include: [expr.yml]\ns: S\n").unwrap();
+        // 循环包含，路径不同但是同一个文件
+        std::fs::write(dir.join("loop.yml"), "include: [sub/../loop.yml]\ns: S\n").unwrap();
+        // 菱形包含，公共文件只合并一次
+        std::fs::write(
+            dir.join("diamond.yml"),
+            "include: [a.yml, b.yml]\ns: S\nv: [S]\np:\n  - left: S\n    right: [A, B]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("a.yml"),
+            "include: [common.yml]\nv: [A]\nt: [a]\np:\n  - left: A\n    right: [a, C]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("b.yml"),
+            "include: [sub/../common.yml]\nv: [B]\nt: [b]\np:\n  - left: B\n    right: [b, C]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("common.yml"),
+            "v: [C]\nt: [c]\np:\n  - left: C\n    right: [c]\n",
+        )
+        .unwrap();
+
+        let g = Grammar::from_yml_with_includes(&dir.join("expr.yml"));
+        let conflict = Grammar::from_yml_with_includes(&dir.join("conflict.yml"));
+        let looped = Grammar::from_yml_with_includes(&dir.join("loop.yml"));
+        let diamond = Grammar::from_yml_with_includes(&dir.join("diamond.yml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let g = g.unwrap();
+        let expected = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        assert_eq!(g.v, expected.v);
+        assert_eq!(g.p, expected.p);
+        g.validate().unwrap();
+
+        let (tokens, _) = lexical_analysis("a + b * ( c + d )".to_string()).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        assert!(slr1_analysis(&g, &action, &goto, tokens.clone()));
+        let (action, goto) = get_slr1_table(&expected).unwrap();
+        assert!(slr1_analysis(&expected, &action, &goto, tokens));
+
+        assert!(conflict.unwrap_err().message.contains("冲突"));
+        assert!(looped.unwrap_err().message.starts_with("循环包含文件"));
+
+        let diamond = diamond.unwrap();
+        diamond.validate().unwrap();
+        assert_eq!(diamond.v, vec!["S", "A", "C", "B"]);
+        assert_eq!(diamond.p.len(), 4);
+    }
+
+    #[test]
+    fn test_grammar_builder() {
+        let g = GrammarBuilder::new()
+            .start("E")
+            .terminal("+")
+            .terminal("*")
+            .rule("E", &["E", "+", "T"])
+            .rule("E", &["T"])
+            .rule("T", &["T", "*", "F"])
+            .rule("T", &["F"])
+            .rule("F", &["(", "E", ")"])
+            .rule("F", &["id"])
+            .build()
+            .unwrap();
+        assert_eq!(g, Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap());
+
+        // 空产生式以空的右部表示，未声明开始符号时为第一条规则的左部
+        let g = GrammarBuilder::new()
+            .rule("S", &["a", "S"])
+            .rule("S", &["ε"])
+            .build()
+            .unwrap();
+        assert_eq!(g.s, "S");
+        assert_eq!(g.t, vec!["a"]);
+        assert!(g.p[1].right.is_empty());
+
+        // 开始符号不是任何规则的左部
+        let e = GrammarBuilder::new()
+            .start("X")
+            .rule("S", &["a"])
+            .build()
+            .unwrap_err();
+        assert_eq!(e.message, "开始符号不在非终结符集中");
+        // 声明为终结符的符号又作为规则左部
+        assert!(GrammarBuilder::new()
+            .terminal("S")
+            .rule("S", &["a"])
+            .build()
+            .is_err());
+        assert!(GrammarBuilder::new().build().is_err());
+    }
+
+    #[test]
+    fn test_terminal_with_space() {
+        let yml = r#"
+        s: S
+        v: [S, B]
+        t: ["a b", a, b]
+        p:
+          - left: S
+            right: [B, a, b]
+          - left: S
+            right: [B, "a b"]
+          - left: B
+            right: []
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        g.validate().unwrap();
+
+        // 符号串 ["a b"] 与 ["a", "b"] 拼接后相同，但FIRST集不同
+        let first = get_first_sets(&g);
+        let mut sequence_first = SequenceFirst::new(&first);
+        assert_eq!(
+            sequence_first.get(&[s!("a b")]),
+            &HashSet::from([s!("a b")])
+        );
+        assert_eq!(
+            sequence_first.get(&[s!("a"), s!("b")]),
+            &HashSet::from([s!("a")])
+        );
+        assert_eq!(get_first(&g)["S"], vec!["a", "a b"]);
+        assert_eq!(get_follow(&g)["B"], vec!["a", "a b"]);
+    }
+
+    #[test]
+    fn test_first_follow_trace() {
+        let g = Grammar::from_yml(GRAMMAR_YML).unwrap();
+
+        let mut trace = vec![];
+        let first = get_first_with_trace(&g, &mut trace);
+        assert_eq!(first, get_first(&g));
+        assert_eq!(
+            trace[0],
+            "pass 1: added `+` to FIRST(E') from production 1 (E' -> + T E')"
+        );
+        assert!(trace.contains(&s!(
+            "pass 1: added `ε` to FIRST(E') from production 2 (E' -> ε)"
+        )));
+        // FIRST(E) 依赖 FIRST(T)，在之后的轮次中才被加入
+        assert!(trace.contains(&s!(
+            "pass 3: added `id` to FIRST(E) from production 0 (E -> T E')"
+        )));
+
+        let mut trace = vec![];
+        let follow = get_follow_with_trace(&g, &mut trace);
+        assert_eq!(follow, get_follow(&g));
+        assert_eq!(trace[0], "added `#` to FOLLOW(E) as the start symbol");
+        assert!(trace.contains(&s!(
+            "pass 1: added `+` to FOLLOW(T) from production 0 (E -> T E')"
+        )));
+
+        let (first, trace) = get_first_traced(&g);
+        assert_eq!(first, get_first(&g));
+        // 可以推导出ε的非终结符T'的FIRST集中加入ε的过程
+        assert!(trace.contains(&s!(
+            "pass 1: added `ε` to FIRST(T') from production 5 (T' -> ε)"
+        )));
+        let (follow, follow_trace) = get_follow_traced(&g);
+        assert_eq!(follow, get_follow(&g));
+        assert_eq!(
+            follow_trace[0],
+            "added `#` to FOLLOW(E) as the start symbol"
+        );
+    }
+
     #[test]
     fn test_follow() {
         let g = Grammar::from_yml(GRAMMAR_YML).unwrap();
@@ -962,6 +3413,1312 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_desugar_ebnf() {
+        let ebnf = r#"
+        s: P
+        v:
+          - P
+          - S
+        t:
+          - id
+          - ;
+          - else
+        p:
+          - left: P
+            right:
+              - S*
+          - left: S
+            right:
+              - id
+              - else?
+              - ;+
+        "#;
+        let manual = r#"
+        s: P
+        v:
+          - P
+          - P_rep
+          - S
+          - S_opt
+          - S_plus
+        t:
+          - id
+          - ;
+          - else
+          - ε
+        p:
+          - left: P
+            right:
+              - P_rep
+          - left: S
+            right:
+              - id
+              - S_opt
+              - S_plus
+          - left: P_rep
+            right:
+              - S
+              - P_rep
+          - left: P_rep
+            right:
+              - ε
+          - left: S_opt
+            right:
+              - else
+          - left: S_opt
+            right:
+              - ε
+          - left: S_plus
+            right:
+              - ;
+              - S_plus
+          - left: S_plus
+            right:
+              - ;
+        "#;
+        let g = Grammar::from_yml(ebnf).unwrap();
+        let expected = Grammar::from_yml(manual).unwrap();
+        assert_eq!(g.v, expected.v);
+        assert_eq!(g.p.len(), expected.p.len());
+        assert!(g.validate().is_ok());
+        // 非终结符的 FIRST 集与 FOLLOW 集和手工展开的文法相同
+        let sets = |g: &Grammar| {
+            let (first, follow) = (get_first(g), get_follow(g));
+            g.v.iter()
+                .map(|v| {
+                    let (mut f, mut w) = (first[v].clone(), follow[v].clone());
+                    f.sort();
+                    w.sort();
+                    (v.clone(), f, w)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sets(&g), sets(&expected));
+        let mut first_p = get_first(&g)["P"].clone();
+        first_p.sort();
+        assert_eq!(first_p, vec![s!("id"), s!("ε")]);
+        assert_eq!(Grammar::from_yml(&g.to_yml().unwrap()).unwrap(), g);
+
+        // 已声明的符号不被展开，如作为终结符的 + 与 *
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        assert_eq!(g.v, vec![s!("E"), s!("T"), s!("F")]);
+    }
+
+    #[test]
+    fn test_to_yml() {
+        let g = Grammar::from_yml(GRAMMAR_YML).unwrap();
+        let yml = g.to_yml().unwrap();
+        assert!(yml.starts_with("s: E\n"));
+        assert_eq!(Grammar::from_yml(&yml).unwrap(), g);
+    }
+
+    #[test]
+    fn test_validate_useless_nonterminals() {
+        let yml = r#"
+        s: S
+        v:
+          - S
+          - X
+        t:
+          - a
+        p:
+          - left: S
+            right:
+              - a
+          - left: S
+            right:
+              - X
+          - left: X
+            right:
+              - X
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        assert_eq!(
+            g.validate().unwrap_err().to_string(),
+            "非终结符 \"X\" 无法推导出终结符串"
+        );
+
+        let yml = r#"
+        s: S
+        v:
+          - S
+          - Y
+        t:
+          - a
+        p:
+          - left: S
+            right:
+              - a
+          - left: Y
+            right:
+              - a
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        assert_eq!(
+            g.validate().unwrap_err().to_string(),
+            "非终结符 \"Y\" 无法从开始符号到达"
+        );
+    }
+
+    #[test]
+    fn test_validate_undeclared_symbol() {
+        // 第3个产生式中的 `F` 误写为 `f`
+        let yml = EXPR_GRAMMAR_YML.replacen(
+            "          - F\n      - left: F",
+            "          - f\n      - left: F",
+            1,
+        );
+        let g = Grammar::from_yml(&yml).unwrap();
+        assert_eq!(
+            g.validate().unwrap_err().to_string(),
+            "第3个产生式 `T -> f` 的右部符号 \"f\" 不在非终结符集和终结符集中"
+        );
+
+        let mut g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        g.p[4].left = s!("G");
+        assert_eq!(
+            g.validate().unwrap_err().to_string(),
+            "第4个产生式 `G -> ( E )` 的左部 \"G\" 不在非终结符集中"
+        );
+    }
+
+    #[test]
+    fn test_augment_fresh_start() {
+        // 文法中已有 E'，拓广文法的开始符号不能与之重复
+        let g = Grammar::from_yml(GRAMMAR_YML).unwrap();
+        assert!(g.v.contains(&s!("E'")));
+        let outreach_g = augment(&g);
+        assert_eq!(outreach_g.s, "E''");
+        assert_eq!(outreach_g.p.last().unwrap().right, vec![s!("E")]);
+        assert_eq!(
+            outreach_g.p.iter().filter(|p| p.left == "E'").count(),
+            g.p.iter().filter(|p| p.left == "E'").count()
+        );
+
+        let parser = Parser::new(g).unwrap();
+        let (tokens, _) = lexical_analysis("a + b * ( c + d )".to_string()).unwrap();
+        assert!(parser.parse(tokens));
+    }
+
+    #[test]
+    fn test_duplicate_production() {
+        let yml = EXPR_GRAMMAR_YML.trim_end().to_string()
+            + "\n      - left: T\n        right:\n          - F\n";
+        let mut g = Grammar::from_yml(&yml).unwrap();
+        assert_eq!(
+            g.validate().unwrap_err().to_string(),
+            "第6个产生式 `T -> F` 与第3个产生式重复"
+        );
+        assert_eq!(g.dedup_productions(), vec![6]);
+        assert_eq!(g, Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap());
+        g.validate().unwrap();
+
+        // 右部为空与只有ε的产生式相同
+        let mut g = Grammar::from_bnf("S -> a S | ε | b\nS -> ε\n").unwrap();
+        g.p[1].right = vec![s!("ε")];
+        assert!(g.validate().is_err());
+        assert_eq!(g.dedup_productions(), vec![3]);
+        assert_eq!(g.p.len(), 3);
+    }
+
+    #[test]
+    fn test_nullable() {
+        let g = Grammar::from_yml(GRAMMAR_YML).unwrap();
+        let nullable = g.nullable();
+        assert_eq!(nullable, HashSet::from([s!("E'"), s!("T'")]));
+        assert!(!nullable.contains("E"));
+
+        // 右部全为可空非终结符时同样可空
+        let g = Grammar::from_bnf("S -> A B\nA -> a | ε\nB -> A A\n").unwrap();
+        assert_eq!(g.nullable().len(), 3);
+    }
+
+    #[test]
+    fn test_valid_start_tokens() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        assert_eq!(g.valid_start_tokens(), vec!["(", "id"]);
+
+        // 开始符号可空时不含ε
+        let g = Grammar::from_bnf("S -> a S | ε\n").unwrap();
+        assert_eq!(g.valid_start_tokens(), vec!["a"]);
+    }
+
+    #[test]
+    fn test_structurally_eq() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let mut reordered = g.clone();
+        reordered.v.reverse();
+        reordered.t.reverse();
+        reordered.p.reverse();
+        assert_ne!(g, reordered);
+        assert!(g.structurally_eq(&reordered));
+        assert!(reordered.structurally_eq(&g));
+
+        // 与BNF写法不同顺序的同一文法，BNF以第一个产生式的左部为开始符号
+        let mut bnf =
+            Grammar::from_bnf("F -> id | ( E )\nT -> F | T * F\nE -> T | E + T\n").unwrap();
+        bnf.s = s!("E");
+        assert!(g.structurally_eq(&bnf));
+
+        let mut missing = g.clone();
+        missing.p.pop();
+        assert_eq!(
+            g.structural_diff(&missing).unwrap(),
+            "产生式 `F -> id` 在两个文法中分别出现1次与0次"
+        );
+        let mut duplicated = g.clone();
+        duplicated.p.push(g.p[0].clone());
+        assert!(!g.structurally_eq(&duplicated));
+        let mut extra = g.clone();
+        extra.t.push(s!("-"));
+        assert_eq!(
+            g.structural_diff(&extra).unwrap(),
+            "终结符 `-` 只出现在第二个文法中"
+        );
+    }
+
+    #[test]
+    fn test_validate_end_marker() {
+        let g = Grammar::from_bnf("S -> a # | b\n").unwrap();
+        assert_eq!(
+            g.validate().unwrap_err().to_string(),
+            "\"#\" 是输入结束符，不能作为终结符或非终结符"
+        );
+
+        // 作为非终结符同样不合法
+        let g = Grammar::from_bnf("S -> # a\n# -> b\n").unwrap();
+        assert!(g.validate().is_err());
+    }
+
+    #[test]
+    fn test_empty_production() {
+        let yml = r#"
+        s: E
+        v:
+          - E
+          - E'
+        t:
+          - +
+          - id
+        p:
+          - left: E
+            right:
+              - id
+              - E'
+          - left: E'
+            right:
+              - +
+              - id
+              - E'
+          - left: E'
+            right: []
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        assert!(g.validate().is_ok());
+
+        let mut first = get_first(&g);
+        first.iter_mut().for_each(|(_k, v)| {
+            v.sort();
+        });
+        assert_eq!(first["E"], vec![s!("id")]);
+        assert_eq!(first["E'"], vec![s!("+"), s!("ε")]);
+
+        let mut follow = get_follow(&g);
+        follow.iter_mut().for_each(|(_k, v)| {
+            v.sort();
+        });
+        assert_eq!(follow["E'"], vec![s!("#")]);
+    }
+
+    #[test]
+    fn test_sorted_sets_deterministic() {
+        let g = Grammar::from_yml(GRAMMAR_YML).unwrap();
+        let first = format!("{:?}", get_first_sorted(&g));
+        assert_eq!(first, format!("{:?}", get_first_sorted(&g)));
+        let follow = format!("{:?}", get_follow_sorted(&g));
+        assert_eq!(follow, format!("{:?}", get_follow_sorted(&g)));
+
+        let keys = get_follow_sorted(&g).into_keys().collect::<Vec<_>>();
+        assert_eq!(keys, vec!["E", "E'", "F", "T", "T'"]);
+        assert_eq!(get_follow_sorted(&g)["E"], vec!["#", ")"]);
+    }
+
+    #[test]
+    fn test_grammar_display() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let text = g.to_string();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "s: E");
+        assert_eq!(lines[1], "v: E T F");
+        assert_eq!(lines[2], "t: + * ( ) id");
+        assert_eq!(lines[3], "p:");
+        for (i, p) in g.p.iter().enumerate() {
+            let line = format!("  {}: {} -> {}", i, p.left, p.right.join(" "));
+            assert_eq!(lines[4 + i], line);
+        }
+        assert_eq!(lines.len(), 4 + g.p.len());
+
+        let g = Grammar::from_bnf("S -> a S |\n").unwrap();
+        assert!(g.to_string().ends_with("  1: S -> ε\n"));
+    }
+
+    #[test]
+    fn test_action() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        // 读入 E 后的状态在 # 上接受
+        let s_e = goto[0]["E"].parse::<usize>().unwrap();
+        assert!(matches!(action[s_e]["#"], Action::Accept));
+        assert!(matches!(action[0]["id"], Action::Shift(_)));
+        assert_eq!(action[0]["+"], Action::Error);
+
+        // 显示的字符串与解析互逆
+        for a in [
+            Action::Shift(5),
+            Action::Reduce(3),
+            Action::Accept,
+            Action::Error,
+        ] {
+            assert_eq!(a.to_string().parse::<Action>(), Ok(a));
+        }
+        assert_eq!(Action::Error.to_string(), "");
+        assert!("s".parse::<Action>().is_err());
+        assert!("x1".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn test_get_slr1_table_augmented() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (outreach_g, action, goto) = get_slr1_table_augmented(&g).unwrap();
+        assert_eq!((action.clone(), goto.clone()), get_slr1_table(&g).unwrap());
+        assert_eq!(outreach_g.s, "E'");
+
+        // 每个规约动作的产生式都在拓广文法中，且与原文法中同一序号的产生式相同
+        for row in action.iter() {
+            for a in row.values() {
+                if let Action::Reduce(k) = *a {
+                    assert!(k < outreach_g.p.len());
+                    assert_ne!(outreach_g.p[k].left, outreach_g.s);
+                    assert_eq!(outreach_g.p[k], g.p[k]);
+                }
+            }
+        }
+
+        let input = "a + b * ( c + d )".to_string();
+        let (tokens, _) = lexical_analysis(input).unwrap();
+        assert!(slr1_analysis(&outreach_g, &action, &goto, tokens.clone()));
+        assert!(slr1_analysis(&g, &action, &goto, tokens.clone()));
+
+        // 传入G'时不再次拓广，出错时报告的正在分析的产生式与传入G时相同
+        let error_tokens = lexical_analysis("a + + b".to_string()).unwrap().0;
+        let in_progress = |g: &Grammar| {
+            let options = AnalysisOptions::default();
+            let (_, errors) =
+                slr1_analysis_with_options(g, &action, &goto, error_tokens.clone(), &options);
+            errors[0]
+                .in_progress
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(in_progress(&g), vec!["E -> E + ·T"]);
+        assert_eq!(in_progress(&outreach_g), vec!["E -> E + ·T"]);
+
+        // 产生式集与分析表不一致时报告错误而不是越界
+        let mut wrong = g.clone();
+        wrong.p.truncate(2);
+        let (result, errors) =
+            slr1_analysis_with_options(&wrong, &action, &goto, tokens, &AnalysisOptions::default());
+        assert!(!result);
+        assert!(errors[0].message.starts_with("reduce by production"));
+    }
+
+    #[test]
+    fn test_get_slr1_table_strict() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let tables = get_slr1_table_strict(&g).unwrap();
+        assert_eq!(tables, get_slr1_table(&g).unwrap());
+
+        // 悬空else文法存在移进-归约冲突
+        let g = Grammar::from_bnf("S -> if E then S else S | if E then S | other\nE -> cond\n")
+            .unwrap();
+        let e = get_slr1_table_strict(&g).unwrap_err();
+        assert!(e.message.starts_with("SLR action conflict: ACTION["));
+        assert!(e.message.contains("\"else\""));
+        assert!(get_slr1_table(&g).is_ok());
+    }
+
+    #[test]
+    fn test_closure() {
+        let g = augment(&Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap());
+        let item = |left: &str, right: &[&str], dot: usize| Item {
+            left: s!(left),
+            right: right.iter().map(|s| s.to_string()).collect(),
+            dot,
+        };
+
+        let items = closure(&[item("E'", &["E"], 0)], &g);
+        let expected = vec![
+            item("E'", &["E"], 0),
+            item("E", &["E", "+", "T"], 0),
+            item("E", &["T"], 0),
+            item("T", &["T", "*", "F"], 0),
+            item("T", &["F"], 0),
+            item("F", &["(", "E", ")"], 0),
+            item("F", &["id"], 0),
+        ];
+        assert!(items_eq(&items, &expected));
+        assert_eq!(items[0], expected[0]);
+
+        // 圆点之后为终结符时不展开
+        let items = closure(&[item("E", &["E", "+", "T"], 1)], &g);
+        assert_eq!(items, vec![item("E", &["E", "+", "T"], 1)]);
+
+        let j = goto(&expected, "T", &g);
+        assert!(items_eq(
+            &j,
+            &[item("E", &["T"], 1), item("T", &["T", "*", "F"], 1)]
+        ));
+        assert!(goto(&expected, "+", &g).is_empty());
+    }
+
+    #[test]
+    fn test_first_follow_many_nonterminals() {
+        // N_i -> N_{i+1} t_i | ε，N_{n-1} -> t_{n-1}
+        // 产生式顺序使得每轮迭代只能向前传播一层，共需要 n 轮迭代
+        let n = 100;
+        let v = (0..n).map(|i| format!("N{}", i)).collect::<Vec<_>>();
+        let t = (0..n).map(|i| format!("t{}", i)).collect::<Vec<_>>();
+        let mut p = vec![];
+        for i in 0..n - 1 {
+            p.push(Product {
+                left: v[i].clone(),
+                right: vec![v[i + 1].clone(), t[i].clone()],
+            });
+            p.push(Product {
+                left: v[i].clone(),
+                right: vec![],
+            });
+        }
+        p.push(Product {
+            left: v[n - 1].clone(),
+            right: vec![t[n - 1].clone()],
+        });
+        let g = Grammar {
+            s: v[0].clone(),
+            v: v.clone(),
+            t: t.clone(),
+            p,
+            token_terminals: HashMap::new(),
+            terminal_labels: HashMap::new(),
+            column_order: vec![],
+        };
+
+        let first = get_first(&g);
+        let follow = get_follow(&g);
+
+        // N_{n-1} 不能推出ε，FIRST(N_0) 包含除 t_{n-2} 外的全部终结符与ε
+        assert_eq!(first["N0"].len(), n);
+        assert!(!first["N0"].contains(&t[n - 2]));
+        assert_eq!(first[&v[n - 1]], vec![t[n - 1].clone()]);
+        assert_eq!(follow["N0"], vec![s!("#")]);
+        assert_eq!(follow[&v[n - 1]], vec![t[n - 2].clone()]);
+    }
+
+    #[test]
+    fn test_max_errors() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        // 每个多余的 `)` 都是一个独立的错误
+        let tokens = lexical_analysis("a ) + b ) + c ) + d ) + e )".to_string())
+            .unwrap()
+            .0;
+        let options = AnalysisOptions {
+            recover: true,
+            max_errors: 3,
+            ..Default::default()
+        };
+        let (success, errors) = slr1_analysis_with_options(&g, &action, &goto, tokens, &options);
+        assert!(!success);
+        assert_eq!(errors.len(), 3);
+
+        // 不限制错误数时能收集到全部五个错误
+        let tokens = lexical_analysis("a ) + b ) + c ) + d ) + e )".to_string())
+            .unwrap()
+            .0;
+        let options = AnalysisOptions {
+            recover: true,
+            max_errors: usize::MAX,
+            ..Default::default()
+        };
+        let (success, errors) = slr1_analysis_with_options(&g, &action, &goto, tokens, &options);
+        assert!(!success);
+        assert_eq!(errors.len(), 5);
+    }
+
+    #[test]
+    fn test_panic_mode() {
+        let yml = r#"
+        s: L
+        v:
+          - L
+          - S
+          - E
+        t:
+          - id
+          - =
+          - +
+          - ;
+        p:
+          - left: L
+            right:
+              - L
+              - S
+          - left: L
+            right:
+              - S
+          - left: S
+            right:
+              - id
+              - =
+              - E
+              - ;
+          - left: E
+            right:
+              - E
+              - +
+              - id
+          - left: E
+            right:
+              - id
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let options = AnalysisOptions {
+            recover: true,
+            sync: Some("S".to_string()),
+            max_errors: usize::MAX,
+            ..Default::default()
+        };
+
+        // 两条语句中各有一个错误，同步到下一条语句后继续分析
+        let source = "a = + ; b = c ; d = = ; e = f + g ;";
+        let tokens = lexical_analysis(source.to_string()).unwrap().0;
+        let (success, errors) = slr1_analysis_with_options(&g, &action, &goto, tokens, &options);
+        assert!(!success);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].to_string().starts_with("unexpected `+`"));
+        assert!(errors[1].to_string().starts_with("unexpected `=`"));
+
+        let tokens = lexical_analysis("a = b ; c = d ;".to_string()).unwrap().0;
+        let (success, errors) = slr1_analysis_with_options(&g, &action, &goto, tokens, &options);
+        assert!(success);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_keyword_terminal() {
+        let yml = r#"
+        s: L
+        v:
+          - L
+          - S
+        t:
+          - keyword
+          - id
+          - ;
+        p:
+          - left: L
+            right:
+              - L
+              - S
+          - left: L
+            right:
+              - S
+          - left: S
+            right:
+              - keyword
+              - id
+              - ;
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        g.validate().unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        let options = AnalysisOptions {
+            keyword_terminal: Some("keyword".to_string()),
+            ..Default::default()
+        };
+        let tokens = lexical_analysis("int a; while b;".to_string()).unwrap().0;
+        let (success, errors) = slr1_analysis_with_options(&g, &action, &goto, tokens, &options);
+        assert!(success);
+        assert!(errors.is_empty());
+
+        // 不映射关键字时，`int` 不是文法中的终结符
+        let tokens = lexical_analysis("int a; while b;".to_string()).unwrap().0;
+        assert!(!slr1_analysis(&g, &action, &goto, tokens));
+    }
+
+    #[test]
+    fn test_lr0_item() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let lr0_items = get_lr0_collection(&augment(&g));
+        assert_eq!(lr0_items.len(), 12);
+        assert_eq!(lr0_items[0][0].to_string(), "E' -> ·E");
+
+        // 多次求解得到相同的项目集族，项目集中的项目按规范顺序排列
+        for _ in 0..5 {
+            assert_eq!(get_lr0_collection(&augment(&g)), lr0_items);
+        }
+        let state0 = lr0_items[0]
+            .iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            state0,
+            vec![
+                "E' -> ·E",
+                "E -> ·E + T",
+                "E -> ·T",
+                "T -> ·T * F",
+                "T -> ·F",
+                "F -> ·( E )",
+                "F -> ·id"
+            ]
+        );
+        // 核心项目在前
+        let state = lr0_items
+            .iter()
+            .find(|items| items[0].to_string() == "E -> T ·")
+            .unwrap();
+        assert_eq!(state[1].to_string(), "T -> T ·* F");
+
+        let item = Item {
+            left: s!("E"),
+            right: vec![s!("E"), s!("+"), s!("T")],
+            dot: 2,
+        };
+        assert_eq!(item.to_string(), "E -> E + ·T");
+        let item = Item { dot: 3, ..item };
+        assert_eq!(item.to_string(), "E -> E + T ·");
+        let item = Item {
+            left: s!("A"),
+            right: vec![],
+            dot: 0,
+        };
+        assert_eq!(item.to_string(), "A -> ·");
+
+        let yml = serde_yaml::to_string(&lr0_items[1]).unwrap();
+        assert!(yml.contains("left: E'"));
+        assert!(yml.contains("dot: 1"));
+    }
+
+    #[test]
+    fn test_slr1_parse_with_actions() {
+        let mut g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        g.token_terminals.insert(s!("Constant"), s!("id"));
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        // E -> E + T, T -> T * F, F -> ( E )，其余产生式只有一个符号，直接传递值
+        let mut actions: HashMap<usize, SemanticAction<i64>> = HashMap::new();
+        actions.insert(0, Box::new(|v| v[0] + v[2]));
+        actions.insert(2, Box::new(|v| v[0] * v[2]));
+        actions.insert(4, Box::new(|v| v[1]));
+        let leaf = |token: &Token| token.token_value.parse::<i64>().unwrap_or(0);
+
+        let eval = |input: &str| {
+            let tokens = lexical_analysis(input.to_string()).unwrap().0;
+            slr1_parse_with_actions(&g, &action, &goto, tokens, &actions, leaf)
+        };
+        assert_eq!(eval("2 + 3 * 4").unwrap(), 14);
+        assert_eq!(eval("(2 + 3) * 4").unwrap(), 20);
+        assert_eq!(eval("7").unwrap(), 7);
+        assert!(eval("2 + * 4").is_err());
+
+        // 右部有多个符号的产生式缺少语义动作
+        actions.remove(&2);
+        let tokens = lexical_analysis("2 * 3".to_string()).unwrap().0;
+        let error = slr1_parse_with_actions(&g, &action, &goto, tokens, &actions, leaf);
+        assert!(error.unwrap_err().message.contains("production 2"));
+    }
+
+    #[test]
+    fn test_combined_goto_table() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let table = combined_goto_table(&action, &goto);
+        assert_eq!(table.len(), action.len());
+
+        // 状态0在终结符上移进，在非终结符上GOTO
+        assert_eq!(action[0]["id"].to_string(), format!("s{}", table[0]["id"]));
+        assert_eq!(action[0]["("].to_string(), format!("s{}", table[0]["("]));
+        assert_eq!(table[0]["E"], goto[0]["E"]);
+        assert_eq!(table[0]["E"], "1");
+        assert_eq!(table[0].len(), 5);
+
+        // 规约与接受不是状态转移
+        assert!(!table[1].contains_key("#"));
+        assert_eq!(table[1].keys().collect::<Vec<_>>(), vec!["+"]);
+    }
+
+    #[test]
+    fn test_unshifted_tokens() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let unshifted = |input: &str, options: &AnalysisOptions| {
+            let tokens = lexical_analysis(input.to_string()).unwrap().0;
+            unshifted_tokens(&g, &action, &goto, tokens, options)
+                .into_iter()
+                .map(|token| token.token_value)
+                .collect::<Vec<_>>()
+        };
+        let recover = AnalysisOptions {
+            recover: true,
+            max_errors: 10,
+            ..Default::default()
+        };
+
+        assert!(unshifted("a + b * c", &recover).is_empty());
+        // 错误恢复时跳过的token
+        assert_eq!(unshifted("a + * b", &recover), vec![s!("*")]);
+        assert_eq!(
+            unshifted("a + ) * b ( c", &recover),
+            vec![s!(")"), s!("*"), s!("("), s!("c")]
+        );
+        let tokens = lexical_analysis("a + * b".to_string()).unwrap().0;
+        let (_, events) = slr1_analysis_events(&g, &action, &goto, tokens, &recover);
+        assert!(events.contains(&ParseEvent::Skip { token: s!("*") }));
+
+        // 不恢复时分析停止后的token都没有被移进
+        let default = AnalysisOptions::default();
+        assert_eq!(unshifted("a + ) b", &default), vec![s!(")"), s!("b")]);
+        // 被忽略的剩余输入
+        let lenient = AnalysisOptions {
+            strict_eof: false,
+            ..Default::default()
+        };
+        assert_eq!(unshifted("a b c", &lenient), vec![s!("b"), s!("c")]);
+    }
+
+    #[test]
+    fn test_stack_evolution() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        let tokens = lexical_analysis("a + b".to_string()).unwrap().0;
+        let snapshots = stack_evolution(&g, &action, &goto, tokens.clone());
+        // 3次移进、6次规约和1次接受
+        let (_, events) =
+            slr1_analysis_events(&g, &action, &goto, tokens, &AnalysisOptions::default());
+        let steps = events
+            .iter()
+            .filter(|e| !matches!(e, ParseEvent::Goto { .. }))
+            .count();
+        assert_eq!(steps, 10);
+        assert_eq!(snapshots.len(), steps);
+        assert_eq!(snapshots[0], (vec![0], vec![s!("#")]));
+        assert_eq!(snapshots[1].1, vec![s!("#"), s!("id")]);
+        assert_eq!(snapshots[2].1, vec![s!("#"), s!("F")]);
+        assert_eq!(
+            snapshots.last().unwrap(),
+            &(vec![0, 1], vec![s!("#"), s!("E")])
+        );
+
+        // 出错时记录到出错的一步为止
+        let tokens = lexical_analysis("a + )".to_string()).unwrap().0;
+        let snapshots = stack_evolution(&g, &action, &goto, tokens);
+        assert_eq!(snapshots.last().unwrap().1, vec![s!("#"), s!("E"), s!("+")]);
+    }
+
+    #[test]
+    fn test_slr1_rightmost_derivation() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        let tokens = lexical_analysis("a+b*c".to_string()).unwrap().0;
+        let reductions = slr1_rightmost_derivation(&g, &action, &goto, tokens).unwrap();
+        // F -> id, T -> F, E -> T, F -> id, T -> F, F -> id, T -> T * F, E -> E + T
+        assert_eq!(reductions, vec![5, 3, 1, 5, 3, 5, 2, 0]);
+
+        // 逆序应用产生式替换最右的非终结符，得到输入串
+        let mut form = vec![g.s.clone()];
+        for k in reductions.iter().rev() {
+            let i = form.iter().rposition(|s| g.v.contains(s)).unwrap();
+            assert_eq!(form[i], g.p[*k].left);
+            form.splice(i..=i, g.p[*k].right.iter().cloned());
+        }
+        assert_eq!(form, vec!["id", "+", "id", "*", "id"]);
+
+        let tokens = lexical_analysis("a+".to_string()).unwrap().0;
+        assert!(slr1_rightmost_derivation(&g, &action, &goto, tokens).is_err());
+    }
+
+    #[test]
+    fn test_merge_compatible_states() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        // LR(0)项目集族中没有可以合并的状态
+        assert_eq!(
+            merge_compatible_states(&action, &goto),
+            (action.clone(), goto.clone())
+        );
+
+        // 复制移进 ( 后到达的状态，使状态0移进 ( 时转移到副本，再加入一个不可达的状态
+        let Action::Shift(j) = action[0]["("] else {
+            panic!("state 0 should shift (");
+        };
+        let (mut redundant_action, mut redundant_goto) = (action.clone(), goto.clone());
+        redundant_action.push(action[j].clone());
+        redundant_goto.push(goto[j].clone());
+        redundant_action[0].insert(s!("("), Action::Shift(action.len()));
+        redundant_action.push(action[1].clone());
+        redundant_goto.push(goto[1].clone());
+
+        let (merged_action, merged_goto) =
+            merge_compatible_states(&redundant_action, &redundant_goto);
+        assert_eq!((&merged_action, &merged_goto), (&action, &goto));
+
+        for input in ["a", "a+b*c", "(a+b)*c", "((a))", "a+", "(a", "a b", ")"] {
+            let tokens = lexical_analysis(input.to_string()).unwrap().0;
+            let expected = slr1_analysis(&g, &action, &goto, tokens.clone());
+            assert_eq!(
+                slr1_analysis(&g, &redundant_action, &redundant_goto, tokens.clone()),
+                expected
+            );
+            assert_eq!(
+                slr1_analysis(&g, &merged_action, &merged_goto, tokens),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_derivation_steps() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        let tokens = lexical_analysis("a+b".to_string()).unwrap().0;
+        let steps = derivation_steps(&g, &action, &goto, tokens).unwrap();
+        // F -> id, T -> F, E -> T, F -> id, T -> F, E -> E + T
+        let reductions = steps
+            .iter()
+            .filter_map(|step| match step.action {
+                StepAction::Reduce(k) => Some(k),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(reductions, vec![5, 3, 1, 5, 3, 0]);
+        assert_eq!(steps.len(), 10);
+
+        assert_eq!(steps[0].state_stack, vec![0]);
+        assert_eq!(steps[0].input, vec![s!("id"), s!("+"), s!("id"), s!("#")]);
+        assert!(matches!(steps[0].action, StepAction::Shift(_)));
+        let last = steps.last().unwrap();
+        assert_eq!(last.action, StepAction::Accept);
+        assert_eq!(last.symbol_stack, vec![s!("#"), s!("E")]);
+        assert_eq!(last.input, vec![s!("#")]);
+
+        let tokens = lexical_analysis("a+".to_string()).unwrap().0;
+        let error = derivation_steps(&g, &action, &goto, tokens).unwrap_err();
+        assert!(error.to_string().starts_with("unexpected `#`"));
+    }
+
+    #[test]
+    fn test_in_progress_productions() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        // 在 E -> E + ·T 中遇到 )
+        let tokens = lexical_analysis("a + )".to_string()).unwrap().0;
+        let (_, errors) =
+            slr1_analysis_with_options(&g, &action, &goto, tokens, &AnalysisOptions::default());
+        assert_eq!(
+            errors[0].in_progress,
+            vec![Item {
+                left: s!("E"),
+                right: vec![s!("E"), s!("+"), s!("T")],
+                dot: 2,
+            }]
+        );
+
+        // 在 F -> ( E · ) 和 E -> E · + T 中遇到 #
+        let tokens = lexical_analysis("(a".to_string()).unwrap().0;
+        let (_, errors) =
+            slr1_analysis_with_options(&g, &action, &goto, tokens, &AnalysisOptions::default());
+        let mut in_progress = errors[0]
+            .in_progress
+            .iter()
+            .map(|item| (item.left.as_str(), item.dot))
+            .collect::<Vec<_>>();
+        in_progress.sort();
+        assert_eq!(in_progress, vec![("E", 1), ("F", 2)]);
+    }
+
+    #[test]
+    fn test_in_progress_lalr_table() {
+        let g = Grammar::from_bnf("S -> L = R | R\nL -> * R | id\nR -> L\n").unwrap();
+        let (action, goto) = get_lalr1_table(&g).unwrap();
+        assert_eq!(action.len(), get_lr0_collection(&augment(&g)).len());
+
+        // LALR(1)分析表的状态数与LR(0)项目集族相同但编号不同，不能报告其他状态的项目
+        let tokens = lexical_analysis("a = = b".to_string()).unwrap().0;
+        let (_, errors) =
+            slr1_analysis_with_options(&g, &action, &goto, tokens, &AnalysisOptions::default());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].in_progress.is_empty());
+    }
+
+    #[test]
+    fn test_terminal_matcher() {
+        let g = Grammar::from_bnf("L -> L S | S\nS -> kw id ;\n").unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        // 任何关键字都匹配终结符 kw，其余token按默认方式映射
+        let options = AnalysisOptions {
+            matcher: Some(TerminalMatcher::new(|token, terminal| {
+                matches!(token.token_type, TokenType::Keyword) && terminal == "kw"
+            })),
+            ..Default::default()
+        };
+        let tokens = lexical_analysis("int a; while b; return c;".to_string())
+            .unwrap()
+            .0;
+        let (success, errors) = slr1_analysis_with_options(&g, &action, &goto, tokens, &options);
+        assert!(success);
+        assert!(errors.is_empty());
+
+        // 默认不使用匹配函数
+        let tokens = lexical_analysis("int a;".to_string()).unwrap().0;
+        let (success, errors) =
+            slr1_analysis_with_options(&g, &action, &goto, tokens, &AnalysisOptions::default());
+        assert!(!success);
+        assert!(errors[0].to_string().starts_with("unexpected `int`"));
+
+        // 匹配函数也可以不区分大小写
+        let g = Grammar::from_bnf("S -> BEGIN id END\n").unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let options = AnalysisOptions {
+            matcher: Some(TerminalMatcher::new(|token, terminal| {
+                token.token_value.eq_ignore_ascii_case(terminal)
+            })),
+            ..Default::default()
+        };
+        let tokens = lexical_analysis("begin x end".to_string()).unwrap().0;
+        assert!(slr1_analysis_with_options(&g, &action, &goto, tokens, &options).0);
+    }
+
+    #[test]
+    fn test_strict_eof() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        let tokens = lexical_analysis("a + b c d".to_string()).unwrap().0;
+        let (success, errors) =
+            slr1_analysis_with_options(&g, &action, &goto, tokens, &AnalysisOptions::default());
+        assert!(!success);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "trailing input `id` after a complete sentence"
+        );
+
+        let options = AnalysisOptions {
+            strict_eof: false,
+            ..Default::default()
+        };
+        let tokens = lexical_analysis("a + b c d".to_string()).unwrap().0;
+        let (success, errors) = slr1_analysis_with_options(&g, &action, &goto, tokens, &options);
+        assert!(success);
+        assert!(errors.is_empty());
+
+        // 未构成完整句子时的错误不受影响
+        let tokens = lexical_analysis("a + ) b".to_string()).unwrap().0;
+        let (success, errors) = slr1_analysis_with_options(&g, &action, &goto, tokens, &options);
+        assert!(!success);
+        assert_eq!(
+            errors[0].to_string(),
+            "unexpected `)` in state 6, expected one of `(`, `id`"
+        );
+        assert_eq!(errors[0].expected, vec![s!("("), s!("id")]);
+    }
+
+    #[test]
+    fn test_table_metrics() {
+        let g = Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap();
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let metrics = table_metrics(&action, &goto);
+        assert_eq!(metrics.states, 12);
+        assert_eq!(metrics.action_entries, 36);
+        assert_eq!(metrics.goto_entries, 9);
+        // 12个状态，ACTION表6列，GOTO表3列
+        assert!((metrics.density - 45.0 / 108.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parser_first_follow() {
+        let parser = Parser::new(Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap()).unwrap();
+        assert_eq!(parser.first("E"), Some(&[s!("("), s!("id")][..]));
+        assert_eq!(parser.first("+"), Some(&[s!("+")][..]));
+        assert_eq!(parser.first("X"), None);
+        assert_eq!(
+            parser.follow("T"),
+            Some(&[s!("#"), s!(")"), s!("*"), s!("+")][..])
+        );
+        assert_eq!(parser.follow("id"), None);
+
+        let tokens = lexical_analysis("a * (b + c)".to_string()).unwrap().0;
+        assert!(parser.parse(tokens));
+    }
+
+    #[test]
+    fn test_parse_verbose() {
+        let parser = Parser::new(Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap()).unwrap();
+
+        let tokens = lexical_analysis("a * (b + c)".to_string()).unwrap().0;
+        let (result, warnings) = parse_verbose(&parser, tokens);
+        assert!(result.unwrap());
+        assert!(warnings.is_empty());
+
+        // 多余的 `)` 被跳过后仍能接受，错误作为警告返回
+        let tokens = lexical_analysis("a ) + b ) + c".to_string()).unwrap().0;
+        let (result, warnings) = parse_verbose(&parser, tokens);
+        assert!(!result.unwrap());
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("recovered from syntax error: unexpected `)`"));
+
+        // 输入不完整时无法恢复
+        let tokens = lexical_analysis("a ) + ".to_string()).unwrap().0;
+        let (result, warnings) = parse_verbose(&parser, tokens);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("unexpected `#`"));
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let parser = Parser::new(Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap()).unwrap();
+        let tokens = lexical_analysis("a + b".to_string()).unwrap().0;
+        let (success, events) = parser.parse_events(tokens);
+        assert!(success);
+
+        // 从分析表中查出各步转移到的状态
+        let goto = |from: usize, v: &str| parser.goto[from][v].parse::<usize>().unwrap();
+        let shift = |from: usize, t: &str| match parser.action[from][t] {
+            Action::Shift(j) => j,
+            a => panic!("ACTION[{},{}] = {} is not a shift", from, t, a),
+        };
+        let (s_e, s_plus) = (goto(0, "E"), shift(goto(0, "E"), "+"));
+        let expected = vec![
+            ParseEvent::Shift {
+                state: shift(0, "id"),
+                token: s!("id"),
+            },
+            ParseEvent::Reduce { production: 5 },
+            ParseEvent::Goto {
+                state: goto(0, "F"),
+            },
+            ParseEvent::Reduce { production: 3 },
+            ParseEvent::Goto {
+                state: goto(0, "T"),
+            },
+            ParseEvent::Reduce { production: 1 },
+            ParseEvent::Goto { state: s_e },
+            ParseEvent::Shift {
+                state: s_plus,
+                token: s!("+"),
+            },
+            ParseEvent::Shift {
+                state: shift(s_plus, "id"),
+                token: s!("id"),
+            },
+            ParseEvent::Reduce { production: 5 },
+            ParseEvent::Goto {
+                state: goto(s_plus, "F"),
+            },
+            ParseEvent::Reduce { production: 3 },
+            ParseEvent::Goto {
+                state: goto(s_plus, "T"),
+            },
+            ParseEvent::Reduce { production: 0 },
+            ParseEvent::Goto { state: s_e },
+            ParseEvent::Accept,
+        ];
+        assert_eq!(events, expected);
+
+        let tokens = lexical_analysis("a + )".to_string()).unwrap().0;
+        let (success, events) = parser.parse_events(tokens);
+        assert!(!success);
+        assert_eq!(
+            events.last(),
+            Some(&ParseEvent::Error {
+                state: s_plus,
+                token: s!(")")
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_termination() {
+        let mut parser = Parser::new(Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap()).unwrap();
+        assert_eq!(verify_termination(&parser, 4), Ok(()));
+
+        // 在读入 T 后的状态遇到 + 时按 E -> T 规约，转移到读入 E 后的状态，
+        // 将该状态遇到 + 时的动作改为按 T -> F 规约，两次规约构成不读入符号的循环
+        let s_t = parser.goto[0]["T"].parse::<usize>().unwrap();
+        let s_e = parser.goto[0]["E"].parse::<usize>().unwrap();
+        assert_eq!(parser.action[s_t]["+"], Action::Reduce(1));
+        parser.action[s_e].insert(s!("+"), Action::Reduce(3));
+        let result = verify_termination(&parser, 2);
+        assert!(result.unwrap_err().contains("no decision within"));
+
+        // 引用不存在的状态
+        parser.action[0].insert(s!("id"), Action::Shift(99));
+        let result = verify_termination(&parser, 1);
+        assert!(result
+            .unwrap_err()
+            .contains("state 99 is not in the ACTION table"));
+    }
+
+    #[test]
+    fn test_parse_longest_prefix() {
+        let parser = Parser::new(Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap()).unwrap();
+        let prefix = |source: &str| {
+            let tokens = lexical_analysis(source.to_string()).unwrap().0;
+            parse_longest_prefix(&parser, &tokens)
+        };
+
+        // 整个输入都是句子的前缀，但不完整
+        assert_eq!(prefix("a + b +"), (4, false));
+        assert_eq!(prefix("a + b"), (3, true));
+        assert_eq!(prefix("a + b + ) c"), (4, false));
+        assert_eq!(prefix(") a"), (0, false));
+        assert_eq!(prefix(""), (0, false));
+    }
+
+    #[test]
+    fn test_completion_context() {
+        let parser = Parser::new(Grammar::from_yml(EXPR_GRAMMAR_YML).unwrap()).unwrap();
+
+        let consumed = lexical_analysis("a +".to_string()).unwrap().0;
+        let context = completion_context(&parser, &consumed);
+        assert!(context.valid_prefix);
+        assert!(!context.complete);
+        // E -> E + ·T 之后可以开始一个 T，进而开始 F
+        assert_eq!(
+            context.suggestions,
+            vec![
+                Suggestion {
+                    terminal: s!("("),
+                    starts: vec![s!("T"), s!("F")],
+                },
+                Suggestion {
+                    terminal: s!("id"),
+                    starts: vec![s!("T"), s!("F")],
+                },
+            ]
+        );
+
+        // 读入 a 后可以继续当前的表达式，也可以结束
+        let consumed = lexical_analysis("a".to_string()).unwrap().0;
+        let context = completion_context(&parser, &consumed);
+        assert!(context.complete);
+        let terminals = context
+            .suggestions
+            .iter()
+            .map(|s| (s.terminal.as_str(), s.starts.is_empty()))
+            .collect::<Vec<_>>();
+        assert_eq!(terminals, vec![("+", true), ("*", true)]);
+
+        let consumed = lexical_analysis("a + )".to_string()).unwrap().0;
+        let context = completion_context(&parser, &consumed);
+        assert!(!context.valid_prefix);
+        assert!(context.suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_token_terminals() {
+        let yml = r#"
+        s: S
+        v:
+          - S
+        t:
+          - ident
+          - num
+          - =
+          - ;
+        p:
+          - left: S
+            right:
+              - ident
+              - =
+              - num
+              - ;
+        token_terminals:
+          Identifier: ident
+          Constant: num
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        g.validate().unwrap();
+        assert_eq!(Grammar::from_yml(&g.to_yml().unwrap()).unwrap(), g);
+
+        let (action, goto) = get_slr1_table(&g).unwrap();
+        let tokens = lexical_analysis("x = 42;".to_string()).unwrap().0;
+        assert!(slr1_analysis(&g, &action, &goto, tokens));
+
+        // 没有映射时标识符仍然映射为 id
+        let mut g = g;
+        g.token_terminals.clear();
+        let tokens = lexical_analysis("x = 42;".to_string()).unwrap().0;
+        assert!(!slr1_analysis(&g, &action, &goto, tokens));
+    }
+
+    #[test]
+    fn test_terminal_labels() {
+        let yml = r#"
+        s: S
+        v:
+          - S
+        t:
+          - id
+          - =
+          - value
+          - ;
+        p:
+          - left: S
+            right:
+              - id
+              - =
+              - value
+              - ;
+        terminal_labels:
+          ;: semicolon
+          value: constant
+        "#;
+        let g = Grammar::from_yml(yml).unwrap();
+        assert_eq!(Grammar::from_yml(&g.to_yml().unwrap()).unwrap(), g);
+        let (action, goto) = get_slr1_table(&g).unwrap();
+
+        let tokens = lexical_analysis("x = 42".to_string()).unwrap().0;
+        let (_, errors) =
+            slr1_analysis_with_options(&g, &action, &goto, tokens, &AnalysisOptions::default());
+        assert_eq!(errors[0].expected, vec![s!("semicolon")]);
+
+        let tokens = lexical_analysis("x = ;".to_string()).unwrap().0;
+        let (_, errors) =
+            slr1_analysis_with_options(&g, &action, &goto, tokens, &AnalysisOptions::default());
+        assert_eq!(errors[0].expected, vec![s!("constant")]);
+
+        // 没有声明名字的终结符以其本身显示
+        let tokens = lexical_analysis("x 42;".to_string()).unwrap().0;
+        let (_, errors) =
+            slr1_analysis_with_options(&g, &action, &goto, tokens, &AnalysisOptions::default());
+        assert_eq!(errors[0].expected, vec![s!("=")]);
+    }
+
     const PROGRAM: &str = r#"
     // This is a note.
     int main(int a, int b){